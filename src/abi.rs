@@ -0,0 +1,733 @@
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use bevy::reflect::TypePath;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use starknet::core::{
+    types::{
+        Call, Felt,
+        contract::{AbiEntry, AbiNamedMember},
+    },
+    utils::get_selector_from_name,
+};
+
+use crate::starknet::{StarknetConnection, SubmitOutcome};
+
+/// Error returned when a call built from an `AbiBinding` doesn't match the ABI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallValidationError {
+    /// The entrypoint is not part of the bound ABI
+    UnknownEntrypoint { entrypoint: String },
+    /// The entrypoint name can't be turned into a selector
+    InvalidEntrypointName { entrypoint: String },
+    /// The calldata doesn't have as many felts as the entrypoint's inputs serialize to
+    ArgumentCountMismatch {
+        entrypoint: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The calldata felt at `index` is out of the range of its Cairo type `ty`
+    ArgumentOutOfRange {
+        entrypoint: String,
+        index: usize,
+        ty: String,
+    },
+    /// The `ContractAbi` asset is not loaded yet
+    AbiNotLoaded,
+}
+
+impl fmt::Display for CallValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallValidationError::UnknownEntrypoint { entrypoint } => {
+                write!(f, "unknown entrypoint `{entrypoint}`")
+            }
+            CallValidationError::InvalidEntrypointName { entrypoint } => {
+                write!(f, "invalid entrypoint name `{entrypoint}`")
+            }
+            CallValidationError::ArgumentCountMismatch {
+                entrypoint,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "entrypoint `{entrypoint}` expects {expected} calldata felts, got {actual}"
+            ),
+            CallValidationError::ArgumentOutOfRange {
+                entrypoint,
+                index,
+                ty,
+            } => write!(
+                f,
+                "calldata felt {index} of entrypoint `{entrypoint}` is out of range for `{ty}`"
+            ),
+            CallValidationError::AbiNotLoaded => write!(f, "contract ABI is not loaded"),
+        }
+    }
+}
+
+impl std::error::Error for CallValidationError {}
+
+/// An entrypoint known from an ABI
+#[derive(Debug, Clone)]
+pub struct BoundEntrypoint {
+    pub selector: Felt,
+    /// Cairo types of the entrypoint's inputs, in order
+    pub inputs: Vec<String>,
+    /// Number of calldata felts the inputs serialize to, if it is fixed
+    pub calldata_len: Option<usize>,
+}
+
+/// Binding of a deployed contract to the entrypoints of its ABI
+///
+/// Calls built through a binding are checked against the number of calldata
+/// felts the entrypoint's inputs serialize to. This distinguishes a call that
+/// genuinely takes no arguments from one whose arguments were lost or
+/// mis-serialized. Entrypoints with variable-length inputs (arrays, spans,
+/// byte arrays, enums) are not checked.
+///
+/// By default a mismatch is logged as a warning and the call is still built.
+/// In strict mode it is returned as an error instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::abi::AbiBinding;
+///
+/// let counter = AbiBinding::new(contract_address)
+///     .with_entrypoint("increment", &[])
+///     .with_entrypoint("set", &["core::integer::u256"])
+///     .strict(true);
+///
+/// // Fails: `set` expects the two felts of a u256
+/// assert!(counter.call("set", vec![Felt::ONE]).is_err());
+/// let call = counter.call("set", vec![Felt::ONE, Felt::ZERO]).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct AbiBinding {
+    pub address: Felt,
+    entrypoints: HashMap<String, BoundEntrypoint>,
+    structs: HashMap<String, Vec<String>>,
+    strict: bool,
+}
+
+impl AbiBinding {
+    /// Create an empty binding for the contract at `address`
+    pub fn new(address: Felt) -> Self {
+        Self {
+            address,
+            entrypoints: HashMap::new(),
+            structs: HashMap::new(),
+            strict: false,
+        }
+    }
+
+    /// Create a binding from the entries of a Sierra contract ABI
+    pub fn from_abi(address: Felt, abi: &[AbiEntry]) -> Self {
+        let mut binding = Self::new(address);
+        let mut functions = Vec::new();
+        binding.collect_entries(abi, &mut functions);
+        for (name, inputs) in functions {
+            let inputs = inputs
+                .iter()
+                .map(|input| input.r#type.as_str())
+                .collect::<Vec<_>>();
+            binding = binding.with_entrypoint(&name, &inputs);
+        }
+        binding
+    }
+
+    fn collect_entries(
+        &mut self,
+        entries: &[AbiEntry],
+        functions: &mut Vec<(String, Vec<AbiNamedMember>)>,
+    ) {
+        for entry in entries {
+            match entry {
+                AbiEntry::Function(function) => {
+                    functions.push((function.name.clone(), function.inputs.clone()));
+                }
+                AbiEntry::Interface(interface) => self.collect_entries(&interface.items, functions),
+                AbiEntry::Struct(item) => {
+                    let members = item.members.iter().map(|m| m.r#type.clone()).collect();
+                    self.structs.insert(item.name.clone(), members);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Register an entrypoint with the Cairo types of its inputs
+    ///
+    /// Entrypoints whose name can't be turned into a selector are ignored.
+    pub fn with_entrypoint(mut self, name: &str, inputs: &[&str]) -> Self {
+        let Ok(selector) = get_selector_from_name(name) else {
+            warn!("Ignoring entrypoint with invalid name `{}`", name);
+            return self;
+        };
+        let calldata_len = inputs
+            .iter()
+            .map(|ty| self.serialized_len(ty))
+            .sum::<Option<usize>>();
+        self.entrypoints.insert(
+            name.to_string(),
+            BoundEntrypoint {
+                selector,
+                inputs: inputs.iter().map(|ty| ty.to_string()).collect(),
+                calldata_len,
+            },
+        );
+        self
+    }
+
+    /// Enable or disable strict mode
+    ///
+    /// In strict mode, calls that don't match the ABI are rejected instead of
+    /// only logging a warning.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns true if calls that don't match the ABI are rejected
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Returns the names of the bound entrypoints
+    pub fn entrypoint_names(&self) -> impl Iterator<Item = &str> {
+        self.entrypoints.keys().map(String::as_str)
+    }
+
+    /// Returns the bound entrypoint named `name`, if any
+    pub fn entrypoint(&self, name: &str) -> Option<&BoundEntrypoint> {
+        self.entrypoints.get(name)
+    }
+
+    /// Build a call to `entrypoint`, validating the calldata against the ABI
+    ///
+    /// # Arguments
+    ///
+    /// * `entrypoint` - The name of the entrypoint to call
+    /// * `calldata` - The serialized arguments
+    ///
+    /// # Returns
+    ///
+    /// The call, or a `CallValidationError` in strict mode if the entrypoint is
+    /// unknown or the calldata length doesn't match its inputs
+    pub fn call(&self, entrypoint: &str, calldata: Vec<Felt>) -> Result<Call, CallValidationError> {
+        self.call_to(self.address, entrypoint, calldata)
+    }
+
+    fn call_to(
+        &self,
+        to: Felt,
+        entrypoint: &str,
+        calldata: Vec<Felt>,
+    ) -> Result<Call, CallValidationError> {
+        let selector = match self.entrypoints.get(entrypoint) {
+            Some(bound) => {
+                if let Err(err) = Self::check_len(entrypoint, bound, &calldata) {
+                    if self.strict {
+                        return Err(err);
+                    }
+                    warn!("{}", err);
+                }
+                bound.selector
+            }
+            None => {
+                let err = CallValidationError::UnknownEntrypoint {
+                    entrypoint: entrypoint.to_string(),
+                };
+                if self.strict {
+                    return Err(err);
+                }
+                warn!("{}", err);
+                get_selector_from_name(entrypoint).map_err(|_| {
+                    CallValidationError::InvalidEntrypointName {
+                        entrypoint: entrypoint.to_string(),
+                    }
+                })?
+            }
+        };
+
+        Ok(Call {
+            to,
+            selector,
+            calldata,
+        })
+    }
+
+    /// Check an existing call against the ABI, including the range of each felt
+    ///
+    /// On top of the calldata length checked when building calls, each felt is
+    /// checked against the Cairo type it belongs to, so that a negative number
+    /// passed as a `u64` or a `u256` serialized as a single felt is caught. The
+    /// felts after the first variable-length input are not checked.
+    ///
+    /// # Returns
+    ///
+    /// `Ok` if the call matches the ABI, or a `CallValidationError` if its
+    /// selector is unknown, its calldata length doesn't match the inputs or a
+    /// felt is out of range, whether or not the binding is strict
+    pub fn validate(&self, call: &Call) -> Result<(), CallValidationError> {
+        let Some((entrypoint, bound)) = self
+            .entrypoints
+            .iter()
+            .find(|(_, bound)| bound.selector == call.selector)
+        else {
+            return Err(CallValidationError::UnknownEntrypoint {
+                entrypoint: format!("{:#x}", call.selector),
+            });
+        };
+        Self::check_len(entrypoint, bound, &call.calldata)?;
+
+        let mut types = Vec::new();
+        for input in &bound.inputs {
+            if self.felt_types(input, &mut types).is_none() {
+                break;
+            }
+        }
+        for (index, (ty, value)) in types.iter().zip(&call.calldata).enumerate() {
+            if !felt_fits(ty, value) {
+                return Err(CallValidationError::ArgumentOutOfRange {
+                    entrypoint: entrypoint.clone(),
+                    index,
+                    ty: ty.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_len(
+        entrypoint: &str,
+        bound: &BoundEntrypoint,
+        calldata: &[Felt],
+    ) -> Result<(), CallValidationError> {
+        match bound.calldata_len {
+            Some(expected) if expected != calldata.len() => {
+                Err(CallValidationError::ArgumentCountMismatch {
+                    entrypoint: entrypoint.to_string(),
+                    expected,
+                    actual: calldata.len(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Number of felts a value of the Cairo type `ty` serializes to, if fixed
+    fn serialized_len(&self, ty: &str) -> Option<usize> {
+        if let Some(members) = self.structs.get(ty) {
+            return members
+                .iter()
+                .map(|member| self.serialized_len(member))
+                .sum();
+        }
+        match ty.rsplit("::").next().unwrap_or(ty) {
+            "u256" => Some(2),
+            "felt252" | "bool" | "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32"
+            | "i64" | "i128" | "bytes31" | "ContractAddress" | "ClassHash" | "EthAddress"
+            | "StorageAddress" => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Push the Cairo type of each felt a value of type `ty` serializes to
+    ///
+    /// Returns `None` if `ty` has a variable length, after pushing the types of
+    /// the felts before it.
+    fn felt_types<'a>(&'a self, ty: &'a str, types: &mut Vec<&'a str>) -> Option<()> {
+        if let Some(members) = self.structs.get(ty) {
+            for member in members {
+                self.felt_types(member, types)?;
+            }
+            return Some(());
+        }
+        match self.serialized_len(ty)? {
+            // A u256 is a struct of its low and high u128
+            2 => types.extend(["u128", "u128"]),
+            _ => types.push(ty),
+        }
+        Some(())
+    }
+}
+
+/// Returns true if `value` is in the range of the single-felt Cairo type `ty`
+fn felt_fits(ty: &str, value: &Felt) -> bool {
+    let (signed, bits) = match ty.rsplit("::").next().unwrap_or(ty) {
+        "bool" => return *value <= Felt::ONE,
+        "u8" => (false, 8),
+        "u16" => (false, 16),
+        "u32" => (false, 32),
+        "u64" => (false, 64),
+        "u128" => (false, 128),
+        "i8" => (true, 8),
+        "i16" => (true, 16),
+        "i32" => (true, 32),
+        "i64" => (true, 64),
+        "i128" => (true, 128),
+        "EthAddress" => (false, 160),
+        "bytes31" => (false, 248),
+        "ContractAddress" | "ClassHash" | "StorageAddress" => (false, 251),
+        _ => return true,
+    };
+    if signed {
+        // Negative values wrap around the field
+        let bound = Felt::from(1u128 << (bits - 1));
+        *value < bound || -*value <= bound
+    } else {
+        value.bits() <= bits
+    }
+}
+
+/// ABI bindings registered with a `StarknetConnection`, by contract address
+#[derive(Default)]
+pub(crate) struct AbiState {
+    bindings: HashMap<Felt, AbiBinding>,
+}
+
+impl StarknetConnection {
+    /// Check the calls of submitted transactions against `binding`
+    ///
+    /// Calls to `binding.address` are validated with `AbiBinding::validate`
+    /// when submitted through `execute_transaction` or `execute_batch`. If
+    /// the binding is strict, a mismatch rejects the
+    /// transaction with `SubmitOutcome::CalldataTypeMismatch` before anything
+    /// is sent; otherwise it's only logged. This replaces any binding
+    /// registered for the same address.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn setup(mut sn: ResMut<StarknetConnection>) {
+    ///     sn.register_abi(
+    ///         AbiBinding::new(token_address)
+    ///             .with_entrypoint("transfer", &["ContractAddress", "u256"])
+    ///             .strict(true),
+    ///     );
+    /// }
+    /// ```
+    pub fn register_abi(&mut self, binding: AbiBinding) {
+        self.abis.bindings.insert(binding.address, binding);
+    }
+
+    /// Stop checking the calls to `address`, returning its binding if any
+    pub fn unregister_abi(&mut self, address: Felt) -> Option<AbiBinding> {
+        self.abis.bindings.remove(&address)
+    }
+
+    /// Check `calls` against the registered ABI bindings
+    pub(crate) fn check_calldata(&self, calls: &[Call]) -> Result<(), SubmitOutcome> {
+        for (index, call) in calls.iter().enumerate() {
+            let Some(binding) = self.abis.bindings.get(&call.to) else {
+                continue;
+            };
+            if let Err(err) = binding.validate(call) {
+                if binding.is_strict() {
+                    warn!("Rejecting transaction: call {}: {}", index, err);
+                    return Err(SubmitOutcome::CalldataTypeMismatch { index });
+                }
+                warn!("Call {}: {}", index, err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A Sierra contract ABI loaded through Bevy's `AssetServer`
+///
+/// Files ending in `.abi.json` or `.contract_class.json` are loaded by the
+/// `ContractAbiLoader` registered by the `BevyDojoPlugin`. They can hold either
+/// the bare ABI array or a whole contract class with an `abi` field, as
+/// produced by Scarb. With
+/// Bevy's `file_watcher` feature, edited ABI files are hot reloaded like any
+/// other asset.
+///
+/// Calls built from the asset are strict: unknown entrypoints and calldata
+/// that doesn't match the inputs are rejected.
+///
+/// # Example
+///
+/// ```no_run
+/// #[derive(Resource)]
+/// struct CounterAbi(Handle<ContractAbi>);
+///
+/// fn load_abi(mut commands: Commands, assets: Res<AssetServer>) {
+///     commands.insert_resource(CounterAbi(assets.load("counter.abi.json")));
+/// }
+///
+/// fn increment(
+///     abi: Res<CounterAbi>,
+///     abis: Res<Assets<ContractAbi>>,
+///     runtime: Res<TokioRuntime>,
+///     sn: ResMut<StarknetConnection>,
+/// ) {
+///     if let Ok(call) = abi_call(&abis, &abi.0, counter_address, "increment", vec![]) {
+///         execute_transaction(runtime, sn, vec![call]);
+///     }
+/// }
+/// ```
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct ContractAbi {
+    pub entries: Vec<AbiEntry>,
+    binding: AbiBinding,
+}
+
+impl ContractAbi {
+    /// Create an ABI asset from the entries of a Sierra contract ABI
+    pub fn new(entries: Vec<AbiEntry>) -> Self {
+        let binding = AbiBinding::from_abi(Felt::ZERO, &entries).strict(true);
+        Self { entries, binding }
+    }
+
+    /// Returns the selector of `entrypoint`, if it is part of the ABI
+    pub fn selector(&self, entrypoint: &str) -> Option<Felt> {
+        self.binding
+            .entrypoint(entrypoint)
+            .map(|bound| bound.selector)
+    }
+
+    /// Bind the ABI to the contract deployed at `address`
+    pub fn bind(&self, address: Felt) -> AbiBinding {
+        AbiBinding {
+            address,
+            ..self.binding.clone()
+        }
+    }
+
+    /// Build a call to `entrypoint` of the contract at `to`
+    pub fn call(
+        &self,
+        to: Felt,
+        entrypoint: &str,
+        calldata: Vec<Felt>,
+    ) -> Result<Call, CallValidationError> {
+        self.binding.call_to(to, entrypoint, calldata)
+    }
+}
+
+/// Build a call from a `ContractAbi` asset
+///
+/// # Arguments
+///
+/// * `abis` - The `ContractAbi` assets
+/// * `handle` - The handle of the ABI to use
+/// * `to` - The address of the contract to call
+/// * `entrypoint` - The name of the entrypoint to call
+/// * `calldata` - The serialized arguments
+///
+/// # Returns
+///
+/// The call, `CallValidationError::AbiNotLoaded` if the asset isn't loaded
+/// yet, or another `CallValidationError` if the call doesn't match the ABI
+pub fn abi_call(
+    abis: &Assets<ContractAbi>,
+    handle: &Handle<ContractAbi>,
+    to: Felt,
+    entrypoint: &str,
+    calldata: Vec<Felt>,
+) -> Result<Call, CallValidationError> {
+    abis.get(handle)
+        .ok_or(CallValidationError::AbiNotLoaded)?
+        .call(to, entrypoint, calldata)
+}
+
+/// Error produced while loading a `ContractAbi` asset
+#[derive(Debug)]
+pub enum ContractAbiLoaderError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The file is neither an ABI array nor a contract class with an `abi` field
+    MissingAbi,
+}
+
+impl fmt::Display for ContractAbiLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContractAbiLoaderError::Io(err) => write!(f, "failed to read ABI: {err}"),
+            ContractAbiLoaderError::Json(err) => write!(f, "failed to parse ABI: {err}"),
+            ContractAbiLoaderError::MissingAbi => write!(f, "no ABI found in file"),
+        }
+    }
+}
+
+impl std::error::Error for ContractAbiLoaderError {}
+
+impl From<std::io::Error> for ContractAbiLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        ContractAbiLoaderError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ContractAbiLoaderError {
+    fn from(err: serde_json::Error) -> Self {
+        ContractAbiLoaderError::Json(err)
+    }
+}
+
+/// Asset loader for `.abi.json` and `.contract_class.json` files
+#[derive(Default)]
+pub struct ContractAbiLoader;
+
+impl AssetLoader for ContractAbiLoader {
+    type Asset = ContractAbi;
+    type Settings = ();
+    type Error = ContractAbiLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<ContractAbi, ContractAbiLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let entries = match serde_json::from_slice(&bytes)? {
+            abi @ serde_json::Value::Array(_) => serde_json::from_value(abi)?,
+            serde_json::Value::Object(mut class) => match class.remove("abi") {
+                // Sierra classes declared on-chain carry the ABI as a JSON string
+                Some(serde_json::Value::String(abi)) => serde_json::from_str(&abi)?,
+                Some(abi @ serde_json::Value::Array(_)) => serde_json::from_value(abi)?,
+                _ => return Err(ContractAbiLoaderError::MissingAbi),
+            },
+            _ => return Err(ContractAbiLoaderError::MissingAbi),
+        };
+        Ok(ContractAbi::new(entries))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["abi.json", "contract_class.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    fn counter() -> AbiBinding {
+        AbiBinding::new(Felt::from(0x42u8))
+            .with_entrypoint("increment", &[])
+            .with_entrypoint("set", &["core::integer::u256"])
+    }
+
+    #[test]
+    fn count_mismatch_is_an_error_in_strict_mode() {
+        let counter = counter().strict(true);
+        assert_eq!(
+            counter.call("set", vec![Felt::ONE]).unwrap_err(),
+            CallValidationError::ArgumentCountMismatch {
+                entrypoint: "set".to_string(),
+                expected: 2,
+                actual: 1,
+            }
+        );
+        assert_eq!(
+            counter.call("increment", vec![Felt::ONE]).unwrap_err(),
+            CallValidationError::ArgumentCountMismatch {
+                entrypoint: "increment".to_string(),
+                expected: 0,
+                actual: 1,
+            }
+        );
+
+        let call = counter.call("set", vec![Felt::ONE, Felt::ZERO]).unwrap();
+        assert_eq!(call.selector, get_selector_from_name("set").unwrap());
+        assert_eq!(call.calldata, vec![Felt::ONE, Felt::ZERO]);
+    }
+
+    #[test]
+    fn count_mismatch_is_only_logged_when_not_strict() {
+        let call = counter().call("set", vec![Felt::ONE]).unwrap();
+        assert_eq!(call.to, Felt::from(0x42u8));
+        assert_eq!(call.calldata, vec![Felt::ONE]);
+    }
+
+    #[test]
+    fn strict_mismatch_rejects_the_transaction() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        // `call` targets 0x42, which has no binding
+        let set = |value: u64| Call {
+            to: Felt::from(0x43u8),
+            selector: get_selector_from_name("set").unwrap(),
+            calldata: vec![Felt::from(value), Felt::ZERO],
+        };
+        let level = AbiBinding::new(Felt::from(0x43u8)).with_entrypoint("set", &["u8", "u8"]);
+
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.register_abi(level.clone().strict(true));
+            sn.execute(runtime, vec![call(0), set(300)])
+        });
+        assert_eq!(outcome, SubmitOutcome::CalldataTypeMismatch { index: 1 });
+        let outcome = with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![set(200)]));
+        assert!(outcome.is_queued());
+
+        // Only logged when not strict
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.register_abi(level);
+            sn.execute(runtime, vec![set(300)])
+        });
+        assert!(outcome.is_queued());
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 2
+        }));
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 2);
+    }
+
+    #[test]
+    fn abi_asset_loads_and_builds_calls() {
+        let dir = std::env::temp_dir().join(format!("bevy_dojo_abi_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let abi = serde_json::json!({
+            "sierra_program": [],
+            "abi": [{
+                "type": "function",
+                "name": "set",
+                "inputs": [{ "name": "value", "type": "core::integer::u256" }],
+                "outputs": [],
+                "state_mutability": "external"
+            }]
+        });
+        std::fs::write(dir.join("counter.contract_class.json"), abi.to_string()).unwrap();
+
+        // The asset plugin is added last, after the plugin registering the loader
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            crate::BevyDojoPlugin,
+            AssetPlugin {
+                file_path: dir.to_string_lossy().into_owned(),
+                ..default()
+            },
+        ));
+        app.finish();
+        app.cleanup();
+
+        let handle: Handle<ContractAbi> = app
+            .world()
+            .resource::<AssetServer>()
+            .load("counter.contract_class.json");
+        assert!(crate::mock::update_until(&mut app, |app| {
+            app.world()
+                .resource::<Assets<ContractAbi>>()
+                .contains(&handle)
+        }));
+
+        let abis = app.world().resource::<Assets<ContractAbi>>();
+        let to = Felt::from(0x42u8);
+        let call = abi_call(abis, &handle, to, "set", vec![Felt::ONE, Felt::ZERO]).unwrap();
+        assert_eq!(call.to, to);
+        assert_eq!(call.selector, get_selector_from_name("set").unwrap());
+        assert_eq!(call.calldata, vec![Felt::ONE, Felt::ZERO]);
+        assert!(abi_call(abis, &handle, to, "set", vec![Felt::ONE]).is_err());
+        assert!(abi_call(abis, &handle, to, "reset", vec![]).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,441 @@
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use starknet::{
+    accounts::{Account, AccountError, single_owner::SignError},
+    core::types::{Call, FeeEstimate, ResourceBounds, ResourceBoundsMapping},
+    signers::local_wallet::SignError as LocalWalletSignError,
+};
+use tokio::task::JoinHandle;
+
+use crate::chains::StarknetChains;
+use crate::limit::RequestLimiter;
+use crate::reconnect::TaskEvents;
+use crate::record::TxStatus;
+use crate::session::SessionAccount;
+use crate::signature::DojoAccount;
+use crate::sink::TransactionFailed;
+use crate::starknet::{PollDeadline, StarknetConnection, TxId};
+use crate::tokio::TokioRuntime;
+
+/// Event emitted with the fee estimate of a transaction awaiting approval
+///
+/// Only emitted when fee approval is required, see
+/// `StarknetConnection::set_require_fee_approval`. The transaction is not sent
+/// until `StarknetConnection::approve_fee` is called with its id.
+#[derive(Event, Debug, Clone)]
+pub struct FeeEstimated {
+    pub tx_id: TxId,
+    pub estimate: FeeEstimate,
+}
+
+/// Margin applied to estimated amounts and prices when sending without explicit bounds
+///
+/// This matches the default multipliers of starknet-rs' `ExecutionV3`.
+pub const ESTIMATE_MARGIN: f64 = 1.5;
+
+/// Resource bounds a transaction is sent with
+///
+/// Amounts are in gas units and prices in the smallest unit of the fee token
+/// per gas unit. The `From<&FeeEstimate>` implementation uses the estimated
+/// amounts and prices as-is; the game may raise them to leave some margin.
+///
+/// The tip set with `StarknetConnection::set_tip` is paid on top of these
+/// bounds, so `l2_gas_price` must leave room for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeBounds {
+    pub l1_gas: u64,
+    pub l1_gas_price: u128,
+    pub l2_gas: u64,
+    pub l2_gas_price: u128,
+    pub l1_data_gas: u64,
+    pub l1_data_gas_price: u128,
+}
+
+impl FeeBounds {
+    /// Build bounds from `estimate`, multiplying amounts and prices by `margin`
+    pub fn with_margin(estimate: &FeeEstimate, margin: f64) -> Self {
+        let amount = |value: u64| (value as f64 * margin) as u64;
+        let price = |value: u128| (value as f64 * margin) as u128;
+        Self {
+            l1_gas: amount(estimate.l1_gas_consumed),
+            l1_gas_price: price(estimate.l1_gas_price),
+            l2_gas: amount(estimate.l2_gas_consumed),
+            l2_gas_price: price(estimate.l2_gas_price),
+            l1_data_gas: amount(estimate.l1_data_gas_consumed),
+            l1_data_gas_price: price(estimate.l1_data_gas_price),
+        }
+    }
+
+    /// Returns the maximum fee the transaction may pay with these bounds
+    pub fn max_fee(&self) -> u128 {
+        let fee = |amount: u64, price: u128| u128::from(amount).saturating_mul(price);
+        fee(self.l1_gas, self.l1_gas_price)
+            .saturating_add(fee(self.l2_gas, self.l2_gas_price))
+            .saturating_add(fee(self.l1_data_gas, self.l1_data_gas_price))
+    }
+
+    /// Returns true if these bounds can replace `original` in the mempool
+    ///
+    /// No amount or price may be lower than in `original`, and the maximum fee
+    /// must be higher.
+    pub fn raises(&self, original: &FeeBounds) -> bool {
+        self.l1_gas >= original.l1_gas
+            && self.l1_gas_price >= original.l1_gas_price
+            && self.l2_gas >= original.l2_gas
+            && self.l2_gas_price >= original.l2_gas_price
+            && self.l1_data_gas >= original.l1_data_gas
+            && self.l1_data_gas_price >= original.l1_data_gas_price
+            && self.max_fee() > original.max_fee()
+    }
+
+    /// Returns the bounds as the resource bounds of a v3 transaction
+    pub fn resource_bounds(&self) -> ResourceBoundsMapping {
+        ResourceBoundsMapping {
+            l1_gas: ResourceBounds {
+                max_amount: self.l1_gas,
+                max_price_per_unit: self.l1_gas_price,
+            },
+            l1_data_gas: ResourceBounds {
+                max_amount: self.l1_data_gas,
+                max_price_per_unit: self.l1_data_gas_price,
+            },
+            l2_gas: ResourceBounds {
+                max_amount: self.l2_gas,
+                max_price_per_unit: self.l2_gas_price,
+            },
+        }
+    }
+}
+
+impl From<&FeeEstimate> for FeeBounds {
+    fn from(estimate: &FeeEstimate) -> Self {
+        Self {
+            l1_gas: estimate.l1_gas_consumed,
+            l1_gas_price: estimate.l1_gas_price,
+            l2_gas: estimate.l2_gas_consumed,
+            l2_gas_price: estimate.l2_gas_price,
+            l1_data_gas: estimate.l1_data_gas_consumed,
+            l1_data_gas_price: estimate.l1_data_gas_price,
+        }
+    }
+}
+
+struct EstimatingTx {
+    id: TxId,
+    calls: Vec<Call>,
+    session: Option<Arc<SessionAccount>>,
+    task: JoinHandle<Result<FeeEstimate, AccountError<SignError<LocalWalletSignError>>>>,
+}
+
+/// Transaction waiting for its fee to be approved
+struct AwaitingTx {
+    calls: Vec<Call>,
+    /// Session signing the transaction, `None` for the connected account
+    session: Option<Arc<SessionAccount>>,
+}
+
+/// Transactions of a `StarknetConnection` waiting for their fee to be approved
+#[derive(Default)]
+pub(crate) struct ApprovalState {
+    pub(crate) require_fee_approval: bool,
+    estimating: Vec<EstimatingTx>,
+    awaiting: HashMap<TxId, AwaitingTx>,
+    /// Transactions rejected since the last poll, with their calls
+    rejected: Vec<(TxId, Vec<Call>)>,
+}
+
+impl ApprovalState {
+    /// Spawn the fee estimation of the transaction `id`, signed by `session` if given
+    pub(crate) fn estimate(
+        &mut self,
+        runtime: &TokioRuntime,
+        account: Arc<DojoAccount>,
+        session: Option<Arc<SessionAccount>>,
+        limiter: RequestLimiter,
+        id: TxId,
+        calls: Vec<Call>,
+    ) {
+        let estimated_calls = calls.clone();
+        let estimated_session = session.clone();
+        let task = runtime.runtime.spawn(async move {
+            let _permit = limiter.acquire().await;
+            match estimated_session {
+                Some(session) => session.execute_v3(estimated_calls).estimate_fee().await,
+                None => account.execute_v3(estimated_calls).estimate_fee().await,
+            }
+        });
+        self.estimating.push(EstimatingTx {
+            id,
+            calls,
+            session,
+            task,
+        });
+    }
+
+    /// Returns the number of transactions being estimated or awaiting approval
+    pub(crate) fn len(&self) -> usize {
+        self.estimating.len() + self.awaiting.len()
+    }
+
+    /// Returns the number of running fee estimation tasks
+    pub(crate) fn task_count(&self) -> usize {
+        self.estimating.len()
+    }
+}
+
+impl StarknetConnection {
+    /// Returns true if transactions wait for their fee to be approved before being sent
+    pub fn require_fee_approval(&self) -> bool {
+        self.approvals.require_fee_approval
+    }
+
+    /// Require transactions to wait for their fee to be approved before being sent
+    ///
+    /// When enabled, `execute_transaction` and `execute_batch` first estimate
+    /// the fee and emit a `FeeEstimated` event. The transaction is then only
+    /// sent once the game calls `approve_fee`, or cancelled with `reject_fee`. Disabled by default.
+    pub fn set_require_fee_approval(&mut self, require: bool) {
+        self.approvals.require_fee_approval = require;
+    }
+
+    /// Returns true if the transaction `tx_id` is waiting for its fee to be approved
+    pub fn is_awaiting_approval(&self, tx_id: TxId) -> bool {
+        self.approvals.awaiting.contains_key(&tx_id)
+    }
+
+    /// Send a transaction awaiting approval with the given resource bounds
+    ///
+    /// # Arguments
+    ///
+    /// * `runtime` - The Tokio runtime
+    /// * `tx_id` - The id from the `FeeEstimated` event
+    /// * `bounds` - The resource bounds, usually built from the estimate
+    ///
+    /// # Returns
+    ///
+    /// False if `tx_id` isn't awaiting approval, or if there's no connected
+    /// account to send it with, in which case it keeps awaiting approval
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn approve_cheap_fees(
+    ///     mut events: EventReader<FeeEstimated>,
+    ///     runtime: Res<TokioRuntime>,
+    ///     mut sn: ResMut<StarknetConnection>,
+    /// ) {
+    ///     for event in events.read() {
+    ///         if event.estimate.overall_fee < MAX_FEE {
+    ///             sn.approve_fee(&runtime, event.tx_id, FeeBounds::from(&event.estimate));
+    ///         } else {
+    ///             sn.reject_fee(event.tx_id);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn approve_fee(&mut self, runtime: &TokioRuntime, tx_id: TxId, bounds: FeeBounds) -> bool {
+        if self.account().is_none() {
+            return false;
+        }
+        let Some(awaiting) = self.approvals.awaiting.remove(&tx_id) else {
+            return false;
+        };
+        self.queue_send_as(
+            runtime,
+            awaiting.session,
+            tx_id,
+            awaiting.calls,
+            Some(bounds),
+            None,
+        );
+        true
+    }
+
+    /// Cancel a transaction awaiting approval
+    ///
+    /// The transaction is reported like any other failure, with a
+    /// `TransactionFailed` event of status `TxStatus::Rejected` emitted by the
+    /// next `check_sn_task`.
+    ///
+    /// Returns false if `tx_id` isn't awaiting approval.
+    pub fn reject_fee(&mut self, tx_id: TxId) -> bool {
+        let Some(AwaitingTx { calls, .. }) = self.approvals.awaiting.remove(&tx_id) else {
+            return false;
+        };
+        info!("Transaction {} rejected", tx_id.0);
+        if let Some(record) = self.record_mut(tx_id) {
+            record.set_status(TxStatus::Rejected);
+        }
+        self.approvals.rejected.push((tx_id, calls));
+        let cancelled = self.abort_batch(tx_id);
+        self.defer_cancelled(cancelled);
+        true
+    }
+
+    /// Report the transactions rejected with `reject_fee` since the last poll
+    pub(crate) fn poll_rejected(&mut self, events: &mut TaskEvents) {
+        for (tx_id, calls) in std::mem::take(&mut self.approvals.rejected) {
+            events.sink().on_failed(&TransactionFailed {
+                tx_id,
+                hash: None,
+                status: TxStatus::Rejected,
+                calls: self.retain_failed_calls.then_some(calls),
+            });
+        }
+    }
+
+    fn poll_fee_estimates(
+        &mut self,
+        runtime: &TokioRuntime,
+        estimated: &mut EventWriter<FeeEstimated>,
+        deadline: &PollDeadline,
+    ) {
+        let mut processed = 0;
+        let mut i = 0;
+        while i < self.approvals.estimating.len() && deadline.allows(processed) {
+            if !self.approvals.estimating[i].task.is_finished() {
+                i += 1;
+                continue;
+            }
+            processed += 1;
+            let estimating = self.approvals.estimating.swap_remove(i);
+            match runtime.runtime.block_on(estimating.task) {
+                Ok(Ok(estimate)) => {
+                    self.approvals.awaiting.insert(
+                        estimating.id,
+                        AwaitingTx {
+                            calls: estimating.calls,
+                            session: estimating.session,
+                        },
+                    );
+                    estimated.write(FeeEstimated {
+                        tx_id: estimating.id,
+                        estimate,
+                    });
+                }
+                Ok(Err(err)) => {
+                    warn!(
+                        "Fee estimation of transaction {} failed: {}",
+                        estimating.id.0, err
+                    );
+                    let cancelled = self.fail_tx(estimating.id, err.to_string());
+                    self.defer_cancelled(cancelled);
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// System that emits `FeeEstimated` for transactions awaiting fee approval
+///
+/// It covers the main connection and the connections of `StarknetChains`, and
+/// is automatically registered by the `BevyDojoPlugin`.
+pub fn check_fee_estimates(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    mut chains: ResMut<StarknetChains>,
+    mut estimated: EventWriter<FeeEstimated>,
+    deadline: Res<PollDeadline>,
+) {
+    sn.poll_fee_estimates(&runtime, &mut estimated, &deadline);
+    for connection in chains.connections_mut() {
+        connection.poll_fee_estimates(&runtime, &mut estimated, &deadline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::starknet::SubmitOutcome;
+
+    #[test]
+    fn rejected_fee_emits_failure() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        collect::<TransactionFailed>(&mut app);
+
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.set_require_fee_approval(true);
+            sn.execute(runtime, vec![call(0)])
+        });
+        let SubmitOutcome::Queued(tx_id) = outcome else {
+            panic!("transaction not queued: {outcome:?}");
+        };
+        assert!(update_until(&mut app, |app| {
+            connection(app).is_awaiting_approval(tx_id)
+        }));
+        assert!(with_connection(&mut app, |_, sn| sn.reject_fee(tx_id)));
+        app.update();
+
+        let failed = collected::<TransactionFailed>(&app);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].tx_id, tx_id);
+        assert_eq!(failed[0].status, TxStatus::Rejected);
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 0);
+    }
+
+    #[test]
+    fn transaction_waits_for_approval() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        collect::<FeeEstimated>(&mut app);
+
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.set_require_fee_approval(true);
+            sn.execute(runtime, vec![call(0)])
+        });
+        let SubmitOutcome::Queued(tx_id) = outcome else {
+            panic!("transaction not queued: {outcome:?}");
+        };
+        assert!(update_until(&mut app, |app| {
+            !collected::<FeeEstimated>(app).is_empty()
+        }));
+        for _ in 0..10 {
+            app.update();
+        }
+        assert!(connection(&app).is_awaiting_approval(tx_id));
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 0);
+
+        let estimated = collected::<FeeEstimated>(&app);
+        assert_eq!(estimated[0].tx_id, tx_id);
+        let mut bounds = FeeBounds::from(&estimated[0].estimate);
+        bounds.l2_gas = 1234;
+        assert!(with_connection(&mut app, |runtime, sn| {
+            sn.approve_fee(runtime, tx_id, bounds)
+        }));
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+        let request = &mock.requests("starknet_addInvokeTransaction")[0];
+        let sent = param(request, 0, "invoke_transaction");
+        assert_eq!(sent["resource_bounds"]["l2_gas"]["max_amount"], "0x4d2");
+        assert_eq!(sent["tip"], "0x0");
+        assert!(!connection(&app).is_awaiting_approval(tx_id));
+    }
+
+    #[test]
+    fn approval_needs_an_account() {
+        let mut app = test_app();
+        let tx_id = TxId(42);
+        let estimate: FeeEstimate = serde_json::from_value(fee_estimate()).unwrap();
+        let approved = with_connection(&mut app, |runtime, sn| {
+            sn.approvals.awaiting.insert(
+                tx_id,
+                AwaitingTx {
+                    calls: vec![call(0)],
+                    session: None,
+                },
+            );
+            sn.approve_fee(runtime, tx_id, FeeBounds::from(&estimate))
+        });
+        assert!(!approved);
+        assert!(connection(&app).is_awaiting_approval(tx_id));
+    }
+}
@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+
+use std::collections::{HashMap, VecDeque};
+
+use starknet::core::types::Call;
+
+use crate::reconnect::TaskEvents;
+use crate::record::{TxRecord, TxStatus};
+use crate::sink::TransactionFailed;
+use crate::starknet::{InvalidCallReason, StarknetConnection, SubmitOutcome, TxId, validate_calls};
+use crate::tokio::TokioRuntime;
+
+/// Default maximum number of calls sent in a single transaction by `execute_batch`
+pub const DEFAULT_MAX_CALLS_PER_TX: usize = 100;
+
+/// Chunks of batches waiting for the previous chunk to be confirmed
+pub(crate) struct BatchState {
+    max_calls_per_tx: usize,
+    /// Remaining chunks, keyed by the id of the chunk they wait for
+    waiting: HashMap<TxId, VecDeque<(TxId, Vec<Call>)>>,
+    /// Chunks cancelled outside of `poll_tasks`, reported by the next one
+    cancelled: Vec<CancelledChunk>,
+}
+
+impl Default for BatchState {
+    fn default() -> Self {
+        Self {
+            max_calls_per_tx: DEFAULT_MAX_CALLS_PER_TX,
+            waiting: HashMap::new(),
+            cancelled: Vec::new(),
+        }
+    }
+}
+
+/// A chunk of a batch cancelled because an earlier chunk didn't go through
+pub(crate) struct CancelledChunk {
+    tx_id: TxId,
+    calls: Vec<Call>,
+    error: String,
+}
+
+impl StarknetConnection {
+    /// Returns the maximum number of calls sent in a single transaction by `execute_batch`
+    pub fn max_calls_per_tx(&self) -> usize {
+        self.batches.max_calls_per_tx
+    }
+
+    /// Sets the maximum number of calls sent in a single transaction by `execute_batch`
+    ///
+    /// Defaults to `DEFAULT_MAX_CALLS_PER_TX`. A value of zero is treated as one.
+    pub fn set_max_calls_per_tx(&mut self, max_calls_per_tx: usize) {
+        self.batches.max_calls_per_tx = max_calls_per_tx.max(1);
+    }
+
+    /// Queue `calls` as a sequence of transactions of at most `max_calls_per_tx` calls
+    ///
+    /// This is the method form of `execute_batch`.
+    pub fn execute_batch(
+        &mut self,
+        runtime: &TokioRuntime,
+        calls: Vec<Call>,
+    ) -> Result<Vec<TxId>, SubmitOutcome> {
+        if self.is_read_only() {
+            return Err(SubmitOutcome::ReadOnly);
+        }
+        let Some(account) = self.account().cloned() else {
+            return Err(SubmitOutcome::NotConnected);
+        };
+        if self.spend_limit_reached() {
+            warn!("Session spend limit reached, rejecting batch");
+            return Err(SubmitOutcome::SpendLimitReached);
+        }
+        if calls.is_empty() {
+            return Err(SubmitOutcome::InvalidCall {
+                index: 0,
+                reason: InvalidCallReason::NoCalls,
+            });
+        }
+        let max_calls_per_tx = self.batches.max_calls_per_tx;
+        for (n, chunk) in calls.chunks(max_calls_per_tx).enumerate() {
+            if let Err(SubmitOutcome::InvalidCall { index, reason }) = validate_calls(chunk) {
+                warn!(
+                    "Rejecting malformed batch: call {} has {}",
+                    n * max_calls_per_tx + index,
+                    reason
+                );
+                return Err(SubmitOutcome::InvalidCall {
+                    index: n * max_calls_per_tx + index,
+                    reason,
+                });
+            }
+        }
+        self.check_calldata(&calls)?;
+
+        let mut chunks = VecDeque::new();
+        let mut calls = calls.into_iter().peekable();
+        while calls.peek().is_some() {
+            let chunk = calls.by_ref().take(max_calls_per_tx).collect::<Vec<_>>();
+            let id = self.next_tx_id();
+            self.push_record(TxRecord::new(id, chunk.clone()));
+            chunks.push_back((id, chunk));
+        }
+        let ids = chunks.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        self.metrics.submitted_txs += ids.len() as u64;
+
+        if let Some((id, chunk)) = chunks.pop_front() {
+            if !chunks.is_empty() {
+                self.batches.waiting.insert(id, chunks);
+            }
+            self.dispatch(runtime, account, None, id, chunk);
+        }
+        Ok(ids)
+    }
+
+    /// Send the chunk that was waiting for the transaction `id` to be confirmed
+    pub(crate) fn send_next_chunk(&mut self, runtime: &TokioRuntime, id: TxId) {
+        let Some(mut chunks) = self.batches.waiting.remove(&id) else {
+            return;
+        };
+        let Some(account) = self.account().cloned() else {
+            return;
+        };
+        if let Some((next, chunk)) = chunks.pop_front() {
+            if !chunks.is_empty() {
+                self.batches.waiting.insert(next, chunks);
+            }
+            self.dispatch(runtime, account, None, next, chunk);
+        }
+    }
+
+    /// Fail the chunks that were waiting for the transaction `id`, which didn't go through
+    ///
+    /// Returns the cancelled chunks, to be reported with `report_cancelled`.
+    #[must_use]
+    pub(crate) fn abort_batch(&mut self, id: TxId) -> Vec<CancelledChunk> {
+        let Some(chunks) = self.batches.waiting.remove(&id) else {
+            return Vec::new();
+        };
+        warn!(
+            "Transaction {} of a batch did not go through, cancelling the {} remaining chunks",
+            id.0,
+            chunks.len()
+        );
+        let error = format!("transaction {} of the batch did not go through", id.0);
+        let mut cancelled = Vec::with_capacity(chunks.len());
+        for (tx_id, calls) in chunks {
+            self.metrics.failed_txs += 1;
+            if let Some(record) = self.record_mut(tx_id) {
+                record.set_status(TxStatus::Failed {
+                    error: error.clone(),
+                });
+            }
+            cancelled.push(CancelledChunk {
+                tx_id,
+                calls,
+                error: error.clone(),
+            });
+        }
+        cancelled
+    }
+
+    /// Emit a `TransactionFailed` for each of the `cancelled` chunks
+    pub(crate) fn report_cancelled(&self, cancelled: Vec<CancelledChunk>, events: &mut TaskEvents) {
+        for chunk in cancelled {
+            events.sink().on_failed(&TransactionFailed {
+                tx_id: chunk.tx_id,
+                hash: None,
+                status: TxStatus::Failed { error: chunk.error },
+                calls: self.retain_failed_calls.then_some(chunk.calls),
+            });
+        }
+    }
+
+    /// Keep chunks cancelled outside of `poll_tasks` to report them in the next one
+    pub(crate) fn defer_cancelled(&mut self, cancelled: Vec<CancelledChunk>) {
+        self.batches.cancelled.extend(cancelled);
+    }
+
+    /// Report the chunks kept by `defer_cancelled` since the last poll
+    pub(crate) fn poll_cancelled(&mut self, events: &mut TaskEvents) {
+        let cancelled = std::mem::take(&mut self.batches.cancelled);
+        self.report_cancelled(cancelled, events);
+    }
+}
+
+/// Execute a large number of calls as a sequence of transactions
+///
+/// Very large multicalls can exceed the calldata or step limits of the node.
+/// This splits `calls` into chunks of at most `max_calls_per_tx` calls, see
+/// `StarknetConnection::set_max_calls_per_tx`, each sent as its own
+/// transaction. Chunks are sent in order, each one only once the previous one
+/// is confirmed.
+///
+/// If a chunk fails to send, is reverted or is dropped, the chunks after it
+/// are not sent: their records are marked as `TxStatus::Failed` and a
+/// `TransactionFailed` event is emitted for each, so the game can tell exactly
+/// which calls went through.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `calls` - The calls to execute, in order
+///
+/// # Returns
+///
+/// * `Ok` with the ids of the transactions, one per chunk, in order
+/// * `Err(SubmitOutcome::NotConnected)` if there's no active Starknet connection
+/// * `Err(SubmitOutcome::SpendLimitReached)` if the session spend limit has been reached
+/// * `Err(SubmitOutcome::InvalidCall)` if `calls` is empty or a call or chunk is
+///   malformed, see `validate_calls`
+/// * `Err(SubmitOutcome::CalldataTypeMismatch)` if a call doesn't match the strict
+///   ABI binding of its contract, see `register_abi`
+///
+/// # Example
+///
+/// ```no_run
+/// fn mint_all(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     let calls = (0..250).map(|i| mint_call(i)).collect();
+///     if let Ok(ids) = execute_batch(runtime, sn, calls) {
+///         println!("Minting in {} transactions", ids.len());
+///     }
+/// }
+/// ```
+pub fn execute_batch(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    calls: Vec<Call>,
+) -> Result<Vec<TxId>, SubmitOutcome> {
+    sn.execute_batch(&runtime, calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::sink::TransactionConfirmed;
+
+    #[test]
+    fn batch_is_sent_in_chunks() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        collect::<TransactionConfirmed>(&mut app);
+
+        let calls = (0..250).map(call).collect::<Vec<_>>();
+        let ids = with_connection(&mut app, |runtime, sn| {
+            assert_eq!(sn.max_calls_per_tx(), 100);
+            sn.execute_batch(runtime, calls)
+        })
+        .unwrap();
+        assert_eq!(ids.len(), 3);
+        assert!(update_until(&mut app, |app| {
+            collected::<TransactionConfirmed>(app).len() == 3
+        }));
+
+        let confirmed = collected::<TransactionConfirmed>(&app);
+        let confirmed = confirmed.iter().map(|c| c.tx_id).collect::<Vec<_>>();
+        assert_eq!(confirmed, ids);
+        // Each chunk is the calldata of an `__execute__`, starting with its call count
+        let counts = mock
+            .requests("starknet_addInvokeTransaction")
+            .iter()
+            .map(|request| param(request, 0, "invoke_transaction")["calldata"][0].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, ["0x64", "0x64", "0x32"]);
+    }
+
+    #[test]
+    fn empty_batch_is_rejected() {
+        let mock = MockRpc::start();
+        let mut app = connected_app(&mock);
+        let outcome = with_connection(&mut app, |runtime, sn| sn.execute_batch(runtime, vec![]));
+        assert_eq!(
+            outcome,
+            Err(SubmitOutcome::InvalidCall {
+                index: 0,
+                reason: InvalidCallReason::NoCalls,
+            })
+        );
+    }
+
+    #[test]
+    fn failed_chunk_fails_the_rest_of_the_batch() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on_error(
+            "starknet_addInvokeTransaction",
+            RpcError::new(55, "Account validation failed"),
+        );
+        let mut app = connected_app(&mock);
+        collect::<TransactionFailed>(&mut app);
+
+        let calls = (0..250).map(call).collect::<Vec<_>>();
+        let ids =
+            with_connection(&mut app, |runtime, sn| sn.execute_batch(runtime, calls)).unwrap();
+        assert!(update_until(&mut app, |app| {
+            collected::<TransactionFailed>(app).len() == 3
+        }));
+
+        let failed = collected::<TransactionFailed>(&app);
+        assert_eq!(failed.iter().map(|f| f.tx_id).collect::<Vec<_>>(), ids);
+        assert!(
+            failed
+                .iter()
+                .all(|f| matches!(f.status, TxStatus::Failed { .. }))
+        );
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 1);
+        assert_eq!(connection(&app).metrics().failed_txs, 3);
+    }
+}
@@ -0,0 +1,280 @@
+use bevy::prelude::*;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use starknet::{
+    core::types::{BlockId, BlockTag, Felt, MaybePendingBlockWithTxHashes},
+    providers::{AnyProvider, Provider, ProviderError},
+};
+use tokio::task::JoinHandle;
+
+use crate::reconnect::TaskEvents;
+use crate::starknet::StarknetConnection;
+use crate::tokio::TokioRuntime;
+
+/// Interval between two reads of the latest block
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Number of blocks the average block time is computed over
+const BLOCK_TIME_SAMPLES: usize = 20;
+
+/// Number and timestamp of a block
+type BlockSample = (u64, u64);
+
+/// Header fields of a block accepted on L2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInfo {
+    pub number: u64,
+    pub block_hash: Felt,
+    /// State root after the block
+    pub new_root: Felt,
+    /// Timestamp of the block, in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Event emitted when the periodic read of the latest block finds a new block
+///
+/// Blocks are read every few seconds, so blocks produced in between are
+/// skipped: consecutive events may not have consecutive block numbers.
+#[derive(Event, Debug, Clone)]
+pub struct NewBlock {
+    pub block: BlockInfo,
+}
+
+/// Recent blocks of a `StarknetConnection`, to estimate block times
+#[derive(Default)]
+pub(crate) struct BlockTimeState {
+    /// Recent blocks, by increasing number
+    samples: VecDeque<BlockSample>,
+    latest: Option<BlockInfo>,
+    last_poll: Option<Instant>,
+    task: Option<JoinHandle<Result<Option<BlockInfo>, ProviderError>>>,
+}
+
+impl BlockTimeState {
+    /// Returns the number of running block reads
+    pub(crate) fn task_count(&self) -> usize {
+        usize::from(self.task.is_some())
+    }
+
+    /// Record the timestamp of the block `number`
+    fn record_block(&mut self, number: u64, timestamp: u64) {
+        let index = self.samples.partition_point(|(n, _)| *n < number);
+        if self.samples.get(index).is_some_and(|(n, _)| *n == number) {
+            return;
+        }
+        self.samples.insert(index, (number, timestamp));
+        if self.samples.len() > BLOCK_TIME_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the average interval between the recorded blocks
+    fn average_block_time(&self) -> Option<Duration> {
+        let (first_number, first_timestamp) = self.samples.front()?;
+        let (last_number, last_timestamp) = self.samples.back()?;
+        let blocks = last_number.checked_sub(*first_number).filter(|n| *n > 0)?;
+        let elapsed = last_timestamp.saturating_sub(*first_timestamp);
+        Some(Duration::from_secs(elapsed).div_f64(blocks as f64))
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the latest block seen by the periodic block reads
+    ///
+    /// The latest block is read every few seconds while connected, and each
+    /// new one is also emitted as a `NewBlock` event. Returns `None` before
+    /// the first read completes.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn show_root(sn: Res<StarknetConnection>) {
+    ///     if let Some(block) = sn.latest_block() {
+    ///         println!("State root at block {}: {:#x}", block.number, block.new_root);
+    ///     }
+    /// }
+    /// ```
+    pub fn latest_block(&self) -> Option<&BlockInfo> {
+        self.block_times.latest.as_ref()
+    }
+
+    /// Returns the average time between blocks, over the recently seen blocks
+    ///
+    /// The latest block is read every few seconds while connected, so the
+    /// average is available after a couple of reads. Returns `None` before that.
+    pub fn average_block_time(&self) -> Option<Duration> {
+        self.block_times.average_block_time()
+    }
+
+    /// Returns an estimate of the time a transaction takes to be confirmed
+    ///
+    /// A transaction sent now is included in one of the next blocks, so this
+    /// is the average block time of `average_block_time`. It's only an
+    /// estimate: busy blocks can delay transactions further.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn show_eta(sn: Res<StarknetConnection>) {
+    ///     if let Some(eta) = sn.estimated_confirmation_time() {
+    ///         println!("Confirming in about {}s", eta.as_secs());
+    ///     }
+    /// }
+    /// ```
+    pub fn estimated_confirmation_time(&self) -> Option<Duration> {
+        self.average_block_time()
+    }
+
+    /// Read the latest block periodically to track block times
+    pub(crate) fn poll_block_times(&mut self, runtime: &TokioRuntime, events: &mut TaskEvents) {
+        let state = &mut self.block_times;
+        if let Some(task) = state.task.take_if(|task| task.is_finished()) {
+            match runtime.runtime.block_on(task) {
+                Ok(Ok(Some(block))) => {
+                    state.record_block(block.number, block.timestamp);
+                    if state
+                        .latest
+                        .is_none_or(|latest| latest.number < block.number)
+                    {
+                        state.latest = Some(block);
+                        events.new_block.write(NewBlock { block });
+                    }
+                }
+                Ok(Err(err)) => debug!("Failed to read the latest block: {}", err),
+                _ => {}
+            }
+        }
+        if state.task.is_some()
+            || state
+                .last_poll
+                .is_some_and(|last_poll| last_poll.elapsed() < BLOCK_POLL_INTERVAL)
+        {
+            return;
+        }
+        let Some(reader) = self.reader() else {
+            return;
+        };
+        let limiter = self.limiter.clone();
+        let state = &mut self.block_times;
+        state.last_poll = Some(Instant::now());
+        state.task = Some(runtime.runtime.spawn(async move {
+            let _permit = limiter.acquire().await;
+            read_latest_block(reader.provider()).await
+        }));
+    }
+}
+
+/// Read the header of the latest block, `None` if the provider returns a pending block
+pub(crate) async fn read_latest_block(
+    provider: &AnyProvider,
+) -> Result<Option<BlockInfo>, ProviderError> {
+    let block = provider
+        .get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest))
+        .await?;
+    Ok(match block {
+        MaybePendingBlockWithTxHashes::Block(block) => Some(BlockInfo {
+            number: block.block_number,
+            block_hash: block.block_hash,
+            new_root: block.new_root,
+            timestamp: block.timestamp,
+        }),
+        MaybePendingBlockWithTxHashes::PendingBlock(_) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::query::{LatestBlockReceived, query_latest_block};
+    use serde_json::json;
+
+    #[test]
+    fn average_block_time_is_computed_from_timestamps() {
+        let mut state = BlockTimeState::default();
+        state.record_block(100, 1_000);
+        assert_eq!(state.average_block_time(), None);
+
+        // Blocks are skipped between reads and may be read out of order
+        state.record_block(110, 1_020);
+        state.record_block(103, 1_006);
+        state.record_block(103, 1_006);
+        assert_eq!(state.samples.len(), 3);
+        assert_eq!(state.average_block_time(), Some(Duration::from_secs(2)));
+
+        state.record_block(112, 1_030);
+        assert_eq!(
+            state.average_block_time(),
+            Some(Duration::from_millis(2_500))
+        );
+    }
+
+    #[test]
+    fn only_recent_blocks_are_averaged() {
+        let mut state = BlockTimeState::default();
+        for number in 0..10 {
+            state.record_block(number, number * 30);
+        }
+        for number in 10..40 {
+            state.record_block(number, 270 + (number - 9) * 6);
+        }
+        assert_eq!(state.samples.len(), BLOCK_TIME_SAMPLES);
+        assert_eq!(state.average_block_time(), Some(Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn confirmation_time_follows_block_times() {
+        let mut app = test_app();
+        assert_eq!(connection(&app).estimated_confirmation_time(), None);
+
+        with_connection(&mut app, |_, sn| {
+            for number in 0..5 {
+                sn.block_times
+                    .record_block(number, 1_700_000_000 + number * 6);
+            }
+        });
+        assert_eq!(
+            connection(&app).estimated_confirmation_time(),
+            Some(Duration::from_secs(6))
+        );
+    }
+
+    #[test]
+    fn latest_block_carries_its_hash_and_root() {
+        let mock = MockRpc::start();
+        let hash = Felt::from_hex_unchecked("0xb10c");
+        let root = Felt::from_hex_unchecked("0x5ea7");
+        mock.on(
+            "starknet_getBlockWithTxHashes",
+            block(7, hash, root, 1_700_000_000),
+        );
+        let mut app = test_app();
+        collect::<NewBlock>(&mut app);
+        collect::<LatestBlockReceived>(&mut app);
+        app.insert_resource(mock.config());
+        connect(&mut app);
+
+        assert!(update_until(&mut app, |app| {
+            connection(app).latest_block().is_some()
+        }));
+        let expected = BlockInfo {
+            number: 7,
+            block_hash: hash,
+            new_root: root,
+            timestamp: 1_700_000_000,
+        };
+        assert_eq!(connection(&app).latest_block(), Some(&expected));
+        let request = &mock.requests("starknet_getBlockWithTxHashes")[0];
+        assert_eq!(param(request, 0, "block_id"), json!("latest"));
+        app.update();
+        assert_eq!(collected::<NewBlock>(&app)[0].block, expected);
+
+        run(&mut app, query_latest_block).unwrap();
+        assert!(update_until(&mut app, |app| {
+            !collected::<LatestBlockReceived>(app).is_empty()
+        }));
+        assert_eq!(collected::<LatestBlockReceived>(&app)[0].block, expected);
+    }
+}
@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+
+use crate::approval::FeeBounds;
+use crate::display::fmt_felt;
+use crate::starknet::{StarknetConnection, TxId};
+use crate::tokio::TokioRuntime;
+
+impl StarknetConnection {
+    /// Resubmit a stuck transaction with the same nonce and higher resource bounds
+    ///
+    /// This is the method form of `bump_transaction`.
+    pub fn bump(&mut self, runtime: &TokioRuntime, tx_id: TxId, bounds: FeeBounds) -> bool {
+        if !self.is_connected() || self.is_sending(tx_id) {
+            return false;
+        }
+        let Some(stuck) = self
+            .confirming_txs
+            .iter()
+            .find(|confirming| confirming.id == tx_id)
+        else {
+            return false;
+        };
+        if !bounds.raises(&stuck.bounds) {
+            warn!(
+                "Not bumping transaction {}: the new bounds are not higher than {:?}",
+                tx_id.0, stuck.bounds
+            );
+            return false;
+        }
+        info!(
+            "Bumping transaction {} ({}) with nonce {}",
+            tx_id.0,
+            fmt_felt(&stuck.hash),
+            fmt_felt(&stuck.nonce)
+        );
+        let (calls, nonce) = (stuck.calls.clone(), stuck.nonce);
+        self.queue_send(runtime, tx_id, calls, Some(bounds), Some(nonce));
+        true
+    }
+
+    /// Returns true if the transaction being sent as `id` replaces a bumped transaction
+    pub(crate) fn is_replacing(&self, id: TxId) -> bool {
+        self.confirming_txs
+            .iter()
+            .any(|confirming| confirming.id == id)
+            || self.replaced_tx_completed(id)
+    }
+
+    /// Returns true if the bumped transaction `id` completed before its replacement was sent
+    ///
+    /// Records of transactions being sent are otherwise pending, so a final
+    /// record means the stuck transaction was included in the meantime.
+    pub(crate) fn replaced_tx_completed(&self, id: TxId) -> bool {
+        self.record(id)
+            .is_some_and(|record| record.status.is_final())
+    }
+}
+
+/// Resubmit a transaction stuck waiting for confirmation, e.g. because it is underpriced
+///
+/// The original calls are sent again with the nonce of the stuck transaction
+/// and the given resource bounds, so the sequencer replaces the stuck
+/// transaction with the new one. The transaction keeps its `TxId`.
+///
+/// The stuck transaction is still watched until the replacement is accepted,
+/// then its record gets the hash and bounds of the replacement and a new
+/// `TransactionSubmitted` event is emitted. If the replacement is rejected, for
+/// example because the stuck transaction was included first, the stuck
+/// transaction keeps being watched and completes as usual.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `tx_id` - The id of the stuck transaction
+/// * `bounds` - The new resource bounds, see `FeeBounds::raises`
+///
+/// # Returns
+///
+/// False if not connected, if `tx_id` isn't waiting for confirmation, if it is
+/// already being bumped, or if `bounds` aren't higher than the bounds it was
+/// sent with
+///
+/// # Example
+///
+/// ```no_run
+/// fn bump_stuck(
+///     runtime: Res<TokioRuntime>,
+///     sn: ResMut<StarknetConnection>,
+///     stuck: TxId,
+///     estimate: &FeeEstimate,
+/// ) {
+///     let mut bounds = FeeBounds::from(estimate);
+///     bounds.l2_gas_price *= 2;
+///     bump_transaction(runtime, sn, stuck, bounds);
+/// }
+/// ```
+pub fn bump_transaction(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    tx_id: TxId,
+    bounds: FeeBounds,
+) -> bool {
+    sn.bump(&runtime, tx_id, bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approval::ESTIMATE_MARGIN;
+    use crate::mock::*;
+    use crate::record::TxStatus;
+    use crate::sink::TransactionConfirmed;
+    use crate::starknet::{ConfirmationPolling, SubmitOutcome, TransactionSubmitted};
+    use serde_json::json;
+    use starknet::core::types::{FeeEstimate, Felt};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    const STUCK: &str = "0x100";
+
+    /// Answer the status of the stuck transaction with `RECEIVED` until `included` is set
+    fn stuck_until(mock: &MockRpc, included: Arc<AtomicBool>) {
+        mock.on_fn("starknet_getTransactionStatus", move |params| {
+            let hash = param(params, 0, "transaction_hash");
+            Ok(if hash == STUCK && !included.load(Ordering::SeqCst) {
+                json!({ "finality_status": "RECEIVED" })
+            } else {
+                json!({ "finality_status": "ACCEPTED_ON_L2", "execution_status": "SUCCEEDED" })
+            })
+        });
+    }
+
+    /// Send a transaction and wait until it is stuck waiting for confirmation
+    fn send_stuck(mock: &MockRpc) -> (App, TxId, FeeBounds) {
+        let mut app = connected_app(mock);
+        collect::<TransactionSubmitted>(&mut app);
+        collect::<TransactionConfirmed>(&mut app);
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.set_confirmation_polling(ConfirmationPolling {
+                min_interval: Duration::from_millis(10),
+                max_interval: Duration::from_millis(10),
+                dropped_after: Duration::from_secs(60),
+                ..Default::default()
+            });
+            sn.execute(runtime, vec![call(1)])
+        });
+        let SubmitOutcome::Queued(tx_id) = outcome else {
+            panic!("transaction not queued: {outcome:?}");
+        };
+        assert!(update_until(&mut app, |app| {
+            collected::<TransactionSubmitted>(app).len() == 1
+        }));
+        let estimate: FeeEstimate = serde_json::from_value(fee_estimate()).unwrap();
+        (
+            app,
+            tx_id,
+            FeeBounds::with_margin(&estimate, ESTIMATE_MARGIN),
+        )
+    }
+
+    fn sent(mock: &MockRpc) -> Vec<serde_json::Value> {
+        mock.requests("starknet_addInvokeTransaction")
+            .iter()
+            .map(|request| param(request, 0, "invoke_transaction"))
+            .collect()
+    }
+
+    #[test]
+    fn bump_resends_with_the_same_nonce_and_a_higher_fee() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on("starknet_getNonce", json!("0x7"));
+        stuck_until(&mock, Arc::new(AtomicBool::new(false)));
+        let (mut app, tx_id, original) = send_stuck(&mock);
+
+        assert!(!with_connection(&mut app, |runtime, sn| {
+            sn.bump(runtime, tx_id, original)
+        }));
+        let mut bumped = original;
+        bumped.l2_gas_price *= 2;
+        assert!(with_connection(&mut app, |runtime, sn| {
+            sn.bump(runtime, tx_id, bumped)
+        }));
+        assert_eq!(connection(&app).pending_tx_count(), 1);
+        assert!(!with_connection(&mut app, |runtime, sn| {
+            sn.bump(runtime, tx_id, bumped)
+        }));
+
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionConfirmed>(app).is_empty()
+        }));
+        let sent = sent(&mock);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0]["nonce"], "0x7");
+        assert_eq!(sent[1]["nonce"], "0x7");
+        assert_eq!(
+            sent[0]["resource_bounds"]["l2_gas"]["max_price_per_unit"],
+            "0x1"
+        );
+        assert_eq!(
+            sent[1]["resource_bounds"]["l2_gas"]["max_price_per_unit"],
+            "0x2"
+        );
+
+        let submitted = collected::<TransactionSubmitted>(&app);
+        assert_eq!(submitted.len(), 2);
+        assert_eq!(submitted[1].bounds, bumped.resource_bounds());
+        let confirmed = &collected::<TransactionConfirmed>(&app)[0];
+        assert_eq!(confirmed.tx_id, tx_id);
+        assert_eq!(confirmed.hash, Felt::from(0x101u64));
+        let record = connection(&app).record(tx_id).unwrap();
+        assert_eq!(record.status, TxStatus::Confirmed);
+        assert_eq!(record.hash, Some(Felt::from(0x101u64)));
+        assert_eq!(connection(&app).metrics().confirmed_txs, 1);
+    }
+
+    #[test]
+    fn stuck_transaction_is_watched_until_replaced() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let included = Arc::new(AtomicBool::new(false));
+        stuck_until(&mock, included.clone());
+        let (mut app, tx_id, original) = send_stuck(&mock);
+
+        mock.on_error(
+            "starknet_addInvokeTransaction",
+            RpcError::new(55, "Account validation failed"),
+        );
+        let mut bumped = original;
+        bumped.l2_gas_price *= 2;
+        assert!(with_connection(&mut app, |runtime, sn| {
+            sn.bump(runtime, tx_id, bumped)
+        }));
+        assert!(update_until(&mut app, |app| !connection(app).is_sending(tx_id)));
+        let record = connection(&app).record(tx_id).unwrap();
+        assert_eq!(record.status, TxStatus::Sent);
+        assert_eq!(record.hash, Some(Felt::from(0x100u64)));
+        assert_eq!(connection(&app).metrics().failed_txs, 0);
+
+        included.store(true, Ordering::SeqCst);
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionConfirmed>(app).is_empty()
+        }));
+        assert_eq!(
+            collected::<TransactionConfirmed>(&app)[0].hash,
+            Felt::from(0x100u64)
+        );
+        assert_eq!(
+            connection(&app).record(tx_id).unwrap().status,
+            TxStatus::Confirmed
+        );
+    }
+}
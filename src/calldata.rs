@@ -0,0 +1,234 @@
+use std::fmt;
+
+use starknet::core::{
+    types::{Call, Felt, U256},
+    utils::get_selector_from_name,
+};
+
+/// Serialization of Rust values to calldata, following Cairo's `Serde`
+///
+/// Values map to felts as follows:
+///
+/// | Rust type | Cairo type | Felts |
+/// |-----------|------------|-------|
+/// | `Felt` | `felt252`, `ContractAddress`, `ClassHash` | 1 |
+/// | `bool` | `bool` | 1 |
+/// | `u8` to `u128`, `usize` | `u8` to `u128`, `usize` | 1 |
+/// | `i8` to `i128` | `i8` to `i128` | 1, negative values wrap around the field |
+/// | `U256` | `u256` | 2, `[low, high]` |
+/// | `[T]`, `Vec<T>` | `Array<T>`, `Span<T>` | length, then each element |
+///
+/// `u256` is the common pitfall: it's a struct of two `u128`, so a single
+/// felt is rejected by the contract. Use `U256` for any value that may not fit
+/// in 128 bits, such as token amounts.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::calldata::ToCalldata;
+/// use starknet::core::types::U256;
+///
+/// let mut calldata = recipient.to_calldata();
+/// U256::from(amount).append_calldata(&mut calldata);
+/// let transfer = Call { to: token, selector: selector!("transfer"), calldata };
+/// ```
+pub trait ToCalldata {
+    /// Append the serialization of `self` to `calldata`
+    fn append_calldata(&self, calldata: &mut Vec<Felt>);
+
+    /// Returns the serialization of `self`
+    fn to_calldata(&self) -> Vec<Felt> {
+        let mut calldata = Vec::new();
+        self.append_calldata(&mut calldata);
+        calldata
+    }
+}
+
+impl ToCalldata for Felt {
+    fn append_calldata(&self, calldata: &mut Vec<Felt>) {
+        calldata.push(*self);
+    }
+}
+
+impl ToCalldata for bool {
+    fn append_calldata(&self, calldata: &mut Vec<Felt>) {
+        calldata.push(Felt::from(*self as u8));
+    }
+}
+
+macro_rules! impl_to_calldata_for_integers {
+    ($($ty:ty),*) => {
+        $(
+            impl ToCalldata for $ty {
+                fn append_calldata(&self, calldata: &mut Vec<Felt>) {
+                    calldata.push(Felt::from(*self));
+                }
+            }
+        )*
+    };
+}
+
+impl_to_calldata_for_integers!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128);
+
+impl ToCalldata for U256 {
+    fn append_calldata(&self, calldata: &mut Vec<Felt>) {
+        calldata.push(Felt::from(self.low()));
+        calldata.push(Felt::from(self.high()));
+    }
+}
+
+impl<T: ToCalldata> ToCalldata for [T] {
+    fn append_calldata(&self, calldata: &mut Vec<Felt>) {
+        calldata.push(Felt::from(self.len()));
+        for value in self {
+            value.append_calldata(calldata);
+        }
+    }
+}
+
+impl<T: ToCalldata> ToCalldata for Vec<T> {
+    fn append_calldata(&self, calldata: &mut Vec<Felt>) {
+        self.as_slice().append_calldata(calldata);
+    }
+}
+
+impl<T: ToCalldata + ?Sized> ToCalldata for &T {
+    fn append_calldata(&self, calldata: &mut Vec<Felt>) {
+        (**self).append_calldata(calldata);
+    }
+}
+
+/// Parse a felt written in hex, with a `0x` prefix, or in decimal
+///
+/// Addresses are usually copied in hex from explorers, while some tools print
+/// decimal felts, so both forms are accepted. Surrounding whitespace is
+/// ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::calldata::parse_felt;
+///
+/// assert_eq!(parse_felt("0x2a"), parse_felt("42"));
+/// ```
+pub fn parse_felt(value: &str) -> Option<Felt> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => Felt::from_hex(&format!("0x{hex}")).ok(),
+        None => Felt::from_dec_str(value).ok(),
+    }
+}
+
+/// Error returned by `parse_call` when a string isn't a valid felt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCallError {
+    /// The contract address isn't a hex or decimal felt
+    InvalidAddress(String),
+    /// The selector is neither a hex or decimal felt nor an entrypoint name
+    InvalidSelector(String),
+    /// A calldata element isn't a hex or decimal felt
+    InvalidCalldata { index: usize, value: String },
+}
+
+impl fmt::Display for ParseCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseCallError::InvalidAddress(value) => write!(f, "invalid address `{value}`"),
+            ParseCallError::InvalidSelector(value) => write!(f, "invalid selector `{value}`"),
+            ParseCallError::InvalidCalldata { index, value } => {
+                write!(f, "invalid calldata element {index} `{value}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseCallError {}
+
+/// Build a `Call` from strings, as found in config files or pasted by users
+///
+/// The address and calldata are parsed with `parse_felt`, so hex and decimal
+/// forms give the same call. The selector is either a felt in one of these
+/// forms or an entrypoint name, such as `transfer`, turned into its selector.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::calldata::parse_call;
+///
+/// let call = parse_call("0x1234", "transfer", &["0x5678", "100", "0"])?;
+/// ```
+pub fn parse_call(to: &str, selector: &str, calldata: &[&str]) -> Result<Call, ParseCallError> {
+    let to = parse_felt(to).ok_or_else(|| ParseCallError::InvalidAddress(to.to_string()))?;
+    let selector = parse_felt(selector)
+        .or_else(|| get_selector_from_name(selector.trim()).ok())
+        .ok_or_else(|| ParseCallError::InvalidSelector(selector.to_string()))?;
+    let calldata = calldata
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            parse_felt(value).ok_or_else(|| ParseCallError::InvalidCalldata {
+                index,
+                value: value.to_string(),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Call {
+        to,
+        selector,
+        calldata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u256_is_two_felts_and_u128_is_one() {
+        let value = U256::from_words(5, 7);
+        assert_eq!(value.to_calldata(), [Felt::from(5u8), Felt::from(7u8)]);
+        assert_eq!(5u128.to_calldata(), [Felt::from(5u8)]);
+
+        // A value that fits in a `u128` still takes two felts as a `u256`
+        assert_eq!(
+            U256::from(u128::MAX).to_calldata(),
+            [Felt::from(u128::MAX), Felt::ZERO]
+        );
+        assert_eq!(u128::MAX.to_calldata(), [Felt::from(u128::MAX)]);
+    }
+
+    #[test]
+    fn hex_and_decimal_give_the_same_call() {
+        let hex = parse_call("0x2a", "0x5e1", &["0x10", " 0XfF ", "0x0"]).unwrap();
+        let decimal = parse_call("42", "1505", &["16", "255", "0"]).unwrap();
+        assert_eq!(hex.to, decimal.to);
+        assert_eq!(hex.selector, decimal.selector);
+        assert_eq!(hex.calldata, decimal.calldata);
+        assert_eq!(hex.to, Felt::from(42u8));
+        assert_eq!(
+            hex.calldata,
+            [Felt::from(16u8), Felt::from(255u8), Felt::ZERO]
+        );
+
+        let named = parse_call("42", "transfer", &[]).unwrap();
+        assert_eq!(named.selector, get_selector_from_name("transfer").unwrap());
+    }
+
+    #[test]
+    fn invalid_felts_are_reported() {
+        assert_eq!(
+            parse_call("0xzz", "transfer", &[]).unwrap_err(),
+            ParseCallError::InvalidAddress("0xzz".to_string())
+        );
+        assert_eq!(
+            parse_call("42", "transfer", &["1", "12a"]).unwrap_err(),
+            ParseCallError::InvalidCalldata {
+                index: 1,
+                value: "12a".to_string(),
+            }
+        );
+    }
+}
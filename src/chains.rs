@@ -0,0 +1,264 @@
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use starknet::core::types::{Call, Felt};
+
+use crate::reconnect::TaskEvents;
+use crate::starknet::{ConnectOutcome, DefaultStarknetConfig, StarknetConnection, SubmitOutcome};
+use crate::tokio::TokioRuntime;
+
+/// Name under which a chain is registered in `StarknetChains`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChainKey(pub String);
+
+impl From<&str> for ChainKey {
+    fn from(key: &str) -> Self {
+        Self(key.to_string())
+    }
+}
+
+impl From<String> for ChainKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl fmt::Display for ChainKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+struct Chain {
+    config: DefaultStarknetConfig,
+    connection: StarknetConnection,
+}
+
+/// Resource holding connections to additional named chains
+///
+/// Games that bridge activity across L2s or app-chains can register one entry
+/// per chain, each with its own configuration, provider and account. Every
+/// chain tracks its own pending and confirming transactions, polled by the
+/// `check_chain_tasks` system registered by the `BevyDojoPlugin`.
+///
+/// The default `StarknetConnection` resource is unaffected by this resource.
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(runtime: Res<TokioRuntime>, mut chains: ResMut<StarknetChains>) {
+///     chains.add_chain("sepolia", DefaultStarknetConfig {
+///         rpc_url: "https://starknet-sepolia.public.blastapi.io".to_string(),
+///         account_address: "0x123...".to_string(),
+///         private_key: "0x456...".to_string(),
+///         proxy: None,
+///     });
+///     chains.add_chain("appchain", DefaultStarknetConfig {
+///         rpc_url: "https://rpc.my-appchain.xyz".to_string(),
+///         account_address: "0x789...".to_string(),
+///         private_key: "0xabc...".to_string(),
+///         proxy: None,
+///     });
+///     chains.connect_all(&runtime);
+/// }
+///
+/// fn play_on_appchain(runtime: Res<TokioRuntime>, chains: ResMut<StarknetChains>) {
+///     execute_transaction_on(runtime, chains, &"appchain".into(), vec![/* calls */]);
+/// }
+/// ```
+#[derive(Resource, Default)]
+pub struct StarknetChains {
+    chains: HashMap<ChainKey, Chain>,
+}
+
+impl StarknetChains {
+    /// Register a chain, replacing any chain previously registered under `key`
+    pub fn add_chain(&mut self, key: impl Into<ChainKey>, config: DefaultStarknetConfig) {
+        self.chains.insert(
+            key.into(),
+            Chain {
+                config,
+                connection: StarknetConnection::default(),
+            },
+        );
+    }
+
+    /// Remove a chain, dropping its connection
+    pub fn remove_chain(&mut self, key: &ChainKey) -> bool {
+        self.chains.remove(key).is_some()
+    }
+
+    /// Start connecting to the chain registered under `key`
+    ///
+    /// Returns `None` if no chain is registered under `key`.
+    pub fn connect(&mut self, runtime: &TokioRuntime, key: &ChainKey) -> Option<ConnectOutcome> {
+        let chain = self.chains.get_mut(key)?;
+        Some(chain.connection.connect(runtime, &chain.config))
+    }
+
+    /// Start connecting to every registered chain
+    pub fn connect_all(&mut self, runtime: &TokioRuntime) {
+        for chain in self.chains.values_mut() {
+            chain.connection.connect(runtime, &chain.config);
+        }
+    }
+
+    /// Returns the keys of all registered chains
+    pub fn keys(&self) -> impl Iterator<Item = &ChainKey> {
+        self.chains.keys()
+    }
+
+    /// Returns the configuration of the chain registered under `key`
+    pub fn config(&self, key: &ChainKey) -> Option<&DefaultStarknetConfig> {
+        self.chains.get(key).map(|chain| &chain.config)
+    }
+
+    /// Returns the connection of the chain registered under `key`
+    pub fn connection(&self, key: &ChainKey) -> Option<&StarknetConnection> {
+        self.chains.get(key).map(|chain| &chain.connection)
+    }
+
+    /// Returns the connection of the chain registered under `key` mutably
+    pub fn connection_mut(&mut self, key: &ChainKey) -> Option<&mut StarknetConnection> {
+        self.chains.get_mut(key).map(|chain| &mut chain.connection)
+    }
+
+    /// Returns the connections of every registered chain
+    pub(crate) fn connections_mut(&mut self) -> impl Iterator<Item = &mut StarknetConnection> {
+        self.chains.values_mut().map(|chain| &mut chain.connection)
+    }
+
+    /// Returns the chain id of the chain registered under `key`, once connected
+    pub fn chain_id(&self, key: &ChainKey) -> Option<Felt> {
+        self.connection(key)?.chain_id()
+    }
+
+    /// Queue a transaction on the chain registered under `key`
+    ///
+    /// This is the method form of `execute_transaction_on`.
+    pub fn execute(
+        &mut self,
+        runtime: &TokioRuntime,
+        key: &ChainKey,
+        calls: Vec<Call>,
+    ) -> SubmitOutcome {
+        match self.connection_mut(key) {
+            Some(connection) => connection.execute(runtime, calls),
+            None => SubmitOutcome::UnknownChain,
+        }
+    }
+}
+
+/// Execute a Starknet transaction on a chain registered in `StarknetChains`
+///
+/// This works like `execute_transaction`, but routes the calls to the
+/// connection of the chain registered under `chain`.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `chains` - The chains resource
+/// * `chain` - The key of the chain to execute on
+/// * `calls` - A vector of Starknet calls to execute
+///
+/// # Returns
+///
+/// The same outcomes as `execute_transaction`, or `SubmitOutcome::UnknownChain`
+/// if no chain is registered under `chain`
+pub fn execute_transaction_on(
+    runtime: Res<TokioRuntime>,
+    mut chains: ResMut<StarknetChains>,
+    chain: &ChainKey,
+    calls: Vec<Call>,
+) -> SubmitOutcome {
+    chains.execute(&runtime, chain, calls)
+}
+
+/// System that checks the tasks of every chain in `StarknetChains`
+///
+/// It is automatically registered by the `BevyDojoPlugin`.
+pub fn check_chain_tasks(
+    runtime: Res<TokioRuntime>,
+    mut chains: ResMut<StarknetChains>,
+    mut events: TaskEvents,
+) {
+    for chain in chains.chains.values_mut() {
+        chain.connection.poll_tasks(&runtime, &mut events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use serde_json::json;
+
+    #[test]
+    fn chains_connect_and_execute_separately() {
+        let sepolia = MockRpc::start();
+        sepolia.accept_transactions(1000);
+        let mainnet = MockRpc::start();
+        mainnet.on("starknet_chainId", json!("0x534e5f4d41494e"));
+        mainnet.accept_transactions(2000);
+
+        let mut app = test_app();
+        app.world_mut()
+            .resource_scope(|world, mut chains: Mut<StarknetChains>| {
+                chains.add_chain("sepolia", sepolia.config());
+                chains.add_chain("mainnet", mainnet.config());
+                chains.connect_all(world.resource::<TokioRuntime>());
+            });
+        let connected = |app: &App, key: &str| {
+            app.world()
+                .resource::<StarknetChains>()
+                .connection(&key.into())
+                .is_some_and(StarknetConnection::is_connected)
+        };
+        assert!(update_until(&mut app, |app| {
+            connected(app, "sepolia") && connected(app, "mainnet")
+        }));
+
+        let chains = app.world().resource::<StarknetChains>();
+        assert_eq!(
+            chains.chain_id(&"sepolia".into()),
+            Some(Felt::from_hex_unchecked("0x534e5f5345504f4c4941"))
+        );
+        assert_eq!(
+            chains.chain_id(&"mainnet".into()),
+            Some(Felt::from_hex_unchecked("0x534e5f4d41494e"))
+        );
+        assert!(!connection(&app).is_connected());
+
+        app.world_mut()
+            .resource_scope(|world, mut chains: Mut<StarknetChains>| {
+                let runtime = world.resource::<TokioRuntime>();
+                assert!(
+                    chains
+                        .execute(runtime, &"sepolia".into(), vec![call(0)])
+                        .is_queued()
+                );
+                assert!(
+                    chains
+                        .execute(runtime, &"mainnet".into(), vec![call(1)])
+                        .is_queued()
+                );
+                assert_eq!(
+                    chains.execute(runtime, &"appchain".into(), vec![call(2)]),
+                    SubmitOutcome::UnknownChain
+                );
+            });
+        let fee_spent = |app: &App, key: &str| {
+            app.world()
+                .resource::<StarknetChains>()
+                .connection(&key.into())
+                .map(|connection| connection.metrics().total_fee_spent)
+        };
+        assert!(update_until(&mut app, |app| {
+            fee_spent(app, "sepolia") == Some(1000) && fee_spent(app, "mainnet") == Some(2000)
+        }));
+        assert_eq!(sepolia.count("starknet_addInvokeTransaction"), 1);
+        assert_eq!(mainnet.count("starknet_addInvokeTransaction"), 1);
+    }
+}
@@ -0,0 +1,101 @@
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::starknet::DefaultStarknetConfig;
+
+/// Address of the first account predeployed and funded by `katana`
+pub const KATANA_ACCOUNT_ADDRESS: &str =
+    "0x127fd5f1fe78a71f8bcd1fec63e3fe2f0486b6ecd5c86a0466c3a21fa5cfcec";
+
+/// Private key of the first account predeployed and funded by `katana`
+pub const KATANA_PRIVATE_KEY: &str =
+    "0xc5b2fcab997346f3ea1c00b002ecf6f382c5f9c9659a3894eb783c5320f912";
+
+/// How long to wait for `katana` to accept connections
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A local `katana` devnet for integration tests
+///
+/// Requires the `devnet-tests` feature. The devnet runs on a free local port
+/// with katana's default predeployed accounts, the first of which is funded
+/// and used by `config`. The process is killed when the devnet is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::devnet::KatanaDevnet;
+///
+/// #[test]
+/// fn confirms_on_devnet() {
+///     let Some(devnet) = KatanaDevnet::spawn() else {
+///         eprintln!("katana is not installed, skipping");
+///         return;
+///     };
+///     let mut app = App::new();
+///     app.add_plugins((MinimalPlugins, BevyDojoPlugin))
+///         .insert_resource(devnet.config());
+///     // ... connect, execute and update the app until the transaction confirms
+/// }
+/// ```
+pub struct KatanaDevnet {
+    process: Child,
+    port: u16,
+}
+
+impl KatanaDevnet {
+    /// Launch `katana` and wait until it accepts connections
+    ///
+    /// Returns `None` if `katana` isn't installed or doesn't start in time, so
+    /// tests can skip gracefully.
+    pub fn spawn() -> Option<Self> {
+        let port = free_port()?;
+        let process = Command::new("katana")
+            .args(["--http.port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut devnet = Self { process, port };
+
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let started_at = Instant::now();
+        while TcpStream::connect_timeout(&address, Duration::from_millis(100)).is_err() {
+            let exited = devnet.process.try_wait().ok().flatten().is_some();
+            if exited || started_at.elapsed() > STARTUP_TIMEOUT {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Some(devnet)
+    }
+
+    /// Returns the URL of the devnet's JSON-RPC endpoint
+    pub fn rpc_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Returns a configuration connecting the first predeployed account to the devnet
+    pub fn config(&self) -> DefaultStarknetConfig {
+        DefaultStarknetConfig {
+            rpc_url: self.rpc_url(),
+            account_address: KATANA_ACCOUNT_ADDRESS.to_string(),
+            private_key: KATANA_PRIVATE_KEY.to_string(),
+            proxy: None,
+        }
+    }
+}
+
+impl Drop for KatanaDevnet {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Returns a local port that is currently free
+fn free_port() -> Option<u16> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).ok()?;
+    Some(listener.local_addr().ok()?.port())
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use starknet::core::{types::Felt, utils::parse_cairo_short_string};
+
+/// Format used to render felts in logs and in the `Display` impls of events
+static FELT_DISPLAY: AtomicU8 = AtomicU8::new(FeltDisplay::Hex as u8);
+
+/// How felts are rendered in logs and in the `Display` impls of events
+///
+/// The format is global to the process, since felts are also logged from
+/// background tasks. Defaults to `Hex`.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::display::{FeltDisplay, set_felt_display};
+///
+/// set_felt_display(FeltDisplay::HexPadded);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum FeltDisplay {
+    /// Hex without leading zeros, e.g. `0x4a`
+    #[default]
+    Hex,
+    /// Hex padded to 64 digits, e.g. `0x00…004a`
+    HexPadded,
+    /// Decimal, e.g. `74`
+    Decimal,
+    /// Cairo short string if the felt is one, hex otherwise, e.g. `J`
+    ShortString,
+}
+
+/// Sets how felts are rendered in logs and events
+pub fn set_felt_display(display: FeltDisplay) {
+    FELT_DISPLAY.store(display as u8, Ordering::Relaxed);
+}
+
+/// Returns how felts are rendered in logs and events
+pub fn felt_display() -> FeltDisplay {
+    match FELT_DISPLAY.load(Ordering::Relaxed) {
+        1 => FeltDisplay::HexPadded,
+        2 => FeltDisplay::Decimal,
+        3 => FeltDisplay::ShortString,
+        _ => FeltDisplay::Hex,
+    }
+}
+
+/// A felt rendered with a `FeltDisplay`
+///
+/// Created with `fmt_felt` to follow the configured format, or with
+/// `FeltDisplay::fmt_felt` to use a specific one.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayFelt<'a> {
+    felt: &'a Felt,
+    display: FeltDisplay,
+}
+
+impl FeltDisplay {
+    /// Render `felt` with this format
+    pub fn fmt_felt(self, felt: &Felt) -> DisplayFelt<'_> {
+        DisplayFelt {
+            felt,
+            display: self,
+        }
+    }
+}
+
+/// Render `felt` with the format set by `set_felt_display`
+pub fn fmt_felt(felt: &Felt) -> DisplayFelt<'_> {
+    felt_display().fmt_felt(felt)
+}
+
+impl fmt::Display for DisplayFelt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.display {
+            FeltDisplay::Hex => write!(f, "{:#x}", self.felt),
+            FeltDisplay::HexPadded => write!(f, "{:#066x}", self.felt),
+            FeltDisplay::Decimal => write!(f, "{}", self.felt),
+            FeltDisplay::ShortString => match parse_cairo_short_string(self.felt) {
+                Ok(string) if !string.is_empty() && !string.chars().any(char::is_control) => {
+                    write!(f, "{string}")
+                }
+                _ => write!(f, "{:#x}", self.felt),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::core::utils::cairo_short_string_to_felt;
+
+    fn render(display: FeltDisplay, felt: Felt) -> String {
+        display.fmt_felt(&felt).to_string()
+    }
+
+    #[test]
+    fn each_mode_renders_a_felt() {
+        let felt = Felt::from(0x4au8);
+        assert_eq!(render(FeltDisplay::Hex, felt), "0x4a");
+        assert_eq!(
+            render(FeltDisplay::HexPadded, felt),
+            format!("0x{}4a", "0".repeat(62))
+        );
+        assert_eq!(render(FeltDisplay::Decimal, felt), "74");
+        assert_eq!(render(FeltDisplay::ShortString, felt), "J");
+    }
+
+    #[test]
+    fn short_string_falls_back_to_hex() {
+        let name = cairo_short_string_to_felt("STRK").unwrap();
+        assert_eq!(render(FeltDisplay::ShortString, name), "STRK");
+        // Not printable
+        assert_eq!(render(FeltDisplay::ShortString, Felt::ONE), "0x1");
+        assert_eq!(render(FeltDisplay::ShortString, Felt::ZERO), "0x0");
+        // Longer than 31 bytes
+        assert_eq!(
+            render(FeltDisplay::ShortString, Felt::MAX),
+            format!("{:#x}", Felt::MAX)
+        );
+    }
+
+    #[test]
+    fn fmt_felt_follows_the_configured_mode() {
+        let felt = Felt::from(0x4au8);
+        set_felt_display(FeltDisplay::Decimal);
+        assert_eq!(felt_display(), FeltDisplay::Decimal);
+        assert_eq!(fmt_felt(&felt).to_string(), "74");
+        set_felt_display(FeltDisplay::default());
+        assert_eq!(fmt_felt(&felt).to_string(), "0x4a");
+    }
+}
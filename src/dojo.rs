@@ -0,0 +1,142 @@
+use starknet::core::types::Felt;
+
+use crate::hash::HashFunction;
+
+/// Upper bound of Starknet storage base addresses, `2^251 - 256`
+const STORAGE_BASE_ADDRESS_BOUND: Felt =
+    Felt::from_hex_unchecked("0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00");
+
+/// Compute the id of a Dojo entity from its keys
+///
+/// This is the Poseidon hash of the serialized keys, as computed by
+/// `dojo::utils::entity_id_from_keys`.
+pub fn dojo_entity_id(keys: &[Felt]) -> Felt {
+    HashFunction::Poseidon.hash_many(keys)
+}
+
+/// Compute the storage address of a model member in a Dojo world contract
+///
+/// Dojo 1.x stores each member of a model under its own key,
+/// `combine_key(entity_id, member_selector)`, the Poseidon hash of the entity
+/// id and the member's selector. The member's felts are then stored from the
+/// base address `poseidon(model_selector, member_key)`, reduced to a storage
+/// base address, one felt per slot: a `u256` member takes the returned address
+/// and the next one.
+///
+/// This covers the members of a model with a fixed-size layout. Members of
+/// nested structs are keyed by combining the key once more with the nested
+/// member's selector.
+///
+/// Unlike the other hashing utilities, this takes no `HashFunction`: the world
+/// contract always derives these keys and addresses with Poseidon, so any other
+/// function would compute an address nothing is stored at.
+///
+/// The address can be read with `query_storage` on the world contract, which
+/// reads model members directly without going through Torii.
+///
+/// # Arguments
+///
+/// * `model_selector` - The selector of the model, as registered in the world
+/// * `keys` - The serialized keys of the entity
+/// * `member_selector` - The selector of the member, `selector!("<member name>")`
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::dojo::dojo_model_storage_address;
+///
+/// // The `y` member of the `Position` model of `player`
+/// let address = dojo_model_storage_address(position_selector, &[player], selector!("y"));
+/// query_storage(runtime, sn, world_address, address);
+/// ```
+pub fn dojo_model_storage_address(
+    model_selector: Felt,
+    keys: &[Felt],
+    member_selector: Felt,
+) -> Felt {
+    let member_key = combine_key(dojo_entity_id(keys), member_selector);
+    storage_base_address(HashFunction::Poseidon.hash_many(&[model_selector, member_key]))
+}
+
+/// Combine a parent key with a child key, like Dojo's `combine_key`
+fn combine_key(parent: Felt, child: Felt) -> Felt {
+    HashFunction::Poseidon.hash_many(&[parent, child])
+}
+
+/// Reduce `felt` to a storage base address, like Cairo's `storage_base_address_from_felt252`
+fn storage_base_address(felt: Felt) -> Felt {
+    if felt >= STORAGE_BASE_ADDRESS_BOUND {
+        felt - STORAGE_BASE_ADDRESS_BOUND
+    } else {
+        felt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::macros::selector;
+
+    const MODEL: Felt = Felt::from_hex_unchecked("0x1234abcd");
+
+    #[test]
+    fn entity_id_is_the_poseidon_hash_of_the_keys() {
+        assert_eq!(
+            dojo_entity_id(&[Felt::from(0x5678u64)]),
+            Felt::from_hex_unchecked(
+                "0x663b4911594be275661b324a74fa929914a63c826490e9f5da5e91fbd8fe7ed"
+            )
+        );
+    }
+
+    #[test]
+    fn members_are_stored_under_their_own_key() {
+        let player = [Felt::from(0x5678u64)];
+        let x = dojo_model_storage_address(MODEL, &player, selector!("x"));
+        let y = dojo_model_storage_address(MODEL, &player, selector!("y"));
+        assert_eq!(
+            x,
+            Felt::from_hex_unchecked(
+                "0x26677b006c2eba9cf96b370e7dece1c4395b01c0670488ec941eee3416adc24"
+            )
+        );
+        assert_eq!(
+            y,
+            Felt::from_hex_unchecked(
+                "0x482588acc729ebc05b13c0df69ffffbfee4b44b22e63c89ce28a4873c91e304"
+            )
+        );
+
+        let keys = [Felt::ONE, Felt::TWO];
+        assert_eq!(
+            dojo_model_storage_address(MODEL, &keys, selector!("x")),
+            Felt::from_hex_unchecked(
+                "0x7f002b445bc2a217a1c260cb9ac9b96c7b0479db58676d551c74b55b6dc22d4"
+            )
+        );
+    }
+
+    #[test]
+    fn member_address_follows_combine_key() {
+        let keys = [Felt::from(7u8), Felt::from(8u8)];
+        let entity_id = dojo_entity_id(&keys);
+        let member_key = combine_key(entity_id, selector!("health"));
+        assert_eq!(
+            member_key,
+            starknet_crypto::poseidon_hash_many(&[entity_id, selector!("health")])
+        );
+        assert_eq!(
+            dojo_model_storage_address(MODEL, &keys, selector!("health")),
+            storage_base_address(starknet_crypto::poseidon_hash_many(&[MODEL, member_key]))
+        );
+    }
+
+    #[test]
+    fn base_addresses_are_reduced() {
+        assert_eq!(storage_base_address(Felt::ONE), Felt::ONE);
+        assert_eq!(
+            storage_base_address(STORAGE_BASE_ADDRESS_BOUND + Felt::TWO),
+            Felt::TWO
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+
+use starknet::core::types::Felt;
+
+use crate::record::TxStatus;
+use crate::sink::{TransactionConfirmed, TransactionFailed};
+use crate::starknet::{StarknetConnection, TxId};
+
+/// Component binding an entity to a transaction that hasn't resolved yet
+///
+/// Once the transaction is confirmed or fails, the `update_pending_transactions`
+/// system removes this component and, if `insert_result` is set, inserts a
+/// `TransactionResult` on the entity. Systems can then drive the entity from
+/// the outcome with `Added<TransactionResult>` or `RemovedComponents`.
+///
+/// Outcomes are read from the `TransactionConfirmed` and `TransactionFailed`
+/// events, so entities aren't updated while a `CustomTransactionSink` replaces
+/// them. A `PendingTransaction` added after these events were emitted is
+/// resolved from the `recent_txs` of the `StarknetConnection` instead; if the
+/// record was already trimmed from the history, or the transaction was sent
+/// through `StarknetChains`, the component stays until removed by the game.
+///
+/// # Example
+///
+/// ```no_run
+/// fn build(mut commands: Commands, runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     if let SubmitOutcome::Queued(tx_id) = execute_transaction(runtime, sn, calls) {
+///         commands.spawn((Building, PendingTransaction::new(tx_id)));
+///     }
+/// }
+///
+/// fn finish_buildings(buildings: Query<(Entity, &TransactionResult), Added<TransactionResult>>) {
+///     for (entity, result) in &buildings {
+///         if result.is_confirmed() {
+///             println!("Building {entity} constructed on-chain");
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingTransaction {
+    pub tx_id: TxId,
+    /// Whether to insert a `TransactionResult` once the transaction resolves
+    pub insert_result: bool,
+}
+
+impl PendingTransaction {
+    /// Track the transaction `tx_id`, inserting its `TransactionResult` once resolved
+    pub fn new(tx_id: TxId) -> Self {
+        Self {
+            tx_id,
+            insert_result: true,
+        }
+    }
+}
+
+/// Component holding the outcome of the transaction of a `PendingTransaction`
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct TransactionResult {
+    pub tx_id: TxId,
+    /// Hash of the transaction, `None` if it couldn't be sent
+    pub hash: Option<Felt>,
+    /// Either `TxStatus::Confirmed`, `TxStatus::Reverted`, `TxStatus::Failed`,
+    /// `TxStatus::Dropped` or `TxStatus::Rejected`
+    pub status: TxStatus,
+}
+
+impl TransactionResult {
+    /// Returns true if the transaction was executed successfully
+    pub fn is_confirmed(&self) -> bool {
+        self.status == TxStatus::Confirmed
+    }
+}
+
+/// System that resolves the `PendingTransaction` components of entities
+///
+/// It is automatically registered by the `BevyDojoPlugin`, after the
+/// `StarknetPollSet`, so entities are updated in the frame the outcome is
+/// known.
+pub fn update_pending_transactions(
+    mut commands: Commands,
+    mut confirmed: EventReader<TransactionConfirmed>,
+    mut failed: EventReader<TransactionFailed>,
+    sn: Option<Res<StarknetConnection>>,
+    pending: Query<(Entity, Ref<PendingTransaction>)>,
+) {
+    let results = confirmed
+        .read()
+        .map(|event| TransactionResult {
+            tx_id: event.tx_id,
+            hash: Some(event.hash),
+            status: TxStatus::Confirmed,
+        })
+        .chain(failed.read().map(|event| TransactionResult {
+            tx_id: event.tx_id,
+            hash: event.hash,
+            status: event.status.clone(),
+        }))
+        .map(|result| (result.tx_id, result))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, tracked) in &pending {
+        let result = match results.get(&tracked.tx_id) {
+            Some(result) => result.clone(),
+            // Tracked after its outcome was reported, look it up in the history
+            None if tracked.is_added() => {
+                let record = sn.as_ref().and_then(|sn| sn.record(tracked.tx_id));
+                match record {
+                    Some(record) if record.status.is_final() => TransactionResult {
+                        tx_id: record.tx_id,
+                        hash: record.hash,
+                        status: record.status.clone(),
+                    },
+                    _ => continue,
+                }
+            }
+            None => continue,
+        };
+        let mut entity = commands.entity(entity);
+        entity.remove::<PendingTransaction>();
+        if tracked.insert_result {
+            entity.insert(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::starknet::SubmitOutcome;
+
+    fn queue(app: &mut App) -> TxId {
+        let outcome = with_connection(app, |runtime, sn| sn.execute(runtime, vec![call(0)]));
+        let SubmitOutcome::Queued(tx_id) = outcome else {
+            panic!("transaction not queued: {outcome:?}");
+        };
+        tx_id
+    }
+
+    #[test]
+    fn pending_transaction_is_resolved_by_its_outcome() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        let tx_id = queue(&mut app);
+        let entity = app.world_mut().spawn(PendingTransaction::new(tx_id)).id();
+        let untracked = app
+            .world_mut()
+            .spawn(PendingTransaction {
+                tx_id,
+                insert_result: false,
+            })
+            .id();
+
+        assert!(update_until(&mut app, |app| {
+            app.world().get::<TransactionResult>(entity).is_some()
+        }));
+        let result = app.world().get::<TransactionResult>(entity).unwrap();
+        assert!(result.is_confirmed());
+        assert_eq!(result.hash, Some(Felt::from(0x100u64)));
+        assert!(app.world().get::<PendingTransaction>(entity).is_none());
+        assert!(app.world().get::<PendingTransaction>(untracked).is_none());
+        assert!(app.world().get::<TransactionResult>(untracked).is_none());
+    }
+
+    #[test]
+    fn pending_transaction_added_late_is_resolved_from_history() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        let tx_id = queue(&mut app);
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 1
+        }));
+        // The outcome events are gone by now
+        app.update();
+        app.update();
+
+        let entity = app.world_mut().spawn(PendingTransaction::new(tx_id)).id();
+        app.update();
+        let result = app.world().get::<TransactionResult>(entity).unwrap();
+        assert_eq!(result.tx_id, tx_id);
+        assert!(result.is_confirmed());
+        assert!(app.world().get::<PendingTransaction>(entity).is_none());
+
+        // Unknown transactions stay pending
+        let unknown = app
+            .world_mut()
+            .spawn(PendingTransaction::new(TxId(u64::MAX)))
+            .id();
+        app.update();
+        assert!(app.world().get::<PendingTransaction>(unknown).is_some());
+    }
+}
@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+
+use std::time::{Duration, Instant};
+
+use starknet::{
+    accounts::{Account, ConnectedAccount},
+    core::{
+        chain_id,
+        types::{Call, Felt},
+    },
+};
+
+use crate::limit::RequestLimiter;
+use crate::query::read_token_balance;
+use crate::signature::DojoAccount;
+use crate::starknet::{StarknetConnection, http_client};
+
+/// Address of the STRK token, used to pay the fees of v3 transactions
+pub const STRK_TOKEN_ADDRESS: Felt =
+    Felt::from_hex_unchecked("0x04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d");
+
+/// Faucet used to top up the account with test tokens on testnet
+///
+/// When a faucet is configured and the connected chain is a testnet, every
+/// transaction first checks that the account's STRK balance covers the
+/// estimated fee. If it doesn't, the faucet is asked for tokens with a JSON
+/// `POST` of `{"address": "<account address>"}` to `url`, and the transaction
+/// is sent once the balance covers the fee or `funding_timeout` elapses.
+/// The request goes through the `proxy` of the connection, if any.
+///
+/// The faucet is never used on chains other than Sepolia.
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(mut sn: ResMut<StarknetConnection>) {
+///     sn.set_faucet(Some(FaucetConfig::new("https://my-faucet.example/api/fund")));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaucetConfig {
+    pub url: String,
+    /// How long to wait for the requested tokens to arrive
+    pub funding_timeout: Duration,
+    /// Interval between balance checks while waiting for the tokens
+    pub poll_interval: Duration,
+}
+
+impl FaucetConfig {
+    /// Create a faucet configuration with default timings
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            funding_timeout: Duration::from_secs(120),
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the faucet used to top up the account on testnet, if any
+    pub fn faucet(&self) -> Option<&FaucetConfig> {
+        self.faucet.as_ref()
+    }
+
+    /// Sets or clears the faucet used to top up the account on testnet
+    pub fn set_faucet(&mut self, faucet: Option<FaucetConfig>) {
+        self.faucet = faucet;
+    }
+}
+
+/// Returns true if `chain_id` is a testnet the faucet may be used on
+pub fn is_testnet(chain_id: Felt) -> bool {
+    chain_id == chain_id::SEPOLIA
+}
+
+/// Make sure the account can afford `calls`, requesting tokens from the faucet if not
+///
+/// Failures are logged and otherwise ignored: the transaction is sent anyway
+/// and fails with the provider's error if the account really can't pay.
+/// The faucet is reached through `proxy`, the proxy of the connection, if any.
+/// A request slot of `limiter` is only held during each provider request, so
+/// waiting for the faucet doesn't hold up the other requests.
+pub(crate) async fn ensure_funds(
+    account: &DojoAccount,
+    calls: &[Call],
+    faucet: &FaucetConfig,
+    proxy: Option<&str>,
+    limiter: &RequestLimiter,
+) {
+    if !is_testnet(account.chain_id()) {
+        return;
+    }
+
+    let permit = limiter.acquire().await;
+    let fee = match account.execute_v3(calls.to_vec()).estimate_fee().await {
+        Ok(estimate) => estimate.overall_fee,
+        Err(err) => {
+            warn!("Failed to estimate fee before sending: {}", err);
+            return;
+        }
+    };
+    let provider = account.provider();
+    let address = account.address();
+    let balance = read_token_balance(provider, STRK_TOKEN_ADDRESS, address).await;
+    drop(permit);
+    match balance {
+        Ok(balance) if balance >= fee => return,
+        Ok(_) => {}
+        Err(err) => {
+            warn!("Failed to read fee token balance: {}", err);
+            return;
+        }
+    }
+
+    info!("Insufficient balance for fee, requesting tokens from faucet");
+    let client = match http_client(proxy) {
+        Ok(client) => client,
+        Err(err) => {
+            warn!("Failed to create the faucet client: {}", err);
+            return;
+        }
+    };
+    if let Err(err) = request_tokens(&client, &faucet.url, address).await {
+        warn!("Faucet request failed: {}", err);
+        return;
+    }
+
+    let started = Instant::now();
+    while started.elapsed() < faucet.funding_timeout {
+        tokio::time::sleep(faucet.poll_interval).await;
+        let _permit = limiter.acquire().await;
+        let balance = read_token_balance(provider, STRK_TOKEN_ADDRESS, address).await;
+        if matches!(balance, Ok(balance) if balance >= fee) {
+            info!("Faucet tokens arrived");
+            return;
+        }
+    }
+    warn!("Timed out waiting for faucet tokens");
+}
+
+async fn request_tokens(
+    client: &reqwest::Client,
+    url: &str,
+    address: Felt,
+) -> Result<(), reqwest::Error> {
+    client
+        .post(url)
+        .header("content-type", "application/json")
+        .body(format!(r#"{{"address":"{:#x}"}}"#, address))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::starknet::DefaultStarknetConfig;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn faucet_tops_up_before_sending() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let funded = Arc::new(AtomicBool::new(false));
+        let faucet_funded = funded.clone();
+        mock.on_fn("", move |_| {
+            faucet_funded.store(true, Ordering::SeqCst);
+            Ok(json!({}))
+        });
+        mock.on_call(move |contract, _, calldata| {
+            assert_eq!(contract, STRK_TOKEN_ADDRESS);
+            assert_eq!(calldata, [ACCOUNT_ADDRESS]);
+            let balance = if funded.load(Ordering::SeqCst) {
+                5000u64
+            } else {
+                0
+            };
+            Ok(vec![Felt::from(balance), Felt::ZERO])
+        });
+        let mut app = connected_app(&mock);
+        with_connection(&mut app, |runtime, sn| {
+            sn.set_faucet(Some(FaucetConfig {
+                url: mock.url(),
+                funding_timeout: Duration::from_secs(5),
+                poll_interval: Duration::from_millis(10),
+            }));
+            sn.execute(runtime, vec![call(1)]);
+        });
+
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+        let faucet = mock.requests("");
+        assert_eq!(
+            faucet,
+            vec![json!({ "address": format!("{ACCOUNT_ADDRESS:#x}") })]
+        );
+        // Once before asking the faucet, then until the tokens arrived
+        assert!(mock.count("starknet_call") >= 2);
+        assert!(mock.request_times("")[0] < mock.request_times("starknet_addInvokeTransaction")[0]);
+    }
+
+    #[test]
+    fn faucet_is_not_asked_when_the_balance_covers_the_fee() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on("", json!({}));
+        mock.on_call(|_, _, _| Ok(vec![Felt::from(5000u64), Felt::ZERO]));
+        let mut app = connected_app(&mock);
+        with_connection(&mut app, |runtime, sn| {
+            sn.set_faucet(Some(FaucetConfig::new(mock.url())));
+            sn.execute(runtime, vec![call(1)]);
+        });
+
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+        assert_eq!(mock.count(""), 0);
+    }
+
+    #[test]
+    fn faucet_is_reached_through_the_proxy() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on("", json!({}));
+        mock.on_call(|_, _, _| Ok(vec![Felt::ZERO, Felt::ZERO]));
+        let mut app = test_app();
+        // The mock is also the proxy, the only way to reach hosts that don't exist
+        app.insert_resource(DefaultStarknetConfig {
+            rpc_url: "http://rpc.invalid".to_string(),
+            proxy: Some(mock.url()),
+            ..mock.config()
+        });
+        connect(&mut app);
+        with_connection(&mut app, |runtime, sn| {
+            sn.set_faucet(Some(FaucetConfig {
+                url: "http://faucet.invalid/fund".to_string(),
+                funding_timeout: Duration::from_millis(100),
+                poll_interval: Duration::from_millis(10),
+            }));
+            sn.execute(runtime, vec![call(1)]);
+        });
+
+        assert!(update_until(&mut app, |_| mock.count("") == 1));
+        assert!(connection(&app).is_connected());
+        assert_eq!(
+            mock.requests(""),
+            vec![json!({ "address": format!("{ACCOUNT_ADDRESS:#x}") })]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::prelude::*;
+
+use std::sync::Arc;
+
+use starknet::{
+    accounts::{Account, ConnectedAccount},
+    core::types::Felt,
+};
+use tokio::task::JoinHandle;
+
+use crate::faucet::STRK_TOKEN_ADDRESS;
+use crate::query::read_token_balance;
+use crate::signature::DojoAccount;
+use crate::starknet::StarknetConnection;
+use crate::tokio::TokioRuntime;
+
+/// Address of the ETH token, used to pay the fees of legacy transactions
+pub const ETH_TOKEN_ADDRESS: Felt =
+    Felt::from_hex_unchecked("0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7");
+
+/// Token an account holds to pay transaction fees
+///
+/// This is only what detection found in the account: transactions always pay
+/// their fees in STRK, whichever token is detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeToken {
+    /// STRK, paid by v3 transactions
+    Strk,
+    /// ETH, only accepted by legacy v1 transactions
+    Eth,
+}
+
+/// Fee token detection of a `StarknetConnection`
+#[derive(Default)]
+pub(crate) struct FeeTokenState {
+    enabled: bool,
+    detected: Option<FeeToken>,
+    task: Option<JoinHandle<Option<FeeToken>>>,
+}
+
+impl FeeTokenState {
+    /// Returns the number of running detection tasks
+    pub(crate) fn task_count(&self) -> usize {
+        usize::from(self.task.is_some())
+    }
+}
+
+impl StarknetConnection {
+    /// Returns true if the fee token is detected on connect and after fee failures
+    pub fn fee_token_detection(&self) -> bool {
+        self.fee_token.enabled
+    }
+
+    /// Enable or disable the detection of the token the account pays fees with
+    ///
+    /// When enabled, the STRK and ETH balances of the account are read on
+    /// connect, and again whenever a transaction fails because the account
+    /// can't pay its fee, which costs two extra `call` requests each time. The
+    /// result is read with `fee_token`, STRK being preferred whenever the
+    /// account holds any. Disabled by default.
+    ///
+    /// This only detects the token, it never switches to paying fees in ETH:
+    /// transactions are always v3 transactions paying fees in STRK, since the
+    /// RPC version used by this crate no longer accepts the v1 transactions
+    /// that pay fees in ETH. Detecting `FeeToken::Eth` lets the game tell the
+    /// player to acquire STRK before playing.
+    pub fn set_fee_token_detection(&mut self, enabled: bool) {
+        self.fee_token.enabled = enabled;
+    }
+
+    /// Returns the fee token detected for the account, if detection ran
+    ///
+    /// `None` if detection hasn't run yet, failed, or the account holds
+    /// neither STRK nor ETH. `FeeToken::Eth` is only reported once the STRK
+    /// balance was read as zero, never guessed from a failed STRK read.
+    pub fn fee_token(&self) -> Option<FeeToken> {
+        self.fee_token.detected
+    }
+
+    /// Start detecting the fee token now, whether or not detection is enabled
+    ///
+    /// The result is read with `fee_token` once the balances have been read.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if detection started or was already running
+    /// * `false` if there is no account to detect the fee token of
+    pub fn detect_fee_token(&mut self, runtime: &TokioRuntime) -> bool {
+        if self.fee_token.task.is_some() {
+            return true;
+        }
+        let Some(account) = self.account().cloned() else {
+            return false;
+        };
+        let limiter = self.limiter.clone();
+        self.fee_token.task = Some(runtime.runtime.spawn(async move {
+            let _permit = limiter.acquire().await;
+            read_fee_token(account).await
+        }));
+        true
+    }
+
+    /// Start detecting the fee token if detection is enabled
+    pub(crate) fn redetect_fee_token(&mut self, runtime: &TokioRuntime) {
+        if self.fee_token.enabled {
+            self.detect_fee_token(runtime);
+        }
+    }
+
+    /// Record the result of a finished fee token detection
+    pub(crate) fn poll_fee_token(&mut self, runtime: &TokioRuntime) {
+        let Some(task) = self.fee_token.task.take_if(|task| task.is_finished()) else {
+            return;
+        };
+        let Ok(detected) = runtime.runtime.block_on(task) else {
+            return;
+        };
+        match detected {
+            Some(FeeToken::Strk) => info!("Paying fees in STRK"),
+            Some(FeeToken::Eth) => {
+                warn!("The account only holds ETH, but transactions can only pay fees in STRK")
+            }
+            None => warn!("The account holds neither STRK nor ETH to pay fees"),
+        }
+        self.fee_token.detected = detected;
+    }
+}
+
+/// Returns the token the account can pay fees with, preferring STRK
+async fn read_fee_token(account: Arc<DojoAccount>) -> Option<FeeToken> {
+    let provider = account.provider();
+    let address = account.address();
+    let (strk, eth) = futures::join!(
+        read_token_balance(provider, STRK_TOKEN_ADDRESS, address),
+        read_token_balance(provider, ETH_TOKEN_ADDRESS, address),
+    );
+    match (strk, eth) {
+        (Ok(strk), _) if strk > 0 => Some(FeeToken::Strk),
+        (Ok(_), Ok(eth)) if eth > 0 => Some(FeeToken::Eth),
+        (Ok(_), Ok(_)) => None,
+        (Err(err), _) | (_, Err(err)) => {
+            warn!("Failed to read the fee token balances: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn eth_only_account_is_detected_but_pays_in_strk() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on_call(|contract, _, _| {
+            let balance = if contract == ETH_TOKEN_ADDRESS {
+                5000u64
+            } else {
+                0
+            };
+            Ok(vec![Felt::from(balance), Felt::ZERO])
+        });
+        let mut app = test_app();
+        app.insert_resource(mock.config());
+        with_connection(&mut app, |_, sn| sn.set_fee_token_detection(true));
+        connect(&mut app);
+
+        assert!(update_until(&mut app, |app| {
+            connection(app).fee_token() == Some(FeeToken::Eth)
+        }));
+        assert_eq!(mock.count("starknet_call"), 2);
+
+        with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(1)]));
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+        let sent = param(
+            &mock.requests("starknet_addInvokeTransaction")[0],
+            0,
+            "invoke_transaction",
+        );
+        assert_eq!(sent["version"], "0x3");
+    }
+
+    #[test]
+    fn fee_token_is_not_detected_unless_enabled() {
+        let mock = MockRpc::start();
+        mock.on_call(|_, _, _| Ok(vec![Felt::from(5000u64), Felt::ZERO]));
+        let mut app = connected_app(&mock);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(connection(&app).fee_token(), None);
+        assert_eq!(mock.count("starknet_call"), 0);
+
+        assert!(with_connection(&mut app, |runtime, sn| sn.detect_fee_token(runtime)));
+        assert!(update_until(&mut app, |app| {
+            connection(app).fee_token() == Some(FeeToken::Strk)
+        }));
+    }
+
+    #[test]
+    fn failed_strk_read_is_not_reported_as_eth() {
+        let mock = MockRpc::start();
+        mock.on_call(|contract, _, _| {
+            if contract == STRK_TOKEN_ADDRESS {
+                Err(RpcError::new(-32603, "Internal error"))
+            } else {
+                Ok(vec![Felt::from(5000u64), Felt::ZERO])
+            }
+        });
+        let mut app = connected_app(&mock);
+
+        assert!(with_connection(&mut app, |runtime, sn| sn.detect_fee_token(runtime)));
+        assert!(update_until(&mut app, |app| {
+            connection(app).fee_token.task.is_none()
+        }));
+        assert_eq!(mock.count("starknet_call"), 2);
+        assert_eq!(connection(&app).fee_token(), None);
+    }
+
+    #[test]
+    fn fee_token_is_detected_again_after_insufficient_balance() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let spent = Arc::new(AtomicBool::new(false));
+        let balances = spent.clone();
+        mock.on_call(move |contract, _, _| {
+            let balance = if contract == STRK_TOKEN_ADDRESS && balances.load(Ordering::SeqCst) {
+                0
+            } else {
+                5000u64
+            };
+            Ok(vec![Felt::from(balance), Felt::ZERO])
+        });
+        let mut app = test_app();
+        app.insert_resource(mock.config());
+        with_connection(&mut app, |_, sn| sn.set_fee_token_detection(true));
+        connect(&mut app);
+        assert!(update_until(&mut app, |app| {
+            connection(app).fee_token() == Some(FeeToken::Strk)
+        }));
+
+        spent.store(true, Ordering::SeqCst);
+        mock.on_error(
+            "starknet_addInvokeTransaction",
+            RpcError::new(
+                54,
+                "Account balance is smaller than the transaction's max_fee",
+            ),
+        );
+        with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(1)]));
+
+        assert!(update_until(&mut app, |app| {
+            connection(app).fee_token() == Some(FeeToken::Eth)
+        }));
+        assert_eq!(mock.count("starknet_call"), 4);
+    }
+}
@@ -0,0 +1,322 @@
+use starknet::{
+    core::{
+        crypto::compute_hash_on_elements,
+        types::{Call, Felt, ResourceBounds, ResourceBoundsMapping},
+    },
+    macros::short_string,
+};
+use starknet_crypto::{pedersen_hash, poseidon_hash, poseidon_hash_many};
+
+/// Hash function used by the hashing utilities of the crate
+///
+/// Dojo and Cairo 1 code, including v3 transactions, hash with Poseidon,
+/// while Cairo 0 contracts and many existing Merkle allowlists use Pedersen.
+/// Each utility defaults to the function its on-chain counterpart uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashFunction {
+    Pedersen,
+    #[default]
+    Poseidon,
+}
+
+impl HashFunction {
+    /// Hash two felts
+    pub fn hash(self, a: &Felt, b: &Felt) -> Felt {
+        match self {
+            HashFunction::Pedersen => pedersen_hash(a, b),
+            HashFunction::Poseidon => poseidon_hash(*a, *b),
+        }
+    }
+
+    /// Hash an array of felts
+    ///
+    /// With Pedersen, this is the chain of hashes over the elements followed
+    /// by their count, as computed by Cairo 0's `hash_chain` helpers. With
+    /// Poseidon, this is the sponge hash of the elements, as computed by
+    /// Cairo's `poseidon_hash_span`.
+    pub fn hash_many(self, values: &[Felt]) -> Felt {
+        match self {
+            HashFunction::Pedersen => compute_hash_on_elements(values),
+            HashFunction::Poseidon => poseidon_hash_many(values),
+        }
+    }
+}
+
+/// Encode calls the way an account's `__execute__` receives them
+///
+/// This is the calldata of an invoke transaction sent by a Cairo 1 account:
+/// the number of calls followed by, for each call, its target, its selector,
+/// the length of its calldata and the calldata itself.
+pub fn encode_calls(calls: &[Call]) -> Vec<Felt> {
+    let len = calls
+        .iter()
+        .map(|call| 3 + call.calldata.len())
+        .sum::<usize>();
+    let mut encoded = Vec::with_capacity(1 + len);
+    encoded.push(Felt::from(calls.len()));
+    for call in calls {
+        encoded.push(call.to);
+        encoded.push(call.selector);
+        encoded.push(Felt::from(call.calldata.len()));
+        encoded.extend_from_slice(&call.calldata);
+    }
+    encoded
+}
+
+/// Compute the canonical hash of the calldata of an invoke executing `calls`
+///
+/// This is the Poseidon hash of the encoded calldata, the same value a v3
+/// invoke transaction commits to in its transaction hash. Games can compare it
+/// with the calldata of the transaction that landed on-chain to check that
+/// the submitted calls weren't altered. Use `hash_calls_with` to hash with
+/// another function, e.g. to match a Cairo 0 contract.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::hash::hash_calls;
+///
+/// let expected = hash_calls(&calls);
+/// // ... later, with the calls decoded from the on-chain transaction
+/// assert_eq!(hash_calls(&landed_calls), expected);
+/// ```
+pub fn hash_calls(calls: &[Call]) -> Felt {
+    hash_calls_with(calls, HashFunction::Poseidon)
+}
+
+/// Compute the hash of the calldata of an invoke executing `calls` with `hash`
+///
+/// See `hash_calls`, which hashes with Poseidon.
+pub fn hash_calls_with(calls: &[Call], hash: HashFunction) -> Felt {
+    hash.hash_many(&encode_calls(calls))
+}
+
+/// Compute the hash of the v3 invoke transaction signed by `sender`
+///
+/// This is the hash starknet-rs signs, except that it commits to `tip` where
+/// starknet-rs 0.15 always hashes a tip of 0. Data availability modes are L1,
+/// and paymaster and account deployment data are empty.
+pub(crate) fn invoke_v3_hash(
+    chain_id: Felt,
+    sender: Felt,
+    nonce: Felt,
+    calldata: &[Felt],
+    bounds: &ResourceBoundsMapping,
+    tip: u64,
+) -> Felt {
+    // Resource bounds are packed as `name << 192 | amount << 128 | price`
+    let resource = |name: Felt, bounds: &ResourceBounds| {
+        name * Felt::TWO.pow(192u32)
+            + Felt::from(bounds.max_amount) * Felt::TWO.pow(128u32)
+            + Felt::from(bounds.max_price_per_unit)
+    };
+    let fees = poseidon_hash_many(&[
+        Felt::from(tip),
+        resource(short_string!("L1_GAS"), &bounds.l1_gas),
+        resource(short_string!("L2_GAS"), &bounds.l2_gas),
+        resource(short_string!("L1_DATA"), &bounds.l1_data_gas),
+    ]);
+    poseidon_hash_many(&[
+        short_string!("invoke"),
+        Felt::THREE,
+        sender,
+        fees,
+        poseidon_hash_many(&[]),
+        chain_id,
+        nonce,
+        Felt::ZERO,
+        poseidon_hash_many(&[]),
+        poseidon_hash_many(calldata),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::call;
+    use starknet::{
+        accounts::{Account, ExecutionEncoder, ExecutionEncoding, SingleOwnerAccount},
+        core::utils::cairo_short_string_to_felt,
+        providers::{JsonRpcClient, Url, jsonrpc::HttpTransport},
+        signers::{LocalWallet, SigningKey},
+    };
+
+    fn account() -> SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet> {
+        let provider = JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://localhost:5050").unwrap(),
+        ));
+        let signer = LocalWallet::from(SigningKey::from_secret_scalar(Felt::ONE));
+        SingleOwnerAccount::new(
+            provider,
+            signer,
+            Felt::from(0x1234u64),
+            Felt::from(0x534eu64),
+            ExecutionEncoding::New,
+        )
+    }
+
+    fn multicall() -> Vec<Call> {
+        let mut transfer = call(1);
+        transfer
+            .calldata
+            .extend([Felt::from(2u64), Felt::from(3u64)]);
+        vec![
+            transfer,
+            call(4),
+            Call {
+                to: Felt::from(0x43u64),
+                selector: Felt::from(0x5e2u64),
+                calldata: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn calls_are_encoded_like_starknet_accounts() {
+        let calls = multicall();
+        let account = account();
+        assert_eq!(encode_calls(&calls), account.encode_calls(&calls));
+        assert_eq!(
+            hash_calls(&calls),
+            poseidon_hash_many(&account.encode_calls(&calls))
+        );
+    }
+
+    #[test]
+    fn hash_calls_is_committed_to_by_invoke_v3() {
+        let calls = multicall();
+        let account = account();
+        let prepared = account
+            .execute_v3(calls.clone())
+            .nonce(Felt::from(7u64))
+            .l1_gas(0)
+            .l1_gas_price(0)
+            .l2_gas(0)
+            .l2_gas_price(0)
+            .l1_data_gas(0)
+            .l1_data_gas_price(0)
+            .prepared()
+            .unwrap();
+
+        // Resource bounds are packed as `name << 192 | amount << 128 | price`
+        let shift = Felt::TWO.pow(192u32);
+        let resource = |name: &str| cairo_short_string_to_felt(name).unwrap() * shift;
+        let bounds = poseidon_hash_many(&[
+            Felt::ZERO,
+            resource("L1_GAS"),
+            resource("L2_GAS"),
+            resource("L1_DATA"),
+        ]);
+        let expected = poseidon_hash_many(&[
+            cairo_short_string_to_felt("invoke").unwrap(),
+            Felt::THREE,
+            account.address(),
+            bounds,
+            poseidon_hash_many(&[]),
+            account.chain_id(),
+            Felt::from(7u64),
+            Felt::ZERO,
+            poseidon_hash_many(&[]),
+            hash_calls(&calls),
+        ]);
+        assert_eq!(prepared.transaction_hash(false), expected);
+    }
+
+    #[test]
+    fn invoke_v3_hash_matches_starknet_accounts_without_tip() {
+        let calls = multicall();
+        let account = account();
+        let prepared = account
+            .execute_v3(calls.clone())
+            .nonce(Felt::from(7u64))
+            .l1_gas(1)
+            .l1_gas_price(2)
+            .l2_gas(3000)
+            .l2_gas_price(4)
+            .l1_data_gas(5)
+            .l1_data_gas_price(6)
+            .prepared()
+            .unwrap();
+        let bounds = ResourceBoundsMapping {
+            l1_gas: ResourceBounds {
+                max_amount: 1,
+                max_price_per_unit: 2,
+            },
+            l2_gas: ResourceBounds {
+                max_amount: 3000,
+                max_price_per_unit: 4,
+            },
+            l1_data_gas: ResourceBounds {
+                max_amount: 5,
+                max_price_per_unit: 6,
+            },
+        };
+        let hash = |tip| {
+            invoke_v3_hash(
+                account.chain_id(),
+                account.address(),
+                Felt::from(7u64),
+                &encode_calls(&calls),
+                &bounds,
+                tip,
+            )
+        };
+
+        assert_eq!(hash(0), prepared.transaction_hash(false));
+        assert_ne!(hash(10), hash(0));
+    }
+
+    fn felt(hex: &str) -> Felt {
+        Felt::from_hex(hex).unwrap()
+    }
+
+    #[test]
+    fn both_hash_functions_match_known_vectors() {
+        let (one, two) = (Felt::ONE, Felt::TWO);
+        assert_eq!(
+            HashFunction::Pedersen.hash(&one, &two),
+            felt("0x5bb9440e27889a364bcb678b1f679ecd1347acdedcbf36e83494f857cc58026")
+        );
+        assert_eq!(
+            HashFunction::Poseidon.hash(&one, &two),
+            felt("0x5d44a3decb2b2e0cc71071f7b802f45dd792d064f0fc7316c46514f70f9891a")
+        );
+
+        let values = [Felt::ONE, Felt::TWO, Felt::THREE];
+        assert_eq!(
+            HashFunction::Pedersen.hash_many(&values),
+            felt("0xf9d95fbf356fbeda26538c92f7040abe51bf142350f73c9ee5ba7c660bae71")
+        );
+        assert_eq!(
+            HashFunction::Poseidon.hash_many(&values),
+            felt("0x2f0d8840bcf3bc629598d8a6cc80cb7c0d9e52d93dab244bbf9cd0dca0ad082")
+        );
+    }
+
+    #[test]
+    fn pedersen_hash_many_chains_and_appends_the_length() {
+        let values = [Felt::ONE, Felt::TWO, Felt::THREE];
+        let chained = values
+            .iter()
+            .fold(Felt::ZERO, |acc, value| pedersen_hash(&acc, value));
+        assert_eq!(
+            HashFunction::Pedersen.hash_many(&values),
+            pedersen_hash(&chained, &Felt::THREE)
+        );
+    }
+
+    #[test]
+    fn calls_hash_with_the_requested_function() {
+        let calls = [call(1)];
+        let encoded = encode_calls(&calls);
+        assert_eq!(hash_calls(&calls), poseidon_hash_many(&encoded));
+        assert_eq!(
+            hash_calls_with(&calls, HashFunction::Pedersen),
+            compute_hash_on_elements(&encoded)
+        );
+        assert_ne!(
+            hash_calls(&calls),
+            hash_calls_with(&calls, HashFunction::Pedersen)
+        );
+    }
+}
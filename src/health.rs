@@ -0,0 +1,120 @@
+use crate::starknet::{StarknetConnection, StarknetMetrics};
+
+/// Snapshot of the state of a `StarknetConnection` for external monitoring
+///
+/// With the `serde` feature enabled, the snapshot implements `Serialize`, so
+/// server-hosted games can expose it as is on a monitoring endpoint.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HealthSnapshot {
+    /// True if the connection is established
+    pub connected: bool,
+    /// True if connected and nothing failed since the last success
+    pub healthy: bool,
+    /// Number of transactions not confirmed yet
+    pub pending_txs: usize,
+    /// Number of the most recent block a transaction was confirmed in
+    pub last_block: Option<u64>,
+    /// The most recent connection or transaction error
+    pub last_error: Option<String>,
+    pub metrics: StarknetMetrics,
+}
+
+/// Health tracking of a `StarknetConnection`
+#[derive(Default)]
+pub(crate) struct HealthState {
+    last_block: Option<u64>,
+    last_error: Option<String>,
+    failing: bool,
+}
+
+impl HealthState {
+    /// Record a successful operation, confirmed in `block` if known
+    pub(crate) fn record_success(&mut self, block: Option<u64>) {
+        self.failing = false;
+        if block.is_some() {
+            self.last_block = block.max(self.last_block);
+        }
+    }
+
+    /// Record a failed operation
+    pub(crate) fn record_error(&mut self, error: String) {
+        self.failing = true;
+        self.last_error = Some(error);
+    }
+}
+
+impl StarknetConnection {
+    /// Returns a snapshot of the connection state for monitoring
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn report_health(sn: Res<StarknetConnection>) {
+    ///     let health = sn.health_snapshot();
+    ///     if !health.healthy {
+    ///         println!("Starknet is unhealthy: {:?}", health.last_error);
+    ///     }
+    /// }
+    /// ```
+    pub fn health_snapshot(&self) -> HealthSnapshot {
+        let connected = self.is_connected();
+        HealthSnapshot {
+            connected,
+            healthy: connected && !self.health.failing,
+            pending_txs: self.pending_tx_count(),
+            last_block: self.health.last_block,
+            last_error: self.health.last_error.clone(),
+            metrics: self.metrics().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::*;
+
+    #[test]
+    fn snapshot_reflects_the_connection() {
+        let mut app = test_app();
+        let health = connection(&app).health_snapshot();
+        assert!(!health.connected);
+        assert!(!health.healthy);
+
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        app.insert_resource(mock.config());
+        connect(&mut app);
+        with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(1)]));
+        assert_eq!(connection(&app).health_snapshot().pending_txs, 1);
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 1
+        }));
+
+        let health = connection(&app).health_snapshot();
+        assert!(health.connected);
+        assert!(health.healthy);
+        assert_eq!(health.pending_txs, 0);
+        assert_eq!(health.last_block, Some(1));
+        assert_eq!(health.last_error, None);
+        assert_eq!(health.metrics.submitted_txs, 1);
+        assert_eq!(health.metrics.total_fee_spent, 1000);
+
+        mock.on_error(
+            "starknet_addInvokeTransaction",
+            RpcError::new(55, "Account validation failed"),
+        );
+        with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(2)]));
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().failed_txs == 1
+        }));
+
+        let health = connection(&app).health_snapshot();
+        assert!(health.connected);
+        assert!(!health.healthy);
+        assert_eq!(health.pending_txs, 0);
+        assert_eq!(health.last_block, Some(1));
+        assert!(health.last_error.is_some());
+        assert_eq!(health.metrics.confirmed_txs, 1);
+    }
+}
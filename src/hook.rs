@@ -0,0 +1,84 @@
+use bevy::ecs::system::SystemId;
+
+use crate::reconnect::TaskEvents;
+use crate::starknet::StarknetConnection;
+
+impl StarknetConnection {
+    /// Run the one-shot system `system` once the connection is ready
+    ///
+    /// The system runs exactly once, from the `check_sn_task` system of the
+    /// frame the connection first becomes ready, so the account is available
+    /// when it runs, e.g. to submit a setup transaction at startup. If the
+    /// connection is already ready, it runs during the next `check_sn_task`.
+    /// Reconnecting doesn't run it again.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn spawn_player(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+    ///     execute_transaction(runtime, sn, vec![/* calls */]);
+    /// }
+    ///
+    /// fn setup(mut commands: Commands, mut sn: ResMut<StarknetConnection>) {
+    ///     let system = commands.register_system(spawn_player);
+    ///     sn.on_connected(system);
+    /// }
+    /// ```
+    pub fn on_connected(&mut self, system: SystemId) {
+        self.connected_hooks.push(system);
+    }
+
+    /// Queue the systems registered with `on_connected`, once the connection is ready
+    pub(crate) fn run_connected_hooks(&mut self, events: &mut TaskEvents) {
+        if !self.is_connected() {
+            return;
+        }
+        for system in self.connected_hooks.drain(..) {
+            events.commands.run_system(system);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    /// Number of times the hook ran, and whether the account was available each time
+    #[derive(Resource, Default)]
+    struct HookRuns(Vec<bool>);
+
+    fn hook(sn: Res<StarknetConnection>, mut runs: ResMut<HookRuns>) {
+        runs.0.push(sn.account().is_some());
+    }
+
+    #[test]
+    fn hook_runs_once_after_connecting() {
+        let mock = MockRpc::start();
+        mock.delay("starknet_chainId", Duration::from_millis(100));
+        let mut app = test_app();
+        app.init_resource::<HookRuns>();
+        app.insert_resource(mock.config());
+        let system = app.world_mut().register_system(hook);
+        with_connection(&mut app, |runtime, sn| {
+            sn.on_connected(system);
+            sn.connect(runtime, &mock.config());
+        });
+
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(!connection(&app).is_connected());
+        assert!(app.world().resource::<HookRuns>().0.is_empty());
+
+        assert!(update_until(&mut app, |app| {
+            !app.world().resource::<HookRuns>().0.is_empty()
+        }));
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<HookRuns>().0, [true]);
+    }
+}
@@ -20,13 +20,11 @@
 //! use bevy::prelude::*;
 //! use bevy_dojo::prelude::*;
 //!
-//! fn main() {
-//!     App::new()
-//!         .add_plugins(DefaultPlugins)
-//!         .add_plugins(BevyDojoPlugin)
-//!         .add_systems(Update, keyboard_control)
-//!         .run();
-//! }
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(BevyDojoPlugin)
+//!     .add_systems(Update, keyboard_control)
+//!     .run();
 //! ```
 //!
 //! ## Environment Variables
@@ -36,6 +34,7 @@
 //! - `STARKNET_RPC_URL`: URL of your Starknet RPC provider
 //! - `STARKNET_ACCOUNT_ADDRESS`: Your Starknet account address (as a hex string)
 //! - `STARKNET_PRIVATE_KEY`: Your private key (as a hex string)
+//! - `STARKNET_PROXY`: Optional HTTP or HTTPS proxy to reach the RPC provider
 //!
 //! Alternatively, you can provide these values explicitly by replacing the
 //! `DefaultStarknetConfig` resource.
@@ -89,9 +88,54 @@
 //!     }
 //! }
 //! ```
+//!
+//! ## Event Timing
+//!
+//! Transaction, query and subscription events are emitted by the systems of
+//! the `StarknetPollSet`, which run in `Update` by default. Systems ordered
+//! `.after(StarknetPollSet)` read them in the frame they're emitted, while
+//! unordered systems may only read them in the next frame. Inserting `PollSchedule::First` before
+//! adding the plugin polls at the start of the frame instead, so every `Update`
+//! system reads them in the same frame.
+//!
+//! A transaction is never confirmed in the frame it's submitted: it is sent
+//! and confirmed by background tasks whose completion is noticed by a later
+//! poll.
 
 // Re-export modules
+pub mod abi;
+pub mod approval;
+pub mod batch;
+pub mod block_time;
+pub mod bump;
+pub mod calldata;
+pub mod chains;
+#[cfg(feature = "devnet-tests")]
+pub mod devnet;
+pub mod display;
+pub mod dojo;
+pub mod entity;
+pub mod faucet;
+pub mod fee_token;
+pub mod hash;
+pub mod health;
+pub mod hook;
+pub mod limit;
+pub mod merkle;
+#[cfg(test)]
+mod mock;
+pub mod param;
+pub mod query;
+pub mod readonly;
+pub mod reconnect;
+pub mod record;
+pub mod resubmit;
+mod session;
+pub mod signature;
+pub mod sink;
 pub mod starknet;
+pub mod startup;
+pub mod subscription;
 pub mod tokio;
 
 // Import and re-export main types for convenience
@@ -99,29 +143,148 @@ use bevy::prelude::*;
 
 // Main prelude module that users can import
 pub mod prelude {
+    pub use crate::abi::{
+        AbiBinding, CallValidationError, ContractAbi, ContractAbiLoader, ContractAbiLoaderError,
+        abi_call,
+    };
+    pub use crate::approval::{ESTIMATE_MARGIN, FeeBounds, FeeEstimated, check_fee_estimates};
+    pub use crate::batch::{DEFAULT_MAX_CALLS_PER_TX, execute_batch};
+    pub use crate::block_time::{BlockInfo, NewBlock};
+    pub use crate::bump::bump_transaction;
+    pub use crate::calldata::{ParseCallError, ToCalldata, parse_call, parse_felt};
+    pub use crate::chains::{ChainKey, StarknetChains, check_chain_tasks, execute_transaction_on};
+    pub use crate::display::{DisplayFelt, FeltDisplay, felt_display, fmt_felt, set_felt_display};
+    pub use crate::dojo::{dojo_entity_id, dojo_model_storage_address};
+    pub use crate::entity::{PendingTransaction, TransactionResult, update_pending_transactions};
+    pub use crate::faucet::FaucetConfig;
+    pub use crate::fee_token::{ETH_TOKEN_ADDRESS, FeeToken};
+    pub use crate::hash::{HashFunction, encode_calls, hash_calls, hash_calls_with};
+    pub use crate::health::HealthSnapshot;
+    pub use crate::merkle::{MerkleTree, claim_call, claim_calldata, verify_proof};
+    pub use crate::param::Starknet;
+    pub use crate::query::{
+        AccountDeployedStatus, BlockQueryId, CallCompleted, CallId, CallReverted, CallTimedOut,
+        DEFAULT_CALL_TIMEOUT, DeployCheckId, EntrypointCheckId, EntrypointRegistry,
+        LatestBlockReceived, MetaId, PubKeyId, PublicKeyReceived, QueryEvents, QueryId,
+        StorageQueryId, StorageValueReceived, TokenMetadata, TokenMetadataReceived, TxEvents,
+        TxEventsId, UnknownEntrypoint, check_registered_entrypoints, check_sn_queries,
+        decode_cairo_result, query_account_deployed, query_call, query_entrypoints,
+        query_latest_block, query_public_key, query_storage, query_token_metadata, query_tx_events,
+    };
+    pub use crate::readonly::connect_readonly;
+    pub use crate::reconnect::{
+        Jitter, ReconnectAttempt, ReconnectExhausted, ReconnectPolicy, ReconnectSucceeded,
+        TaskEvents,
+    };
+    pub use crate::record::{DEFAULT_RECENT_TXS_CAPACITY, PendingTxInfo, TxRecord, TxStatus};
+    pub use crate::resubmit::resubmit_with;
+    pub use crate::signature::{DojoAccount, SignatureFormat};
+    pub use crate::sink::{
+        CustomTransactionSink, TransactionConfirmed, TransactionEvents, TransactionFailed,
+        TransactionSink,
+    };
     pub use crate::starknet::{
-        DefaultStarknetConfig, StarknetConnection, check_sn_task, connect_to_starknet,
-        init_starknet_connection,
+        ConfirmationPolling, ConnectError, ConnectOutcome, DefaultStarknetConfig,
+        InvalidCallReason, MAX_CALLDATA_LEN, SessionSpendLimit, StarknetConnection,
+        StarknetMetrics, SubmitOutcome, TransactionDropped, TransactionSubmitted, TxId,
+        cancel_connecting, check_sn_task, connect_to_starknet, execute_transaction,
+        init_starknet_connection, try_connect_to_starknet, validate_calls,
+    };
+    pub use crate::startup::{AutoConnect, auto_connect};
+    pub use crate::subscription::{
+        AccountTransaction, DEFAULT_MAX_BLOCK_RANGE, SubscriptionId, check_sn_subscriptions,
+        stop_subscriptions_on_exit, subscribe_account_txs,
     };
     pub use crate::tokio::{TokioPlugin, TokioRuntime};
+    pub use crate::{BevyDojoPlugin, PollSchedule, StarknetConnectSet, StarknetPollSet};
 
     // Re-export commonly used Starknet types
     pub use starknet::{
         accounts::{Account, SingleOwnerAccount},
         core::{
-            types::{Call, Felt, InvokeTransactionResult},
+            types::{Call, Felt, InvokeTransactionResult, U256},
             utils::get_selector_from_name,
         },
     };
 }
 
+/// System set of the systems polling Starknet tasks and emitting their events
+///
+/// `check_sn_task`, `check_chain_tasks`, `check_fee_estimates`,
+/// `check_sn_queries`, `check_registered_entrypoints` and
+/// `check_sn_subscriptions` run in this set, in the schedule selected by
+/// `PollSchedule`. Events they emit are readable in the same frame by systems
+/// ordered after the set, and in the next frame by the others.
+///
+/// # Example
+///
+/// ```no_run
+/// app.add_systems(Update, show_confirmations.after(StarknetPollSet));
+/// ```
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StarknetPollSet;
+
+/// System set of the `auto_connect` system, in `PostStartup`
+///
+/// It runs after every `Startup` system, so configuration resources they
+/// insert are in place. Order `PostStartup` systems with `.before` or `.after`
+/// this set to run them before the connection starts or once it has started.
+///
+/// # Example
+///
+/// ```no_run
+/// app.add_systems(PostStartup, load_saved_config.before(StarknetConnectSet));
+/// ```
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StarknetConnectSet;
+
+/// Schedule the `StarknetPollSet` runs in
+///
+/// Insert this resource before adding the `BevyDojoPlugin` to change it.
+/// With `First`, transaction, query and subscription events are emitted at the
+/// start of the frame, so every system in `Update` reads them in the same
+/// frame without being ordered after the set.
+///
+/// # Example
+///
+/// ```no_run
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .insert_resource(PollSchedule::First)
+///     .add_plugins(BevyDojoPlugin)
+///     .run();
+/// ```
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PollSchedule {
+    /// Poll in `First`, before the game's `Update` systems
+    First,
+    /// Poll in `Update`, alongside the game's systems
+    #[default]
+    Update,
+}
+
 /// Starknet integration plugin with default configuration
 ///
 /// This plugin initializes all resources needed for Starknet integration:
 /// - Adds the `TokioPlugin` to create a Tokio runtime
 /// - Initializes the `StarknetConnection` resource
 /// - Initializes the `DefaultStarknetConfig` resource
-/// - Registers the `check_sn_task` system to monitor async tasks
+/// - Initializes the `StarknetChains` resource for additional named chains
+/// - Registers the `check_sn_task` and `check_chain_tasks` systems to monitor async tasks,
+///   in the `StarknetPollSet` of the schedule selected by `PollSchedule`
+/// - Registers the transaction, reconnection and `NewBlock` events emitted by those systems
+/// - Registers the `check_fee_estimates` system and the `FeeEstimated` event
+/// - Registers the `check_sn_queries` system and the query result events
+/// - Initializes the `EntrypointRegistry` resource and registers the
+///   `check_registered_entrypoints` system validating it once connected
+/// - Registers the `check_sn_subscriptions` system and the subscription events
+/// - Registers the `auto_connect` system in the `StarknetConnectSet` of `PostStartup`,
+///   connecting if the `AutoConnect` resource exists
+/// - Registers the `stop_subscriptions_on_exit` system to stop subscriptions on `AppExit`
+/// - Registers the `update_pending_transactions` system after the `StarknetPollSet`,
+///   resolving the `PendingTransaction` components of entities
+/// - Registers the `ContractAbi` asset and its loader once plugins are built, if
+///   the `AssetPlugin` was added
 ///
 /// # Example
 ///
@@ -129,12 +292,10 @@ pub mod prelude {
 /// use bevy::prelude::*;
 /// use bevy_dojo::prelude::*;
 ///
-/// fn main() {
-///     App::new()
-///         .add_plugins(DefaultPlugins)
-///         .add_plugins(BevyDojoPlugin)
-///         .run();
-/// }
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(BevyDojoPlugin)
+///     .run();
 /// ```
 pub struct BevyDojoPlugin;
 
@@ -143,6 +304,132 @@ impl Plugin for BevyDojoPlugin {
         app.add_plugins(tokio::TokioPlugin)
             .init_resource::<starknet::StarknetConnection>()
             .init_resource::<starknet::DefaultStarknetConfig>()
-            .add_systems(Update, starknet::check_sn_task);
+            .init_resource::<chains::StarknetChains>()
+            .init_resource::<query::EntrypointRegistry>()
+            .init_resource::<starknet::PollDeadline>()
+            .add_event::<starknet::TransactionSubmitted>()
+            .add_event::<starknet::TransactionDropped>()
+            .add_event::<sink::TransactionConfirmed>()
+            .add_event::<sink::TransactionFailed>()
+            .add_event::<reconnect::ReconnectAttempt>()
+            .add_event::<reconnect::ReconnectSucceeded>()
+            .add_event::<reconnect::ReconnectExhausted>()
+            .add_event::<approval::FeeEstimated>()
+            .add_event::<query::TokenMetadataReceived>()
+            .add_event::<query::AccountDeployedStatus>()
+            .add_event::<query::StorageValueReceived>()
+            .add_event::<query::TxEvents>()
+            .add_event::<query::UnknownEntrypoint>()
+            .add_event::<query::PublicKeyReceived>()
+            .add_event::<query::LatestBlockReceived>()
+            .add_event::<query::CallTimedOut>()
+            .add_event::<query::CallCompleted>()
+            .add_event::<query::CallReverted>()
+            .add_event::<block_time::NewBlock>()
+            .add_event::<subscription::AccountTransaction>()
+            .add_systems(
+                PostStartup,
+                startup::auto_connect.in_set(StarknetConnectSet),
+            )
+            .add_systems(Last, subscription::stop_subscriptions_on_exit);
+
+        let poll_systems = (
+            starknet::check_sn_task,
+            chains::check_chain_tasks,
+            approval::check_fee_estimates,
+            query::check_sn_queries,
+            query::check_registered_entrypoints,
+            subscription::check_sn_subscriptions,
+        )
+            .in_set(StarknetPollSet);
+        let budget_system = starknet::start_poll_budget.before(StarknetPollSet);
+        let entity_systems = entity::update_pending_transactions.after(StarknetPollSet);
+        let systems = (budget_system, poll_systems, entity_systems);
+        match app.world().get_resource::<PollSchedule>().copied() {
+            Some(PollSchedule::First) => app.add_systems(First, systems),
+            _ => app.add_systems(Update, systems),
+        };
+    }
+
+    fn finish(&self, app: &mut App) {
+        // In `finish` rather than `build`, so the `AssetPlugin` may be added after this plugin
+        if app.world().contains_resource::<AssetServer>() {
+            app.init_asset::<abi::ContractAbi>()
+                .init_asset_loader::<abi::ContractAbiLoader>();
+        } else {
+            debug!("No AssetServer, ContractAbi assets won't be loaded");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::sink::TransactionConfirmed;
+
+    /// Frame of the app, and the frames a confirmation was emitted and read in
+    #[derive(Resource, Default)]
+    struct Frames {
+        current: u32,
+        emitted: Option<u32>,
+        read: Option<u32>,
+    }
+
+    fn note_emitted(mut frames: ResMut<Frames>, mut confirmed: EventReader<TransactionConfirmed>) {
+        if confirmed.read().next().is_some() {
+            frames.emitted = Some(frames.current);
+        }
+    }
+
+    fn note_read(mut frames: ResMut<Frames>, mut confirmed: EventReader<TransactionConfirmed>) {
+        if confirmed.read().next().is_some() {
+            frames.read = Some(frames.current);
+        }
+    }
+
+    /// Returns the frames a confirmation was emitted and read by an unordered `Update` system
+    fn confirmation_frames(schedule: PollSchedule) -> (u32, u32) {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = App::new();
+        app.insert_resource(schedule)
+            .add_plugins((MinimalPlugins, BevyDojoPlugin))
+            .init_resource::<Frames>()
+            .insert_resource(mock.config());
+        // Right after the poll, in the schedule it runs in
+        match schedule {
+            PollSchedule::First => app.add_systems(First, note_emitted.after(StarknetPollSet)),
+            PollSchedule::Update => app.add_systems(Update, note_emitted.after(StarknetPollSet)),
+        };
+        // Worst case for a system of `Update` that isn't ordered after the poll
+        match schedule {
+            PollSchedule::First => app.add_systems(Update, note_read),
+            PollSchedule::Update => app.add_systems(Update, note_read.before(StarknetPollSet)),
+        };
+        app.finish();
+        app.cleanup();
+        connect(&mut app);
+
+        with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(1)]));
+        assert!(update_until(&mut app, |app| {
+            let mut frames = app.world_mut().resource_mut::<Frames>();
+            frames.current += 1;
+            frames.read.is_some()
+        }));
+        let frames = app.world().resource::<Frames>();
+        (frames.emitted.unwrap(), frames.read.unwrap())
+    }
+
+    #[test]
+    fn events_are_read_in_the_next_frame_when_polling_in_update() {
+        let (emitted, read) = confirmation_frames(PollSchedule::Update);
+        assert_eq!(read, emitted + 1);
+    }
+
+    #[test]
+    fn events_are_read_in_the_same_frame_when_polling_in_first() {
+        let (emitted, read) = confirmation_frames(PollSchedule::First);
+        assert_eq!(read, emitted);
     }
 }
@@ -0,0 +1,219 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::starknet::StarknetConnection;
+
+/// Bound on the number of provider requests a connection runs at once
+///
+/// Clones share the same bound, so changing it applies to the tasks already
+/// running as well as to those spawned afterwards.
+#[derive(Clone, Default)]
+pub(crate) struct RequestLimiter(Arc<Mutex<LimiterState>>);
+
+struct LimiterState {
+    max: Option<usize>,
+    semaphore: Arc<Semaphore>,
+    /// Slots still to remove after lowering `max` while they were in use
+    debt: usize,
+}
+
+impl Default for LimiterState {
+    fn default() -> Self {
+        Self {
+            max: None,
+            semaphore: Arc::new(Semaphore::new(0)),
+            debt: 0,
+        }
+    }
+}
+
+impl RequestLimiter {
+    /// Wait for a request slot, released when the returned permit is dropped
+    ///
+    /// Returns `None` right away if requests aren't limited, or as soon as
+    /// the limit is lifted while waiting.
+    pub(crate) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        loop {
+            let semaphore = {
+                let state = self.0.lock().unwrap();
+                state.max?;
+                state.semaphore.clone()
+            };
+            // The semaphore is closed when the limit is lifted or replaced
+            let permit = semaphore.clone().acquire_owned().await.ok()?;
+            let mut state = self.0.lock().unwrap();
+            if state.debt > 0 && Arc::ptr_eq(&state.semaphore, &semaphore) {
+                state.debt -= 1;
+                permit.forget();
+                continue;
+            }
+            return Some(permit);
+        }
+    }
+
+    /// Returns the maximum number of requests run at once, if limited
+    pub(crate) fn max(&self) -> Option<usize> {
+        self.0.lock().unwrap().max
+    }
+
+    /// Change the maximum number of requests run at once
+    fn set_max(&self, max: Option<usize>) {
+        let mut state = self.0.lock().unwrap();
+        match (state.max, max) {
+            (Some(old), Some(new)) if new >= old => {
+                let repaid = state.debt.min(new - old);
+                state.debt -= repaid;
+                state.semaphore.add_permits(new - old - repaid);
+            }
+            (Some(old), Some(new)) => {
+                let excess = old - new;
+                state.debt += excess - state.semaphore.forget_permits(excess);
+            }
+            (_, max) => {
+                // Waiting tasks give up on the closed semaphore and go unlimited
+                state.semaphore.close();
+                state.semaphore = Arc::new(Semaphore::new(max.unwrap_or(0)));
+                state.debt = 0;
+            }
+        }
+        state.max = max;
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the maximum number of provider requests run at once, if limited
+    pub fn max_concurrent_requests(&self) -> Option<usize> {
+        self.limiter.max()
+    }
+
+    /// Limit the number of provider requests run at once
+    ///
+    /// Every background task of the connection touching the provider, from
+    /// connecting to sending transactions, waiting for receipts, queries and
+    /// subscription polls, waits for a free slot before each request. This
+    /// bounds the load on rate-limited RPC providers regardless of how much
+    /// work the game submits. The new limit also applies to tasks already
+    /// running, such as subscriptions and confirmation polling; requests
+    /// already running finish before a lowered limit takes effect.
+    /// Unlimited by default; a limit of zero is treated as one.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn setup(mut sn: ResMut<StarknetConnection>) {
+    ///     sn.set_max_concurrent_requests(Some(4));
+    /// }
+    /// ```
+    pub fn set_max_concurrent_requests(&mut self, max: Option<usize>) {
+        self.limiter.set_max(max.map(|max| max.max(1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faucet::FaucetConfig;
+    use crate::mock::*;
+    use crate::query::{AccountDeployedStatus, query_account_deployed};
+    use crate::sink::TransactionConfirmed;
+    use bevy::prelude::*;
+    use serde_json::json;
+    use starknet::core::types::Felt;
+    use std::time::Duration;
+
+    #[test]
+    fn limit_changes_apply_to_waiting_tasks() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let wait = Duration::from_millis(50);
+            let limiter = RequestLimiter::default();
+            assert!(limiter.acquire().await.is_none());
+
+            limiter.set_max(Some(1));
+            let first = limiter.acquire().await.unwrap();
+            let waiting = tokio::spawn({
+                let limiter = limiter.clone();
+                async move { limiter.acquire().await.is_some() }
+            });
+            tokio::time::sleep(wait).await;
+            assert!(!waiting.is_finished());
+            // Raising the limit lets the waiting task through
+            limiter.set_max(Some(2));
+            let second = tokio::time::timeout(wait, waiting).await.unwrap().unwrap();
+            assert!(second);
+
+            // Lowering it while both slots are used takes effect once they are released
+            let mut held = vec![first, limiter.acquire().await.unwrap()];
+            held.pop();
+            limiter.set_max(Some(1));
+            held.clear();
+            let only = limiter.acquire().await.unwrap();
+            assert!(tokio::time::timeout(wait, limiter.acquire()).await.is_err());
+
+            // Lifting the limit releases the waiting tasks
+            let waiting = tokio::spawn({
+                let limiter = limiter.clone();
+                async move { limiter.acquire().await.is_none() }
+            });
+            tokio::time::sleep(wait).await;
+            limiter.set_max(None);
+            assert!(tokio::time::timeout(wait, waiting).await.unwrap().unwrap());
+            drop(only);
+        });
+    }
+
+    #[test]
+    fn at_most_two_requests_run_at_once() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        for method in [
+            "starknet_getNonce",
+            "starknet_estimateFee",
+            "starknet_addInvokeTransaction",
+        ] {
+            mock.delay(method, Duration::from_millis(50));
+        }
+        let mut app = connected_app(&mock);
+        collect::<TransactionConfirmed>(&mut app);
+        with_connection(&mut app, |runtime, sn| {
+            sn.set_max_concurrent_requests(Some(2));
+            for n in 0..5 {
+                assert!(sn.execute(runtime, vec![call(n)]).is_queued());
+            }
+        });
+
+        assert!(update_until(&mut app, |app| {
+            collected::<TransactionConfirmed>(app).len() == 5
+        }));
+        assert_eq!(mock.max_in_flight(), 2);
+    }
+
+    #[test]
+    fn waiting_for_the_faucet_does_not_hold_a_request_slot() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on("", json!({}));
+        mock.on("starknet_getClassHashAt", json!("0xc1a55"));
+        // The tokens never arrive
+        mock.on_call(|_, _, _| Ok(vec![Felt::ZERO, Felt::ZERO]));
+        let mut app = connected_app(&mock);
+        collect::<AccountDeployedStatus>(&mut app);
+        with_connection(&mut app, |runtime, sn| {
+            sn.set_max_concurrent_requests(Some(1));
+            sn.set_faucet(Some(FaucetConfig {
+                url: mock.url(),
+                funding_timeout: Duration::from_secs(3),
+                poll_interval: Duration::from_millis(100),
+            }));
+            sn.execute(runtime, vec![call(1)]);
+        });
+        assert!(update_until(&mut app, |_| mock.count("") == 1));
+
+        run(&mut app, query_account_deployed).unwrap();
+        assert!(update_until(&mut app, |app| {
+            !collected::<AccountDeployedStatus>(app).is_empty()
+        }));
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 0);
+    }
+}
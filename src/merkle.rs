@@ -0,0 +1,221 @@
+use starknet::{
+    core::types::{Call, Felt},
+    macros::selector,
+};
+use starknet_crypto::{pedersen_hash, poseidon_hash_many};
+
+use crate::hash::HashFunction;
+
+/// Hash a pair of nodes, in ascending order so the result doesn't depend on
+/// which side each node is on
+///
+/// This matches the commutative hashers of the OpenZeppelin Cairo
+/// `merkle_proof` library, which feed both nodes to a hash state: with
+/// Pedersen, that's `pedersen(pedersen(0, low), high)`, and with Poseidon, the
+/// sponge hash of `[low, high]`. Neither is the plain two-felt hash of
+/// `HashFunction::hash`.
+pub fn hash_pair(hash: HashFunction, a: &Felt, b: &Felt) -> Felt {
+    let (low, high) = if a < b { (a, b) } else { (b, a) };
+    match hash {
+        HashFunction::Pedersen => pedersen_hash(&pedersen_hash(&Felt::ZERO, low), high),
+        HashFunction::Poseidon => poseidon_hash_many(&[*low, *high]),
+    }
+}
+
+/// Merkle tree over a list of leaves, for allowlists and airdrop claims
+///
+/// Leaves are used as-is, so they're usually the hash of the data being
+/// claimed, computed the same way as the contract does. A node without a
+/// sibling is promoted to the next layer unchanged.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy_dojo::hash::HashFunction;
+/// use bevy_dojo::merkle::{MerkleTree, claim_call, verify_proof};
+///
+/// let tree = MerkleTree::new(leaves, HashFunction::Poseidon);
+/// let proof = tree.proof(2).unwrap();
+/// assert!(verify_proof(HashFunction::Poseidon, tree.root(), tree.leaves()[2], &proof));
+///
+/// let call = claim_call(airdrop_address, &[amount], &proof);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    hash: HashFunction,
+    /// Every layer of the tree, from the leaves to the root
+    layers: Vec<Vec<Felt>>,
+}
+
+impl MerkleTree {
+    /// Build a tree from `leaves` using the `hash` function
+    ///
+    /// OpenZeppelin's `merkle_proof` library supports both functions; older
+    /// allowlists generally use Pedersen.
+    pub fn new(leaves: Vec<Felt>, hash: HashFunction) -> Self {
+        let mut layers = vec![leaves];
+        while let Some(layer) = layers.last().filter(|layer| layer.len() > 1) {
+            let next = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(hash, a, b),
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { hash, layers }
+    }
+
+    /// Returns the hash function of the tree
+    pub fn hash(&self) -> HashFunction {
+        self.hash
+    }
+
+    /// Returns the leaves of the tree
+    pub fn leaves(&self) -> &[Felt] {
+        &self.layers[0]
+    }
+
+    /// Returns the root of the tree, or zero if it has no leaves
+    pub fn root(&self) -> Felt {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or(Felt::ZERO)
+    }
+
+    /// Returns the proof of the leaf at `index`
+    ///
+    /// The proof lists the siblings on the path from the leaf to the root.
+    /// Returns `None` if `index` is out of bounds.
+    pub fn proof(&self, index: usize) -> Option<Vec<Felt>> {
+        if index >= self.leaves().len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        let mut index = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+/// Check that `leaf` belongs to the tree with the given `root`
+///
+/// # Arguments
+///
+/// * `hash` - The hash function the tree was built with
+/// * `root` - The root of the tree
+/// * `leaf` - The leaf to check
+/// * `proof` - The proof returned by `MerkleTree::proof`
+pub fn verify_proof(hash: HashFunction, root: Felt, leaf: Felt, proof: &[Felt]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| hash_pair(hash, &node, sibling))
+        == root
+}
+
+/// Assemble the calldata of a `claim` entrypoint taking a proof
+///
+/// The `args` come first, followed by the proof serialized as a `Span<felt252>`.
+pub fn claim_calldata(args: &[Felt], proof: &[Felt]) -> Vec<Felt> {
+    let mut calldata = Vec::with_capacity(args.len() + 1 + proof.len());
+    calldata.extend_from_slice(args);
+    calldata.push(Felt::from(proof.len()));
+    calldata.extend_from_slice(proof);
+    calldata
+}
+
+/// Build a call to the `claim` entrypoint of the contract at `to`
+///
+/// See `claim_calldata` for how the arguments are laid out.
+pub fn claim_call(to: Felt, args: &[Felt], proof: &[Felt]) -> Call {
+    Call {
+        to,
+        selector: selector!("claim"),
+        calldata: claim_calldata(args, proof),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet_crypto::poseidon_hash;
+
+    fn leaves(count: u64) -> Vec<Felt> {
+        (1..=count)
+            .map(|n| poseidon_hash_many(&[Felt::from(0xa11ce), Felt::from(n)]))
+            .collect()
+    }
+
+    #[test]
+    fn pairs_are_hashed_like_openzeppelin() {
+        let (a, b) = (Felt::from(2u8), Felt::from(1u8));
+        assert_eq!(
+            hash_pair(HashFunction::Pedersen, &a, &b),
+            pedersen_hash(&pedersen_hash(&Felt::ZERO, &b), &a)
+        );
+        assert_eq!(
+            hash_pair(HashFunction::Poseidon, &a, &b),
+            poseidon_hash_many(&[b, a])
+        );
+        assert_ne!(
+            hash_pair(HashFunction::Pedersen, &a, &b),
+            pedersen_hash(&b, &a)
+        );
+        assert_ne!(
+            hash_pair(HashFunction::Poseidon, &a, &b),
+            poseidon_hash(b, a)
+        );
+        for hash in [HashFunction::Pedersen, HashFunction::Poseidon] {
+            assert_eq!(hash_pair(hash, &a, &b), hash_pair(hash, &b, &a));
+        }
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        for hash in [HashFunction::Pedersen, HashFunction::Poseidon] {
+            for count in 1..=9 {
+                let tree = MerkleTree::new(leaves(count), hash);
+                for (index, leaf) in tree.leaves().iter().enumerate() {
+                    let proof = tree.proof(index).unwrap();
+                    assert!(verify_proof(hash, tree.root(), *leaf, &proof));
+                    assert!(!verify_proof(hash, tree.root(), leaf + Felt::ONE, &proof));
+                }
+                assert_eq!(tree.proof(count as usize), None);
+            }
+        }
+    }
+
+    #[test]
+    fn roots_depend_on_the_hash_function() {
+        let pedersen = MerkleTree::new(leaves(4), HashFunction::Pedersen);
+        let poseidon = MerkleTree::new(leaves(4), HashFunction::Poseidon);
+        assert_ne!(pedersen.root(), poseidon.root());
+        let proof = pedersen.proof(1).unwrap();
+        assert!(!verify_proof(
+            HashFunction::Poseidon,
+            pedersen.root(),
+            pedersen.leaves()[1],
+            &proof
+        ));
+    }
+
+    #[test]
+    fn root_of_four_leaves() {
+        let hash = HashFunction::Pedersen;
+        let leaves = leaves(4);
+        let tree = MerkleTree::new(leaves.clone(), hash);
+        let left = hash_pair(hash, &leaves[0], &leaves[1]);
+        let right = hash_pair(hash, &leaves[2], &leaves[3]);
+        assert_eq!(tree.root(), hash_pair(hash, &left, &right));
+        assert_eq!(tree.proof(2).unwrap(), vec![leaves[3], left]);
+    }
+}
@@ -0,0 +1,493 @@
+//! Local JSON-RPC server answering with canned responses, shared by the unit tests
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use starknet::core::types::{Call, Felt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+use crate::BevyDojoPlugin;
+use crate::starknet::{DefaultStarknetConfig, StarknetConnection};
+use crate::tokio::TokioRuntime;
+
+/// Account address used by `MockRpc::config`
+pub(crate) const ACCOUNT_ADDRESS: Felt = Felt::from_hex_unchecked("0x1234");
+
+/// Starknet error code of `TRANSACTION_HASH_NOT_FOUND`
+pub(crate) const TRANSACTION_HASH_NOT_FOUND: i64 = 29;
+/// Starknet error code of `CONTRACT_NOT_FOUND`
+pub(crate) const CONTRACT_NOT_FOUND: i64 = 20;
+/// Starknet error code of `CONTRACT_ERROR`
+pub(crate) const CONTRACT_ERROR: i64 = 40;
+
+/// JSON-RPC error returned by a handler
+#[derive(Debug, Clone)]
+pub(crate) struct RpcError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+    pub(crate) data: Option<Value>,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    pub(crate) fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+type Handler = Arc<dyn Fn(&Value) -> Result<Value, RpcError> + Send + Sync>;
+
+#[derive(Default)]
+struct Shared {
+    handlers: Mutex<HashMap<String, Handler>>,
+    requests: Mutex<Vec<(String, Value, Instant)>>,
+    batches: Mutex<Vec<usize>>,
+    delays: Mutex<HashMap<String, Duration>>,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+/// A JSON-RPC server on a local port, answering each method with its handler
+///
+/// Methods without a handler are answered with a "method not found" error.
+/// Bodies that aren't JSON-RPC requests are handled as the method `""`, with
+/// the whole body as params.
+/// `starknet_chainId` answers `SN_SEPOLIA` unless replaced. The server stops
+/// when dropped.
+pub(crate) struct MockRpc {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    hashes: Arc<AtomicU64>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockRpc {
+    pub(crate) fn start() -> Self {
+        let shared = Arc::new(Shared::default());
+        let (shutdown, mut stopped) = oneshot::channel::<()>();
+        let (bound, address) = std::sync::mpsc::channel();
+        let server = shared.clone();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                bound.send(listener.local_addr().unwrap()).unwrap();
+                loop {
+                    tokio::select! {
+                        _ = &mut stopped => break,
+                        accepted = listener.accept() => {
+                            if let Ok((stream, _)) = accepted {
+                                tokio::spawn(serve(stream, server.clone()));
+                            }
+                        }
+                    }
+                }
+            });
+        });
+
+        let mock = Self {
+            address: address.recv().unwrap(),
+            shared,
+            hashes: Arc::new(AtomicU64::new(0x100)),
+            _shutdown: shutdown,
+        };
+        mock.on("starknet_chainId", json!("0x534e5f5345504f4c4941"));
+        mock
+    }
+
+    /// Returns the URL of the server
+    pub(crate) fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// Returns a configuration connecting a test account to the server
+    pub(crate) fn config(&self) -> DefaultStarknetConfig {
+        DefaultStarknetConfig {
+            rpc_url: self.url(),
+            account_address: format!("{:#x}", ACCOUNT_ADDRESS),
+            private_key: "0x1".to_string(),
+            proxy: None,
+        }
+    }
+
+    /// Answer `method` with `handler`, called with the request params
+    pub(crate) fn on_fn(
+        &self,
+        method: &str,
+        handler: impl Fn(&Value) -> Result<Value, RpcError> + Send + Sync + 'static,
+    ) {
+        self.shared
+            .handlers
+            .lock()
+            .unwrap()
+            .insert(method.to_string(), Arc::new(handler));
+    }
+
+    /// Answer `method` with `result`
+    pub(crate) fn on(&self, method: &str, result: Value) {
+        self.on_fn(method, move |_| Ok(result.clone()));
+    }
+
+    /// Answer `method` with a JSON-RPC error
+    pub(crate) fn on_error(&self, method: &str, error: RpcError) {
+        self.on_fn(method, move |_| Err(error.clone()));
+    }
+
+    /// Answer `starknet_call` with `handler`, called with the contract, selector and calldata
+    pub(crate) fn on_call(
+        &self,
+        handler: impl Fn(Felt, Felt, Vec<Felt>) -> Result<Vec<Felt>, RpcError> + Send + Sync + 'static,
+    ) {
+        self.on_fn("starknet_call", move |params| {
+            let request = param(params, 0, "request");
+            let felt = |value: &Value| Felt::from_hex(value.as_str().unwrap_or_default()).unwrap();
+            let calldata = request["calldata"]
+                .as_array()
+                .map(|calldata| calldata.iter().map(felt).collect())
+                .unwrap_or_default();
+            let result = handler(
+                felt(&request["contract_address"]),
+                felt(&request["entry_point_selector"]),
+                calldata,
+            )?;
+            Ok(felts(&result))
+        });
+    }
+
+    /// Wait `delay` before answering `method`
+    pub(crate) fn delay(&self, method: &str, delay: Duration) {
+        self.shared
+            .delays
+            .lock()
+            .unwrap()
+            .insert(method.to_string(), delay);
+    }
+
+    /// Returns the params of every request made to `method`, in order
+    pub(crate) fn requests(&self, method: &str) -> Vec<Value> {
+        self.shared
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _, _)| name == method)
+            .map(|(_, params, _)| params.clone())
+            .collect()
+    }
+
+    /// Returns when each request to `method` was received, in order
+    pub(crate) fn request_times(&self, method: &str) -> Vec<Instant> {
+        self.shared
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _, _)| name == method)
+            .map(|(_, _, received_at)| *received_at)
+            .collect()
+    }
+
+    /// Returns the number of requests made to `method`
+    pub(crate) fn count(&self, method: &str) -> usize {
+        self.requests(method).len()
+    }
+
+    /// Returns the number of requests in each batch the server received
+    pub(crate) fn batches(&self) -> Vec<usize> {
+        self.shared.batches.lock().unwrap().clone()
+    }
+
+    /// Returns the largest number of requests the server handled at once
+    pub(crate) fn max_in_flight(&self) -> usize {
+        self.shared.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Answer the requests sending a transaction, up to its confirmation
+    ///
+    /// Transactions get the hashes `0x100`, `0x101` and so on, are accepted
+    /// on L2 right away and pay `fee` each.
+    pub(crate) fn accept_transactions(&self, fee: u128) {
+        self.on("starknet_getNonce", json!("0x0"));
+        self.on("starknet_estimateFee", json!([fee_estimate()]));
+        let hashes = self.hashes.clone();
+        self.on_fn("starknet_addInvokeTransaction", move |_| {
+            let hash = hashes.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({ "transaction_hash": format!("{hash:#x}") }))
+        });
+        self.on(
+            "starknet_getTransactionStatus",
+            json!({ "finality_status": "ACCEPTED_ON_L2", "execution_status": "SUCCEEDED" }),
+        );
+        self.on_fn("starknet_getTransactionReceipt", move |params| {
+            let hash = param(params, 0, "transaction_hash");
+            Ok(receipt(&hash, fee, &[]))
+        });
+    }
+}
+
+/// Returns the param at `index` of positional params, or named `name`
+pub(crate) fn param(params: &Value, index: usize, name: &str) -> Value {
+    match params {
+        Value::Array(params) => params.get(index).cloned().unwrap_or(Value::Null),
+        params => params.get(name).cloned().unwrap_or(Value::Null),
+    }
+}
+
+/// Returns `felts` as a JSON array of hex strings
+pub(crate) fn felts(felts: &[Felt]) -> Value {
+    felts.iter().map(|felt| format!("{felt:#x}")).collect()
+}
+
+/// Returns a valid call, distinct for every `n`
+pub(crate) fn call(n: u64) -> Call {
+    Call {
+        to: Felt::from(0x42u64),
+        selector: Felt::from(0x5e1u64),
+        calldata: vec![Felt::from(n)],
+    }
+}
+
+/// A fee estimate of 1000 FRI
+pub(crate) fn fee_estimate() -> Value {
+    json!({
+        "l1_gas_consumed": "0x0",
+        "l1_gas_price": "0x1",
+        "l2_gas_consumed": "0x3e8",
+        "l2_gas_price": "0x1",
+        "l1_data_gas_consumed": "0x0",
+        "l1_data_gas_price": "0x1",
+        "overall_fee": "0x3e8",
+        "unit": "FRI"
+    })
+}
+
+/// A successful invoke receipt of the transaction `hash` in block 1, emitting `events`
+pub(crate) fn receipt(hash: &Value, fee: u128, events: &[Value]) -> Value {
+    json!({
+        "type": "INVOKE",
+        "transaction_hash": hash,
+        "actual_fee": { "amount": format!("{fee:#x}"), "unit": "FRI" },
+        "execution_status": "SUCCEEDED",
+        "finality_status": "ACCEPTED_ON_L2",
+        "block_hash": "0xb1",
+        "block_number": 1,
+        "messages_sent": [],
+        "events": events,
+        "execution_resources": { "l1_gas": 0, "l1_data_gas": 0, "l2_gas": 1000 }
+    })
+}
+
+/// The block `number` with `hash` and state `root`, without transactions
+pub(crate) fn block(number: u64, hash: Felt, root: Felt, timestamp: u64) -> Value {
+    let price = json!({ "price_in_fri": "0x1", "price_in_wei": "0x1" });
+    json!({
+        "status": "ACCEPTED_ON_L2",
+        "block_hash": format!("{hash:#x}"),
+        "parent_hash": "0x0",
+        "block_number": number,
+        "new_root": format!("{root:#x}"),
+        "timestamp": timestamp,
+        "sequencer_address": "0x1",
+        "l1_gas_price": price,
+        "l2_gas_price": price,
+        "l1_data_gas_price": price,
+        "l1_da_mode": "BLOB",
+        "starknet_version": "0.13.4",
+        "transactions": []
+    })
+}
+
+async fn serve(mut stream: TcpStream, shared: Arc<Shared>) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let (header_len, content_len) = loop {
+        let Ok(read) = stream.read(&mut chunk).await else {
+            return;
+        };
+        if read == 0 {
+            return;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&buffer[..end]).to_ascii_lowercase();
+        let content_len = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .and_then(|len| len.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        break (end + 4, content_len);
+    };
+    while buffer.len() < header_len + content_len {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+    }
+    let Ok(request) = serde_json::from_slice::<Value>(&buffer[header_len..]) else {
+        return;
+    };
+
+    let in_flight = shared.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+    shared.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+    let response = match request {
+        Value::Array(requests) => {
+            shared.batches.lock().unwrap().push(requests.len());
+            let mut responses = Vec::new();
+            for request in requests {
+                responses.push(respond(&shared, request).await);
+            }
+            Value::Array(responses)
+        }
+        request => respond(&shared, request).await,
+    };
+    shared.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+    let body = response.to_string();
+    let reply = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(reply.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn respond(shared: &Shared, request: Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    // Plain JSON posts, like faucet requests, are kept whole under the method ""
+    let params = match request.get("params") {
+        Some(params) => params.clone(),
+        None if method.is_empty() => request.clone(),
+        None => Value::Null,
+    };
+    shared
+        .requests
+        .lock()
+        .unwrap()
+        .push((method.clone(), params.clone(), Instant::now()));
+
+    let delay = shared.delays.lock().unwrap().get(&method).copied();
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    let handler = shared.handlers.lock().unwrap().get(&method).cloned();
+    let result = match handler {
+        Some(handler) => handler(&params),
+        None => Err(RpcError::new(-32601, "Method not found")),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => {
+            let mut body = json!({ "code": error.code, "message": error.message });
+            if let Some(data) = error.data {
+                body["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": body })
+        }
+    }
+}
+
+/// Returns an app with the `BevyDojoPlugin`, built and ready to be updated
+pub(crate) fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, BevyDojoPlugin));
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Returns a test app connected to `mock` with an account
+pub(crate) fn connected_app(mock: &MockRpc) -> App {
+    let mut app = test_app();
+    app.insert_resource(mock.config());
+    connect(&mut app);
+    app
+}
+
+/// Connect the app with its `DefaultStarknetConfig`, waiting until connected
+pub(crate) fn connect(app: &mut App) {
+    let config = app.world().resource::<DefaultStarknetConfig>().clone();
+    with_connection(app, |runtime, sn| sn.connect(runtime, &config));
+    assert!(update_until(app, |app| connection(app).is_connected()));
+}
+
+/// Update `app` until `done` returns true, returning false after 10 seconds
+pub(crate) fn update_until(app: &mut App, mut done: impl FnMut(&mut App) -> bool) -> bool {
+    let started_at = Instant::now();
+    while started_at.elapsed() < Duration::from_secs(10) {
+        app.update();
+        if done(app) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    false
+}
+
+/// Events of type `E` collected by `collect`
+#[derive(Resource)]
+pub(crate) struct Collected<E>(pub(crate) Vec<E>);
+
+/// Keep every event of type `E` emitted from now on in a `Collected<E>` resource
+pub(crate) fn collect<E: Event + Clone>(app: &mut App) {
+    app.insert_resource(Collected::<E>(Vec::new())).add_systems(
+        Last,
+        |mut events: EventReader<E>, mut collected: ResMut<Collected<E>>| {
+            collected.0.extend(events.read().cloned());
+        },
+    );
+}
+
+/// Returns the events of type `E` collected so far
+pub(crate) fn collected<E: Event + Clone>(app: &App) -> Vec<E> {
+    app.world().resource::<Collected<E>>().0.clone()
+}
+
+/// Run `system` once on the world of `app`, returning its output
+pub(crate) fn run<Out: 'static, M>(app: &mut App, system: impl IntoSystem<(), Out, M>) -> Out {
+    app.world_mut().run_system_once(system).unwrap()
+}
+
+/// Returns the connection of `app`
+pub(crate) fn connection(app: &App) -> &StarknetConnection {
+    app.world().resource::<StarknetConnection>()
+}
+
+/// Run `f` with the runtime and the connection of `app`
+pub(crate) fn with_connection<R>(
+    app: &mut App,
+    f: impl FnOnce(&TokioRuntime, &mut StarknetConnection) -> R,
+) -> R {
+    app.world_mut()
+        .resource_scope(|world, mut sn: Mut<StarknetConnection>| {
+            f(world.resource::<TokioRuntime>(), &mut sn)
+        })
+}
@@ -0,0 +1,135 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use starknet::core::types::Call;
+
+use crate::approval::FeeBounds;
+use crate::starknet::{
+    ConnectOutcome, DefaultStarknetConfig, StarknetConnection, SubmitOutcome, TxId,
+};
+use crate::tokio::TokioRuntime;
+
+/// System parameter bundling everything needed to use Starknet from a system
+///
+/// Instead of threading `Res<TokioRuntime>`, `Res<DefaultStarknetConfig>` and
+/// `ResMut<StarknetConnection>` through every system, take a single
+/// `Starknet` parameter.
+///
+/// # Example
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_dojo::prelude::*;
+///
+/// fn keyboard_control(keys: Res<ButtonInput<KeyCode>>, mut starknet: Starknet) {
+///     if keys.just_pressed(KeyCode::KeyC) {
+///         starknet.connect();
+///     }
+///
+///     if keys.just_pressed(KeyCode::KeyT) && starknet.is_connected() {
+///         starknet.execute(vec![/* calls */]);
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct Starknet<'w> {
+    runtime: Res<'w, TokioRuntime>,
+    config: Res<'w, DefaultStarknetConfig>,
+    connection: ResMut<'w, StarknetConnection>,
+}
+
+impl Starknet<'_> {
+    /// Start connecting to Starknet unless already connected or connecting
+    pub fn connect(&mut self) -> ConnectOutcome {
+        self.connection.connect(&self.runtime, &self.config)
+    }
+
+    /// Start connecting to Starknet read-only, unless already connected or connecting
+    pub fn connect_readonly(&mut self) -> ConnectOutcome {
+        self.connection
+            .connect_readonly(&self.runtime, &self.config)
+    }
+
+    /// Abort the connection attempt in progress, if any
+    pub fn cancel_connecting(&mut self) -> bool {
+        self.connection.cancel_connecting()
+    }
+
+    /// Queue a transaction executing `calls`
+    pub fn execute(&mut self, calls: Vec<Call>) -> SubmitOutcome {
+        self.connection.execute(&self.runtime, calls)
+    }
+
+    /// Send a transaction awaiting fee approval with the given resource bounds
+    pub fn approve_fee(&mut self, tx_id: TxId, bounds: FeeBounds) -> bool {
+        self.connection.approve_fee(&self.runtime, tx_id, bounds)
+    }
+
+    /// Cancel a transaction awaiting fee approval
+    pub fn reject_fee(&mut self, tx_id: TxId) -> bool {
+        self.connection.reject_fee(tx_id)
+    }
+
+    /// Returns true if the connection is established
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_connected()
+    }
+
+    /// Returns true if connected read-only, without an account
+    pub fn is_read_only(&self) -> bool {
+        self.connection.is_read_only()
+    }
+
+    /// Returns true if currently trying to establish a connection
+    pub fn is_connecting(&self) -> bool {
+        self.connection.is_connecting()
+    }
+
+    /// Returns the Tokio runtime
+    pub fn runtime(&self) -> &TokioRuntime {
+        &self.runtime
+    }
+
+    /// Returns the configuration used to connect
+    pub fn config(&self) -> &DefaultStarknetConfig {
+        &self.config
+    }
+
+    /// Returns the underlying connection resource
+    pub fn connection(&self) -> &StarknetConnection {
+        &self.connection
+    }
+
+    /// Returns the underlying connection resource mutably
+    pub fn connection_mut(&mut self) -> &mut StarknetConnection {
+        &mut self.connection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    fn play(mut starknet: Starknet, mut submitted: Local<bool>) {
+        if !starknet.is_connected() {
+            starknet.connect();
+        } else if !*submitted {
+            *submitted = starknet.execute(vec![call(0)]).is_queued();
+        }
+    }
+
+    #[test]
+    fn system_connects_and_submits() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = test_app();
+        app.insert_resource(mock.config()).add_systems(Update, play);
+
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 1
+        }));
+        assert_eq!(mock.count("starknet_chainId"), 1);
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 1);
+    }
+}
@@ -0,0 +1,1601 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use starknet::{
+    accounts::{Account, ConnectedAccount},
+    core::{
+        types::{
+            BlockId, BlockTag, ContractClass, ContractExecutionError, Event as StarknetEvent, Felt,
+            FunctionCall, StarknetError, TransactionReceipt, requests::CallRequest,
+        },
+        utils::{get_selector_from_name, parse_cairo_short_string},
+    },
+    macros::selector,
+    providers::{AnyProvider, Provider, ProviderError, ProviderRequestData, ProviderResponseData},
+};
+use tokio::task::JoinHandle;
+
+use crate::abi::AbiBinding;
+use crate::block_time::{BlockInfo, read_latest_block};
+use crate::display::{FeltDisplay, fmt_felt};
+use crate::limit::RequestLimiter;
+use crate::starknet::{PollDeadline, StarknetConnection, felt_to_u128};
+use crate::tokio::TokioRuntime;
+
+/// Default time queries may take before timing out, see `set_call_timeout`
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Identifier of a query started with `query_token_metadata`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MetaId(pub u64);
+
+/// Metadata of an ERC20-style token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub decimals: u8,
+    pub symbol: String,
+    pub name: String,
+}
+
+/// Event emitted when the metadata requested with `query_token_metadata` is available
+#[derive(Event, Debug, Clone)]
+pub struct TokenMetadataReceived {
+    pub id: MetaId,
+    pub token: Felt,
+    pub metadata: TokenMetadata,
+}
+
+/// Identifier of a query started with `query_account_deployed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeployCheckId(pub u64);
+
+/// Event emitted with the result of `query_account_deployed`
+#[derive(Event, Debug, Clone)]
+pub struct AccountDeployedStatus {
+    pub id: DeployCheckId,
+    pub deployed: bool,
+}
+
+/// Identifier of a query started with `query_storage`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StorageQueryId(pub u64);
+
+/// Event emitted with the value read by `query_storage`
+#[derive(Event, Debug, Clone)]
+pub struct StorageValueReceived {
+    pub id: StorageQueryId,
+    pub contract: Felt,
+    pub key: Felt,
+    pub value: Felt,
+}
+
+/// Identifier of a query started with `query_tx_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TxEventsId(pub u64);
+
+/// Event emitted with the events of the transaction requested with `query_tx_events`
+#[derive(Event, Debug, Clone)]
+pub struct TxEvents {
+    pub id: TxEventsId,
+    pub hash: Felt,
+    pub events: Vec<StarknetEvent>,
+}
+
+/// Identifier of a query started with `query_latest_block`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockQueryId(pub u64);
+
+/// Event emitted with the block read by `query_latest_block`
+#[derive(Event, Debug, Clone)]
+pub struct LatestBlockReceived {
+    pub id: BlockQueryId,
+    pub block: BlockInfo,
+}
+
+/// Identifier of a call started with `query_call`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallId(pub u64);
+
+/// Event emitted with the values returned by a successful `query_call`
+///
+/// If the call was made with `returns_result`, `result` holds the payload of
+/// the `Ok` variant, without the variant index.
+#[derive(Event, Debug, Clone)]
+pub struct CallCompleted {
+    pub id: CallId,
+    pub result: Vec<Felt>,
+}
+
+/// Event emitted when a `query_call` panics, or returns an `Err` with `returns_result`
+#[derive(Event, Debug, Clone)]
+pub struct CallReverted {
+    pub id: CallId,
+    /// Revert reason reported by the provider, or the rendered error payload
+    pub reason: String,
+}
+
+/// Identifier of any query, as reported by `CallTimedOut`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryId {
+    TokenMetadata(MetaId),
+    AccountDeployed(DeployCheckId),
+    Storage(StorageQueryId),
+    TxEvents(TxEventsId),
+    PublicKey(PubKeyId),
+    Entrypoints(EntrypointCheckId),
+    LatestBlock(BlockQueryId),
+    Call(CallId),
+}
+
+/// Event emitted instead of the result of a query that took longer than `call_timeout`
+#[derive(Event, Debug, Clone)]
+pub struct CallTimedOut {
+    pub id: QueryId,
+}
+
+/// Identifier of a query started with `query_public_key`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PubKeyId(pub u64);
+
+/// Event emitted with the public key read by `query_public_key`
+#[derive(Event, Debug, Clone)]
+pub struct PublicKeyReceived {
+    pub id: PubKeyId,
+    pub key: Felt,
+}
+
+/// Identifier of a check started with `query_entrypoints`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntrypointCheckId(pub u64);
+
+/// Event emitted for each checked entrypoint the target contract doesn't expose
+///
+/// This usually means the entrypoint name has a typo or the contract address
+/// points to a different contract than expected.
+#[derive(Event, Debug, Clone)]
+pub struct UnknownEntrypoint {
+    pub id: EntrypointCheckId,
+    pub contract: Felt,
+    pub entrypoint: String,
+}
+
+impl fmt::Display for UnknownEntrypoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "contract {} has no entrypoint `{}`",
+            fmt_felt(&self.contract),
+            self.entrypoint
+        )
+    }
+}
+
+/// Contract entrypoints to check once the connection is established
+///
+/// Entrypoints registered here are checked against the classes deployed at
+/// their contract addresses by the `check_registered_entrypoints` system as
+/// soon as the `StarknetConnection` connects. Entrypoints registered after
+/// that are checked on the next frame. Unknown entrypoints are logged and
+/// reported as `UnknownEntrypoint` events.
+///
+/// # Example
+///
+/// ```no_run
+/// fn register(mut registry: ResMut<EntrypointRegistry>) {
+///     registry.register(game_address, "spawn");
+///     registry.register(game_address, "move");
+///     registry.register_binding(&counter_binding);
+/// }
+/// ```
+#[derive(Resource, Debug, Clone, Default)]
+pub struct EntrypointRegistry {
+    entrypoints: Vec<(Felt, String)>,
+    /// Number of entrypoints already checked, from the start of `entrypoints`
+    checked: usize,
+}
+
+impl EntrypointRegistry {
+    /// Register an entrypoint of the contract at `contract`
+    pub fn register(&mut self, contract: Felt, entrypoint: &str) {
+        self.entrypoints.push((contract, entrypoint.to_string()));
+    }
+
+    /// Register every entrypoint of an `AbiBinding`
+    pub fn register_binding(&mut self, binding: &AbiBinding) {
+        for entrypoint in binding.entrypoint_names() {
+            self.register(binding.address, entrypoint);
+        }
+    }
+
+    /// Returns the registered contract and entrypoint pairs
+    pub fn entrypoints(&self) -> &[(Felt, String)] {
+        &self.entrypoints
+    }
+}
+
+/// Error produced by a read query
+#[derive(Debug)]
+pub(crate) enum QueryError {
+    Provider(ProviderError),
+    Decode(&'static str),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Provider(err) => write!(f, "{err}"),
+            QueryError::Decode(what) => write!(f, "failed to decode {what}"),
+        }
+    }
+}
+
+impl From<ProviderError> for QueryError {
+    fn from(err: ProviderError) -> Self {
+        QueryError::Provider(err)
+    }
+}
+
+pub(crate) enum QueryResponse {
+    TokenMetadata {
+        id: MetaId,
+        token: Felt,
+        result: Result<TokenMetadata, QueryError>,
+    },
+    AccountDeployed {
+        id: DeployCheckId,
+        result: Result<bool, QueryError>,
+    },
+    Storage {
+        id: StorageQueryId,
+        contract: Felt,
+        key: Felt,
+        result: Result<Felt, QueryError>,
+    },
+    TxEvents {
+        id: TxEventsId,
+        hash: Felt,
+        result: Result<Vec<StarknetEvent>, QueryError>,
+    },
+    Entrypoints {
+        id: EntrypointCheckId,
+        result: Result<Vec<(Felt, String)>, QueryError>,
+    },
+    PublicKey {
+        id: PubKeyId,
+        result: Result<Felt, QueryError>,
+    },
+    LatestBlock {
+        id: BlockQueryId,
+        result: Result<BlockInfo, QueryError>,
+    },
+    Call {
+        id: CallId,
+        result: Result<CallOutput, QueryError>,
+    },
+    TimedOut {
+        id: QueryId,
+    },
+}
+
+/// Decoded outcome of a `query_call`
+pub(crate) enum CallOutput {
+    Returned(Vec<Felt>),
+    Reverted(String),
+}
+
+/// In-flight queries and cached query results of a `StarknetConnection`
+pub(crate) struct QueryState {
+    next_id: u64,
+    tasks: Vec<JoinHandle<QueryResponse>>,
+    ready: Vec<QueryResponse>,
+    token_metadata: HashMap<Felt, TokenMetadata>,
+    /// Public key of the connected account
+    pub(crate) public_key: Option<Felt>,
+    call_timeout: Duration,
+}
+
+impl Default for QueryState {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            tasks: Vec::new(),
+            ready: Vec::new(),
+            token_metadata: HashMap::new(),
+            public_key: None,
+            call_timeout: DEFAULT_CALL_TIMEOUT,
+        }
+    }
+}
+
+impl QueryState {
+    /// Returns the number of running query tasks
+    pub(crate) fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Spawn the query `id`, answering `QueryResponse::TimedOut` after `call_timeout`
+    fn spawn(
+        &mut self,
+        runtime: &TokioRuntime,
+        limiter: RequestLimiter,
+        id: QueryId,
+        query: impl Future<Output = QueryResponse> + Send + 'static,
+    ) {
+        let call_timeout = self.call_timeout;
+        self.tasks.push(runtime.runtime.spawn(async move {
+            let query = async move {
+                let _permit = limiter.acquire().await;
+                query.await
+            };
+            tokio::time::timeout(call_timeout, query)
+                .await
+                .unwrap_or(QueryResponse::TimedOut { id })
+        }));
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the cached metadata of `token`, if it has been queried before
+    pub fn token_metadata(&self, token: &Felt) -> Option<&TokenMetadata> {
+        self.queries.token_metadata.get(token)
+    }
+
+    /// Returns the cached public key of the connected account, if it has been queried before
+    pub fn public_key(&self) -> Option<Felt> {
+        self.queries.public_key
+    }
+
+    /// Returns how long queries may take before timing out
+    pub fn call_timeout(&self) -> Duration {
+        self.queries.call_timeout
+    }
+
+    /// Set how long queries may take before timing out
+    ///
+    /// Queries such as `query_storage` usually feed the UI, so they should
+    /// fail fast rather than wait on a slow provider. A query that doesn't
+    /// complete within `call_timeout`, including the wait for a request slot
+    /// of `set_max_concurrent_requests`, is abandoned and reported as a
+    /// `CallTimedOut` event instead of its result event. Transactions aren't
+    /// affected, see `ConfirmationPolling` for how long they're watched.
+    /// Defaults to `DEFAULT_CALL_TIMEOUT`, applies to queries started after
+    /// the change.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn setup(mut sn: ResMut<StarknetConnection>) {
+    ///     sn.set_call_timeout(Duration::from_secs(3));
+    /// }
+    /// ```
+    pub fn set_call_timeout(&mut self, call_timeout: Duration) {
+        self.queries.call_timeout = call_timeout;
+    }
+}
+
+/// Query the decimals, symbol and name of a token contract
+///
+/// The three values are read in a single batched JSON-RPC request and cached,
+/// so repeated queries for the same token are answered without touching the
+/// provider. The result is
+/// delivered as a `TokenMetadataReceived` event by the `check_sn_queries` system.
+///
+/// Both short-string (`felt252`) and `ByteArray` encodings of `symbol` and
+/// `name` are supported.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `token` - The address of the token contract
+///
+/// # Returns
+///
+/// * `Some(MetaId)` identifying the query
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn request_strk_metadata(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     let strk = Felt::from_hex_unchecked(
+///         "0x04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d",
+///     );
+///     query_token_metadata(runtime, sn, strk);
+/// }
+///
+/// fn show_metadata(mut events: EventReader<TokenMetadataReceived>) {
+///     for event in events.read() {
+///         println!("{} has {} decimals", event.metadata.symbol, event.metadata.decimals);
+///     }
+/// }
+/// ```
+pub fn query_token_metadata(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    token: Felt,
+) -> Option<MetaId> {
+    let reader = sn.reader()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = MetaId(queries.next_id());
+
+    if let Some(metadata) = queries.token_metadata.get(&token).cloned() {
+        queries.ready.push(QueryResponse::TokenMetadata {
+            id,
+            token,
+            result: Ok(metadata),
+        });
+        return Some(id);
+    }
+
+    queries.spawn(&runtime, limiter, QueryId::TokenMetadata(id), async move {
+        let result = read_token_metadata(reader.provider(), token).await;
+        QueryResponse::TokenMetadata { id, token, result }
+    });
+    Some(id)
+}
+
+/// Check whether the connected account is deployed on-chain
+///
+/// A fresh account address has no contract deployed until its deploy
+/// transaction lands, and can't send transactions before that. This reads the
+/// class hash at the account address, treating `ContractNotFound` as not
+/// deployed, and delivers the result as an `AccountDeployedStatus` event.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+///
+/// # Returns
+///
+/// * `Some(DeployCheckId)` identifying the query
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn check_account(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     query_account_deployed(runtime, sn);
+/// }
+///
+/// fn onboarding(mut events: EventReader<AccountDeployedStatus>) {
+///     for status in events.read() {
+///         if !status.deployed {
+///             println!("Please deploy your account first");
+///         }
+///     }
+/// }
+/// ```
+pub fn query_account_deployed(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+) -> Option<DeployCheckId> {
+    let account = sn.account().cloned()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = DeployCheckId(queries.next_id());
+
+    queries.spawn(
+        &runtime,
+        limiter,
+        QueryId::AccountDeployed(id),
+        async move {
+            let result = match account
+                .provider()
+                .get_class_hash_at(BlockId::Tag(BlockTag::Latest), account.address())
+                .await
+            {
+                Ok(_) => Ok(true),
+                Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => Ok(false),
+                Err(err) => Err(QueryError::Provider(err)),
+            };
+            QueryResponse::AccountDeployed { id, result }
+        },
+    );
+    Some(id)
+}
+
+/// Read a storage slot of a contract
+///
+/// The value is delivered as a `StorageValueReceived` event by the
+/// `check_sn_queries` system. Combined with `dojo_model_storage_address`, this
+/// reads Dojo model members straight from the world contract.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `contract` - The address of the contract
+/// * `key` - The storage address to read
+///
+/// # Returns
+///
+/// * `Some(StorageQueryId)` identifying the query
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn read_slot(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     query_storage(runtime, sn, contract_address, storage_key);
+/// }
+///
+/// fn show_slot(mut events: EventReader<StorageValueReceived>) {
+///     for event in events.read() {
+///         println!("{:#x} = {:#x}", event.key, event.value);
+///     }
+/// }
+/// ```
+pub fn query_storage(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    contract: Felt,
+    key: Felt,
+) -> Option<StorageQueryId> {
+    let reader = sn.reader()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = StorageQueryId(queries.next_id());
+
+    queries.spawn(&runtime, limiter, QueryId::Storage(id), async move {
+        let result = reader
+            .provider()
+            .get_storage_at(contract, key, BlockId::Tag(BlockTag::Latest))
+            .await
+            .map_err(QueryError::Provider);
+        QueryResponse::Storage {
+            id,
+            contract,
+            key,
+            result,
+        }
+    });
+    Some(id)
+}
+
+/// Query the events emitted by a transaction
+///
+/// This fetches the receipt of the transaction and extracts its events, which
+/// avoids scanning blocks to find, for example, the id of a newly-minted NFT.
+/// The events are delivered as a `TxEvents` event by the `check_sn_queries`
+/// system.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `hash` - The hash of the transaction
+///
+/// # Returns
+///
+/// * `Some(TxEventsId)` identifying the query
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn show_mint(mut events: EventReader<TxEvents>) {
+///     for tx in events.read() {
+///         for event in &tx.events {
+///             println!("{:#x} emitted {} keys", event.from_address, event.keys.len());
+///         }
+///     }
+/// }
+/// ```
+pub fn query_tx_events(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    hash: Felt,
+) -> Option<TxEventsId> {
+    let reader = sn.reader()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = TxEventsId(queries.next_id());
+
+    queries.spawn(&runtime, limiter, QueryId::TxEvents(id), async move {
+        let result = reader
+            .provider()
+            .get_transaction_receipt(hash)
+            .await
+            .map(|receipt| receipt_events(receipt.receipt))
+            .map_err(QueryError::Provider);
+        QueryResponse::TxEvents { id, hash, result }
+    });
+    Some(id)
+}
+
+/// Query the hash and state root of the latest block
+///
+/// The latest block is also read periodically and available from
+/// `StarknetConnection::latest_block`. This reads it right away, for
+/// verification flows that need an up-to-date state root. The block is
+/// delivered as a `LatestBlockReceived` event by the `check_sn_queries` system.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+///
+/// # Returns
+///
+/// * `Some(BlockQueryId)` identifying the query
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn read_block(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     query_latest_block(runtime, sn);
+/// }
+///
+/// fn verify(mut events: EventReader<LatestBlockReceived>) {
+///     for event in events.read() {
+///         println!("Block {:#x} has root {:#x}", event.block.block_hash, event.block.new_root);
+///     }
+/// }
+/// ```
+pub fn query_latest_block(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+) -> Option<BlockQueryId> {
+    let reader = sn.reader()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = BlockQueryId(queries.next_id());
+
+    queries.spawn(&runtime, limiter, QueryId::LatestBlock(id), async move {
+        let result = match read_latest_block(reader.provider()).await {
+            Ok(Some(block)) => Ok(block),
+            Ok(None) => Err(QueryError::Decode("latest block")),
+            Err(err) => Err(QueryError::Provider(err)),
+        };
+        QueryResponse::LatestBlock { id, result }
+    });
+    Some(id)
+}
+
+/// Call a view entrypoint of a contract
+///
+/// A contract panic doesn't return any value: the provider reports it as an
+/// error, which is delivered as a `CallReverted` event with the revert reason
+/// rather than as a failed query. Entrypoints returning a Cairo `Result` do
+/// return their error though, as a `1` variant index followed by the error.
+/// With `returns_result`, the returned felts are decoded with
+/// `decode_cairo_result`, so an `Err` is also delivered as `CallReverted`,
+/// and an `Ok` as `CallCompleted` with the payload only.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `call` - The contract, entrypoint selector and calldata to call
+/// * `returns_result` - Whether the entrypoint returns a Cairo `Result`
+///
+/// # Returns
+///
+/// * `Some(CallId)` identifying the call
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn try_claim(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     let call = FunctionCall {
+///         contract_address: game_address,
+///         entry_point_selector: selector!("can_claim"),
+///         calldata: vec![player],
+///     };
+///     query_call(runtime, sn, call, true);
+/// }
+///
+/// fn show_claim(mut reverted: EventReader<CallReverted>) {
+///     for event in reverted.read() {
+///         println!("Can't claim: {}", event.reason);
+///     }
+/// }
+/// ```
+pub fn query_call(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    call: FunctionCall,
+    returns_result: bool,
+) -> Option<CallId> {
+    let reader = sn.reader()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = CallId(queries.next_id());
+
+    queries.spawn(&runtime, limiter, QueryId::Call(id), async move {
+        let result = match reader
+            .provider()
+            .call(call, BlockId::Tag(BlockTag::Latest))
+            .await
+        {
+            Ok(felts) if returns_result => match decode_cairo_result(&felts) {
+                Some(Ok(payload)) => Ok(CallOutput::Returned(payload.to_vec())),
+                Some(Err(error)) => Ok(CallOutput::Reverted(describe_felts(error))),
+                None => Err(QueryError::Decode("Cairo result")),
+            },
+            Ok(felts) => Ok(CallOutput::Returned(felts)),
+            Err(ProviderError::StarknetError(StarknetError::ContractError(data))) => {
+                Ok(CallOutput::Reverted(revert_reason(&data.revert_error)))
+            }
+            Err(err) => Err(QueryError::Provider(err)),
+        };
+        QueryResponse::Call { id, result }
+    });
+    Some(id)
+}
+
+/// Split felts returned by an entrypoint returning a Cairo `Result`
+///
+/// A `Result` is serialized as its variant index, `0` for `Ok` and `1` for
+/// `Err`, followed by the serialization of the value.
+///
+/// # Returns
+///
+/// * `Some(Ok(payload))` for an `Ok` variant
+/// * `Some(Err(payload))` for an `Err` variant
+/// * `None` if `felts` isn't a serialized `Result`
+pub fn decode_cairo_result(felts: &[Felt]) -> Option<Result<&[Felt], &[Felt]>> {
+    match felts.split_first()? {
+        (index, payload) if *index == Felt::ZERO => Some(Ok(payload)),
+        (index, payload) if *index == Felt::ONE => Some(Err(payload)),
+        _ => None,
+    }
+}
+
+/// Render an error payload, showing felts that are short strings as text
+fn describe_felts(felts: &[Felt]) -> String {
+    felts
+        .iter()
+        .map(|felt| FeltDisplay::ShortString.fmt_felt(felt).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the innermost message of a contract execution error
+fn revert_reason(error: &ContractExecutionError) -> String {
+    match error {
+        ContractExecutionError::Message(message) => message.clone(),
+        ContractExecutionError::Nested(inner) => revert_reason(&inner.error),
+    }
+}
+
+/// Query the public key of the connected account
+///
+/// This calls the `get_public_key` entrypoint of the account contract, or
+/// `getPublicKey` for accounts following the older camel case convention if
+/// the first call fails because the entrypoint doesn't exist. Other errors,
+/// such as transport failures, fail the query without a second call. The
+/// key is cached, so later queries are answered without touching the
+/// provider, and delivered as a `PublicKeyReceived` event by the
+/// `check_sn_queries` system.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+///
+/// # Returns
+///
+/// * `Some(PubKeyId)` identifying the query
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn read_key(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     query_public_key(runtime, sn);
+/// }
+///
+/// fn show_key(mut events: EventReader<PublicKeyReceived>) {
+///     for event in events.read() {
+///         println!("Public key: {:#x}", event.key);
+///     }
+/// }
+/// ```
+pub fn query_public_key(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+) -> Option<PubKeyId> {
+    let account = sn.account().cloned()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = PubKeyId(queries.next_id());
+
+    if let Some(key) = queries.public_key {
+        queries.ready.push(QueryResponse::PublicKey {
+            id,
+            result: Ok(key),
+        });
+        return Some(id);
+    }
+
+    queries.spawn(&runtime, limiter, QueryId::PublicKey(id), async move {
+        let result = read_public_key(account.provider(), account.address()).await;
+        QueryResponse::PublicKey { id, result }
+    });
+    Some(id)
+}
+
+/// Check that contracts expose the entrypoints the game is going to call
+///
+/// Calling an entrypoint that doesn't exist only fails once the transaction
+/// is executed. This fetches the class deployed at each contract address and
+/// looks the entrypoint selectors up among its external entrypoints, so typos
+/// are caught early. Each unknown entrypoint is logged and delivered as an
+/// `UnknownEntrypoint` event by the `check_sn_queries` system.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `entrypoints` - The contract addresses and entrypoint names to check
+///
+/// # Returns
+///
+/// * `Some(EntrypointCheckId)` identifying the check
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn check_game(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     query_entrypoints(runtime, sn, vec![(game_address, "spawn".to_string())]);
+/// }
+/// ```
+pub fn query_entrypoints(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    entrypoints: Vec<(Felt, String)>,
+) -> Option<EntrypointCheckId> {
+    let reader = sn.reader()?;
+    let limiter = sn.limiter.clone();
+    let queries = &mut sn.queries;
+    let id = EntrypointCheckId(queries.next_id());
+
+    queries.spawn(&runtime, limiter, QueryId::Entrypoints(id), async move {
+        let result = find_unknown_entrypoints(reader.provider(), entrypoints).await;
+        QueryResponse::Entrypoints { id, result }
+    });
+    Some(id)
+}
+
+/// System that checks the entrypoints of the `EntrypointRegistry` once connected
+///
+/// It is automatically registered by the `BevyDojoPlugin`.
+pub fn check_registered_entrypoints(
+    runtime: Res<TokioRuntime>,
+    sn: ResMut<StarknetConnection>,
+    mut registry: ResMut<EntrypointRegistry>,
+) {
+    if registry.checked == registry.entrypoints.len() || sn.reader().is_none() {
+        return;
+    }
+    let unchecked = registry.entrypoints[registry.checked..].to_vec();
+    registry.checked = registry.entrypoints.len();
+    query_entrypoints(runtime, sn, unchecked);
+}
+
+/// Event writers used while delivering the results of completed queries
+#[derive(SystemParam)]
+pub struct QueryEvents<'w> {
+    pub(crate) token_metadata: EventWriter<'w, TokenMetadataReceived>,
+    pub(crate) account_deployed: EventWriter<'w, AccountDeployedStatus>,
+    pub(crate) storage_values: EventWriter<'w, StorageValueReceived>,
+    pub(crate) tx_events: EventWriter<'w, TxEvents>,
+    pub(crate) unknown_entrypoints: EventWriter<'w, UnknownEntrypoint>,
+    pub(crate) public_keys: EventWriter<'w, PublicKeyReceived>,
+    pub(crate) latest_blocks: EventWriter<'w, LatestBlockReceived>,
+    pub(crate) timed_out: EventWriter<'w, CallTimedOut>,
+    pub(crate) calls: EventWriter<'w, CallCompleted>,
+    pub(crate) reverted_calls: EventWriter<'w, CallReverted>,
+    pub(crate) deadline: Res<'w, PollDeadline>,
+}
+
+/// System that delivers the results of completed queries as events
+///
+/// It is automatically registered by the `BevyDojoPlugin`.
+pub fn check_sn_queries(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    mut events: QueryEvents,
+) {
+    let queries = &mut sn.queries;
+    let mut responses = std::mem::take(&mut queries.ready);
+
+    let mut processed = 0;
+    let mut i = 0;
+    while i < queries.tasks.len() && events.deadline.allows(processed) {
+        if !queries.tasks[i].is_finished() {
+            i += 1;
+            continue;
+        }
+        processed += 1;
+        let task = queries.tasks.swap_remove(i);
+        if let Ok(response) = runtime.runtime.block_on(task) {
+            responses.push(response);
+        }
+    }
+
+    for response in responses {
+        match response {
+            QueryResponse::TokenMetadata { id, token, result } => match result {
+                Ok(metadata) => {
+                    queries.token_metadata.insert(token, metadata.clone());
+                    events.token_metadata.write(TokenMetadataReceived {
+                        id,
+                        token,
+                        metadata,
+                    });
+                }
+                Err(err) => warn!(
+                    "Token metadata query for {} failed: {}",
+                    fmt_felt(&token),
+                    err
+                ),
+            },
+            QueryResponse::AccountDeployed { id, result } => match result {
+                Ok(deployed) => {
+                    events
+                        .account_deployed
+                        .write(AccountDeployedStatus { id, deployed });
+                }
+                Err(err) => warn!("Account deployment query failed: {}", err),
+            },
+            QueryResponse::Storage {
+                id,
+                contract,
+                key,
+                result,
+            } => match result {
+                Ok(value) => {
+                    events.storage_values.write(StorageValueReceived {
+                        id,
+                        contract,
+                        key,
+                        value,
+                    });
+                }
+                Err(err) => warn!(
+                    "Storage query of {} at {} failed: {}",
+                    fmt_felt(&contract),
+                    fmt_felt(&key),
+                    err
+                ),
+            },
+            QueryResponse::TxEvents { id, hash, result } => match result {
+                Ok(tx_events) => {
+                    events.tx_events.write(TxEvents {
+                        id,
+                        hash,
+                        events: tx_events,
+                    });
+                }
+                Err(err) => warn!(
+                    "Events query of transaction {} failed: {}",
+                    fmt_felt(&hash),
+                    err
+                ),
+            },
+            QueryResponse::PublicKey { id, result } => match result {
+                Ok(key) => {
+                    queries.public_key = Some(key);
+                    events.public_keys.write(PublicKeyReceived { id, key });
+                }
+                Err(err) => warn!("Public key query failed: {}", err),
+            },
+            QueryResponse::LatestBlock { id, result } => match result {
+                Ok(block) => {
+                    events
+                        .latest_blocks
+                        .write(LatestBlockReceived { id, block });
+                }
+                Err(err) => warn!("Latest block query failed: {}", err),
+            },
+            QueryResponse::Call { id, result } => match result {
+                Ok(CallOutput::Returned(result)) => {
+                    events.calls.write(CallCompleted { id, result });
+                }
+                Ok(CallOutput::Reverted(reason)) => {
+                    debug!("Call {} reverted: {}", id.0, reason);
+                    events.reverted_calls.write(CallReverted { id, reason });
+                }
+                Err(err) => warn!("Call {} failed: {}", id.0, err),
+            },
+            QueryResponse::TimedOut { id } => {
+                warn!("Query {:?} timed out", id);
+                events.timed_out.write(CallTimedOut { id });
+            }
+            QueryResponse::Entrypoints { id, result } => match result {
+                Ok(unknown) => {
+                    for (contract, entrypoint) in unknown {
+                        warn!(
+                            "Contract {} has no entrypoint `{}`",
+                            fmt_felt(&contract),
+                            entrypoint
+                        );
+                        events.unknown_entrypoints.write(UnknownEntrypoint {
+                            id,
+                            contract,
+                            entrypoint,
+                        });
+                    }
+                }
+                Err(err) => warn!("Entrypoint check failed: {}", err),
+            },
+        }
+    }
+}
+
+/// Read the `balance_of` of `owner` in an ERC20 token, saturating at `u128::MAX`
+pub(crate) async fn read_token_balance(
+    provider: &AnyProvider,
+    token: Felt,
+    owner: Felt,
+) -> Result<u128, QueryError> {
+    let balance = provider
+        .call(
+            FunctionCall {
+                contract_address: token,
+                entry_point_selector: selector!("balance_of"),
+                calldata: vec![owner],
+            },
+            BlockId::Tag(BlockTag::Latest),
+        )
+        .await?;
+
+    // The balance is a u256 returned as its low and high 128-bit halves
+    match balance.as_slice() {
+        [low, high] => {
+            let low = felt_to_u128(low).ok_or(QueryError::Decode("balance"))?;
+            if *high == Felt::ZERO {
+                Ok(low)
+            } else {
+                Ok(u128::MAX)
+            }
+        }
+        _ => Err(QueryError::Decode("balance")),
+    }
+}
+
+/// Returns the events emitted by the transaction of `receipt`
+fn receipt_events(receipt: TransactionReceipt) -> Vec<StarknetEvent> {
+    match receipt {
+        TransactionReceipt::Invoke(receipt) => receipt.events,
+        TransactionReceipt::L1Handler(receipt) => receipt.events,
+        TransactionReceipt::Declare(receipt) => receipt.events,
+        TransactionReceipt::Deploy(receipt) => receipt.events,
+        TransactionReceipt::DeployAccount(receipt) => receipt.events,
+    }
+}
+
+/// Returns the pairs whose entrypoint isn't an external entrypoint of the contract's class
+async fn find_unknown_entrypoints(
+    provider: &AnyProvider,
+    entrypoints: Vec<(Felt, String)>,
+) -> Result<Vec<(Felt, String)>, QueryError> {
+    let mut selectors = HashMap::<Felt, HashSet<Felt>>::new();
+    let mut unknown = Vec::new();
+    for (contract, entrypoint) in entrypoints {
+        if let Entry::Vacant(slot) = selectors.entry(contract) {
+            let class = provider
+                .get_class_at(BlockId::Tag(BlockTag::Latest), contract)
+                .await?;
+            let external = match class {
+                ContractClass::Sierra(class) => class
+                    .entry_points_by_type
+                    .external
+                    .iter()
+                    .map(|entry_point| entry_point.selector)
+                    .collect(),
+                ContractClass::Legacy(class) => class
+                    .entry_points_by_type
+                    .external
+                    .iter()
+                    .map(|entry_point| entry_point.selector)
+                    .collect(),
+            };
+            slot.insert(external);
+        }
+        let known = get_selector_from_name(&entrypoint)
+            .is_ok_and(|selector| selectors[&contract].contains(&selector));
+        if !known {
+            unknown.push((contract, entrypoint));
+        }
+    }
+    Ok(unknown)
+}
+
+async fn read_token_metadata(
+    provider: &AnyProvider,
+    token: Felt,
+) -> Result<TokenMetadata, QueryError> {
+    let call = |entry_point_selector| {
+        ProviderRequestData::Call(CallRequest {
+            request: FunctionCall {
+                contract_address: token,
+                entry_point_selector,
+                calldata: vec![],
+            },
+            block_id: BlockId::Tag(BlockTag::Latest),
+        })
+    };
+    let responses = provider
+        .batch_requests([
+            call(selector!("decimals")),
+            call(selector!("symbol")),
+            call(selector!("name")),
+        ])
+        .await?;
+    let Ok(
+        [
+            ProviderResponseData::Call(decimals),
+            ProviderResponseData::Call(symbol),
+            ProviderResponseData::Call(name),
+        ],
+    ) = <[_; 3]>::try_from(responses)
+    else {
+        return Err(QueryError::Decode("token metadata"));
+    };
+
+    let decimals = decimals
+        .first()
+        .and_then(felt_to_u128)
+        .and_then(|decimals| u8::try_from(decimals).ok())
+        .ok_or(QueryError::Decode("decimals"))?;
+    Ok(TokenMetadata {
+        decimals,
+        symbol: decode_string(&symbol).ok_or(QueryError::Decode("symbol"))?,
+        name: decode_string(&name).ok_or(QueryError::Decode("name"))?,
+    })
+}
+
+/// Read the public key of the account at `account`, trying both naming conventions
+async fn read_public_key(provider: &AnyProvider, account: Felt) -> Result<Felt, QueryError> {
+    let call = |entry_point_selector| FunctionCall {
+        contract_address: account,
+        entry_point_selector,
+        calldata: vec![],
+    };
+    let key = match provider
+        .call(
+            call(selector!("get_public_key")),
+            BlockId::Tag(BlockTag::Latest),
+        )
+        .await
+    {
+        Ok(key) => key,
+        // The entrypoint doesn't exist, the account may use the camel case name
+        Err(ProviderError::StarknetError(
+            StarknetError::EntrypointNotFound | StarknetError::ContractError(_),
+        )) => {
+            provider
+                .call(
+                    call(selector!("getPublicKey")),
+                    BlockId::Tag(BlockTag::Latest),
+                )
+                .await?
+        }
+        Err(err) => return Err(err.into()),
+    };
+    key.first().copied().ok_or(QueryError::Decode("public key"))
+}
+
+/// Decode a string returned either as a single short string or as a serialized `ByteArray`
+fn decode_string(felts: &[Felt]) -> Option<String> {
+    match felts {
+        [word] => parse_cairo_short_string(word).ok(),
+        [len, rest @ ..] => {
+            let len = usize::try_from(felt_to_u128(len)?).ok()?;
+            let [words @ .., pending_word, pending_len] = rest else {
+                return None;
+            };
+            if words.len() != len {
+                return None;
+            }
+            let pending_len = usize::try_from(felt_to_u128(pending_len)?).ok()?;
+            if pending_len > 30 {
+                return None;
+            }
+
+            let mut bytes = Vec::with_capacity(len * 31 + pending_len);
+            for word in words {
+                bytes.extend_from_slice(&word.to_bytes_be()[1..]);
+            }
+            bytes.extend_from_slice(&pending_word.to_bytes_be()[32 - pending_len..]);
+            String::from_utf8(bytes).ok()
+        }
+        [] => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use serde_json::json;
+    use starknet::core::utils::cairo_short_string_to_felt;
+
+    /// Serialize `value` as a Cairo `ByteArray`
+    fn byte_array(value: &str) -> Vec<Felt> {
+        let chunks = value.as_bytes().chunks(31).collect::<Vec<_>>();
+        let (words, pending) = match chunks.split_last() {
+            Some((last, words)) if last.len() < 31 => (words.to_vec(), *last),
+            _ => (chunks.clone(), &[][..]),
+        };
+        let mut felts = vec![Felt::from(words.len())];
+        felts.extend(words.iter().map(|word| Felt::from_bytes_be_slice(word)));
+        felts.push(Felt::from_bytes_be_slice(pending));
+        felts.push(Felt::from(pending.len()));
+        felts
+    }
+
+    #[test]
+    fn decode_string_reads_short_strings_and_byte_arrays() {
+        let short = cairo_short_string_to_felt("STRK").unwrap();
+        assert_eq!(decode_string(&[short]).as_deref(), Some("STRK"));
+
+        let long = "A token name longer than thirty-one bytes";
+        assert_eq!(decode_string(&byte_array(long)).as_deref(), Some(long));
+        let exact = "thirty-one bytes, not one more!";
+        assert_eq!(exact.len(), 31);
+        assert_eq!(decode_string(&byte_array(exact)).as_deref(), Some(exact));
+        assert_eq!(decode_string(&byte_array("")).as_deref(), Some(""));
+
+        // One full word announced, none given
+        let truncated = [Felt::ONE, Felt::ZERO, Felt::ZERO];
+        assert_eq!(decode_string(&truncated), None);
+        assert_eq!(decode_string(&[]), None);
+    }
+
+    #[test]
+    fn token_metadata_has_decimals_symbol_and_name() {
+        let mock = MockRpc::start();
+        let name = "Starknet Token, with a long name";
+        mock.on_call(move |_, selector, _| {
+            Ok(if selector == selector!("decimals") {
+                vec![Felt::from(18u8)]
+            } else if selector == selector!("symbol") {
+                vec![cairo_short_string_to_felt("STRK").unwrap()]
+            } else {
+                byte_array(name)
+            })
+        });
+        let mut app = connected_app(&mock);
+        collect::<TokenMetadataReceived>(&mut app);
+
+        let token = Felt::from(0x70u8);
+        let id = run(
+            &mut app,
+            move |runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>| {
+                query_token_metadata(runtime, sn, token)
+            },
+        )
+        .unwrap();
+        assert!(update_until(&mut app, |app| {
+            !collected::<TokenMetadataReceived>(app).is_empty()
+        }));
+
+        let received = &collected::<TokenMetadataReceived>(&app)[0];
+        assert_eq!(received.id, id);
+        assert_eq!(received.token, token);
+        let expected = TokenMetadata {
+            decimals: 18,
+            symbol: "STRK".to_string(),
+            name: name.to_string(),
+        };
+        assert_eq!(received.metadata, expected);
+        assert_eq!(connection(&app).token_metadata(&token), Some(&expected));
+        assert_eq!(mock.batches(), vec![3]);
+    }
+
+    #[test]
+    fn account_deployed_checks_the_class_hash_at_the_account() {
+        let mock = MockRpc::start();
+        mock.on("starknet_getClassHashAt", json!("0xc1a55"));
+        let mut app = connected_app(&mock);
+        collect::<AccountDeployedStatus>(&mut app);
+
+        let deployed = run(&mut app, query_account_deployed).unwrap();
+        assert!(update_until(&mut app, |app| {
+            !collected::<AccountDeployedStatus>(app).is_empty()
+        }));
+        let request = &mock.requests("starknet_getClassHashAt")[0];
+        assert_eq!(
+            param(request, 1, "contract_address"),
+            json!(format!("{ACCOUNT_ADDRESS:#x}"))
+        );
+
+        mock.on_error(
+            "starknet_getClassHashAt",
+            RpcError::new(CONTRACT_NOT_FOUND, "Contract not found"),
+        );
+        let not_deployed = run(&mut app, query_account_deployed).unwrap();
+        assert!(update_until(&mut app, |app| {
+            collected::<AccountDeployedStatus>(app).len() == 2
+        }));
+
+        let statuses = collected::<AccountDeployedStatus>(&app);
+        assert_eq!(statuses[0].id, deployed);
+        assert!(statuses[0].deployed);
+        assert_eq!(statuses[1].id, not_deployed);
+        assert!(!statuses[1].deployed);
+    }
+
+    #[test]
+    fn late_registrations_are_checked_too() {
+        let mock = MockRpc::start();
+        mock.on(
+            "starknet_getClassAt",
+            json!({
+                "sierra_program": [],
+                "contract_class_version": "0.1.0",
+                "entry_points_by_type": {
+                    "CONSTRUCTOR": [],
+                    "EXTERNAL": [{ "selector": format!("{:#x}", selector!("increment")), "function_idx": 0 }],
+                    "L1_HANDLER": []
+                },
+                "abi": "[]"
+            }),
+        );
+        let game = Felt::from(0x42u8);
+        let mut app = test_app();
+        collect::<UnknownEntrypoint>(&mut app);
+        let mut registry = app.world_mut().resource_mut::<EntrypointRegistry>();
+        registry.register(game, "increment");
+        registry.register(game, "incremnt");
+        app.insert_resource(mock.config());
+        connect(&mut app);
+
+        assert!(update_until(&mut app, |app| {
+            !collected::<UnknownEntrypoint>(app).is_empty()
+        }));
+        app.world_mut()
+            .resource_mut::<EntrypointRegistry>()
+            .register(game, "decrement");
+        assert!(update_until(&mut app, |app| {
+            collected::<UnknownEntrypoint>(app).len() == 2
+        }));
+        for _ in 0..10 {
+            app.update();
+        }
+
+        let unknown = collected::<UnknownEntrypoint>(&app);
+        let names = unknown
+            .iter()
+            .map(|unknown| unknown.entrypoint.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["incremnt", "decrement"]);
+        assert!(unknown.iter().all(|unknown| unknown.contract == game));
+        assert_eq!(mock.count("starknet_getClassAt"), 2);
+    }
+
+    #[test]
+    fn tx_events_are_read_from_the_receipt() {
+        let mock = MockRpc::start();
+        let events = [
+            json!({ "from_address": "0x42", "keys": ["0x1"], "data": ["0x7"] }),
+            json!({ "from_address": "0x43", "keys": ["0x2", "0x3"], "data": [] }),
+        ];
+        mock.on_fn("starknet_getTransactionReceipt", move |params| {
+            let hash = param(params, 0, "transaction_hash");
+            Ok(receipt(&hash, 1000, &events))
+        });
+        let mut app = connected_app(&mock);
+        collect::<TxEvents>(&mut app);
+
+        let hash = Felt::from(0xabcu64);
+        let id = run(
+            &mut app,
+            move |runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>| {
+                query_tx_events(runtime, sn, hash)
+            },
+        )
+        .unwrap();
+        assert!(update_until(&mut app, |app| {
+            !collected::<TxEvents>(app).is_empty()
+        }));
+
+        let received = &collected::<TxEvents>(&app)[0];
+        assert_eq!(received.id, id);
+        assert_eq!(received.hash, hash);
+        assert_eq!(received.events.len(), 2);
+        assert_eq!(received.events[0].from_address, Felt::from(0x42u8));
+        assert_eq!(received.events[0].keys, [Felt::ONE]);
+        assert_eq!(received.events[0].data, [Felt::from(7u8)]);
+        assert_eq!(received.events[1].from_address, Felt::from(0x43u8));
+        assert_eq!(received.events[1].keys, [Felt::TWO, Felt::THREE]);
+        assert!(received.events[1].data.is_empty());
+    }
+
+    /// Start `query_call` on the counter contract of `call`
+    fn call_counter(app: &mut App, returns_result: bool) -> CallId {
+        run(
+            app,
+            move |runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>| {
+                let call = FunctionCall {
+                    contract_address: Felt::from(0x42u8),
+                    entry_point_selector: selector!("get"),
+                    calldata: vec![],
+                };
+                query_call(runtime, sn, call, returns_result)
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn slow_call_times_out() {
+        let mock = MockRpc::start();
+        mock.on_call(|_, _, _| Ok(vec![Felt::from(7u8)]));
+        mock.delay("starknet_call", Duration::from_millis(500));
+        let mut app = connected_app(&mock);
+        collect::<CallTimedOut>(&mut app);
+        collect::<CallCompleted>(&mut app);
+        with_connection(&mut app, |_, sn| {
+            sn.set_call_timeout(Duration::from_millis(50))
+        });
+
+        let id = call_counter(&mut app, false);
+        assert!(update_until(&mut app, |app| {
+            !collected::<CallTimedOut>(app).is_empty()
+        }));
+        assert_eq!(collected::<CallTimedOut>(&app)[0].id, QueryId::Call(id));
+
+        // The provider answers eventually, but the result isn't reported
+        std::thread::sleep(Duration::from_millis(600));
+        app.update();
+        assert!(collected::<CallCompleted>(&app).is_empty());
+        assert_eq!(collected::<CallTimedOut>(&app).len(), 1);
+    }
+
+    #[test]
+    fn cairo_result_is_split_by_variant() {
+        let ok = [Felt::ZERO, Felt::from(7u8)];
+        let err = [Felt::ONE, Felt::from(8u8)];
+        assert_eq!(decode_cairo_result(&ok), Some(Ok(&ok[1..])));
+        assert_eq!(decode_cairo_result(&err), Some(Err(&err[1..])));
+        assert_eq!(decode_cairo_result(&[Felt::TWO]), None);
+        assert_eq!(decode_cairo_result(&[]), None);
+    }
+
+    #[test]
+    fn reverts_and_err_results_are_reported() {
+        let mock = MockRpc::start();
+        let not_owner = cairo_short_string_to_felt("NOT_OWNER").unwrap();
+        mock.on_call(move |_, _, _| Ok(vec![Felt::ONE, not_owner]));
+        let mut app = connected_app(&mock);
+        collect::<CallReverted>(&mut app);
+        collect::<CallCompleted>(&mut app);
+
+        let err_result = call_counter(&mut app, true);
+        assert!(update_until(&mut app, |app| {
+            collected::<CallReverted>(app).len() == 1
+        }));
+
+        mock.on_error(
+            "starknet_call",
+            RpcError::new(CONTRACT_ERROR, "Contract error")
+                .with_data(json!({ "revert_error": "Not allowed" })),
+        );
+        let reverted = call_counter(&mut app, false);
+        assert!(update_until(&mut app, |app| {
+            collected::<CallReverted>(app).len() == 2
+        }));
+
+        mock.on_error(
+            "starknet_call",
+            RpcError::new(CONTRACT_ERROR, "Contract error").with_data(json!({
+                "revert_error": {
+                    "contract_address": "0x42",
+                    "class_hash": "0xc1a55",
+                    "selector": "0x5e1",
+                    "error": "Not allowed either",
+                }
+            })),
+        );
+        let nested = call_counter(&mut app, true);
+        assert!(update_until(&mut app, |app| {
+            collected::<CallReverted>(app).len() == 3
+        }));
+
+        let reverts = collected::<CallReverted>(&app)
+            .into_iter()
+            .map(|event| (event.id, event.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reverts,
+            [
+                (err_result, "NOT_OWNER".to_string()),
+                (reverted, "Not allowed".to_string()),
+                (nested, "Not allowed either".to_string()),
+            ]
+        );
+        assert!(collected::<CallCompleted>(&app).is_empty());
+    }
+
+    fn public_key_query(app: &mut App) {
+        collect::<PublicKeyReceived>(app);
+        run(
+            app,
+            |runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>| {
+                query_public_key(runtime, sn)
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn public_key_is_read_with_get_public_key() {
+        let mock = MockRpc::start();
+        mock.on_call(|_, selector, _| {
+            assert_eq!(selector, selector!("get_public_key"));
+            Ok(vec![Felt::from(0xabcu64)])
+        });
+        let mut app = connected_app(&mock);
+        public_key_query(&mut app);
+
+        assert!(update_until(&mut app, |app| {
+            !collected::<PublicKeyReceived>(app).is_empty()
+        }));
+        assert_eq!(
+            collected::<PublicKeyReceived>(&app)[0].key,
+            Felt::from(0xabcu64)
+        );
+        assert_eq!(connection(&app).public_key(), Some(Felt::from(0xabcu64)));
+        assert_eq!(mock.count("starknet_call"), 1);
+    }
+
+    #[test]
+    fn public_key_falls_back_to_get_public_key_camel_case() {
+        let mock = MockRpc::start();
+        mock.on_call(|_, selector, _| {
+            if selector == selector!("getPublicKey") {
+                Ok(vec![Felt::from(0xdefu64)])
+            } else {
+                Err(RpcError::new(CONTRACT_ERROR, "Contract error")
+                    .with_data(json!({ "revert_error": "ENTRYPOINT_NOT_FOUND" })))
+            }
+        });
+        let mut app = connected_app(&mock);
+        public_key_query(&mut app);
+
+        assert!(update_until(&mut app, |app| {
+            !collected::<PublicKeyReceived>(app).is_empty()
+        }));
+        assert_eq!(
+            collected::<PublicKeyReceived>(&app)[0].key,
+            Felt::from(0xdefu64)
+        );
+        assert_eq!(mock.count("starknet_call"), 2);
+    }
+
+    #[test]
+    fn public_key_does_not_fall_back_on_transport_errors() {
+        let mock = MockRpc::start();
+        mock.on_call(|_, _, _| Err(RpcError::new(-32603, "Internal error")));
+        let mut app = connected_app(&mock);
+        public_key_query(&mut app);
+
+        assert!(update_until(&mut app, |app| {
+            connection(app).queries.task_count() == 0
+        }));
+        assert!(collected::<PublicKeyReceived>(&app).is_empty());
+        assert_eq!(mock.count("starknet_call"), 1);
+    }
+}
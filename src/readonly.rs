@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use starknet::{
+    accounts::ConnectedAccount,
+    core::types::Felt,
+    providers::{AnyProvider, Provider},
+};
+
+use crate::signature::DojoAccount;
+use crate::starknet::{
+    ConnectError, ConnectOutcome, DefaultStarknetConfig, StarknetConnection, http_provider,
+    spawn_connect,
+};
+use crate::tokio::TokioRuntime;
+
+/// Provider of a read-only connection, without an account
+#[derive(Debug)]
+pub(crate) struct ReadOnlyProvider {
+    pub(crate) provider: AnyProvider,
+    pub(crate) chain_id: Felt,
+}
+
+/// Handle used by read operations, available on full and read-only connections
+#[derive(Clone)]
+pub(crate) enum Reader {
+    Account(Arc<DojoAccount>),
+    ReadOnly(Arc<ReadOnlyProvider>),
+}
+
+impl Reader {
+    pub(crate) fn provider(&self) -> &AnyProvider {
+        match self {
+            Reader::Account(account) => account.provider(),
+            Reader::ReadOnly(read_only) => &read_only.provider,
+        }
+    }
+}
+
+impl StarknetConnection {
+    /// Returns true if connected read-only, without an account
+    ///
+    /// `is_connected` is false on read-only connections, since transactions
+    /// can't be sent, while queries work as on a full connection.
+    pub fn is_read_only(&self) -> bool {
+        self.account().is_none() && self.read_only.is_some()
+    }
+
+    /// Start connecting to Starknet read-only, unless already connected or connecting
+    ///
+    /// This is the method form of `connect_readonly`.
+    pub fn connect_readonly(
+        &mut self,
+        runtime: &TokioRuntime,
+        config: &DefaultStarknetConfig,
+    ) -> ConnectOutcome {
+        if self.is_connected() || self.is_read_only() {
+            return ConnectOutcome::AlreadyConnected;
+        }
+        if self.is_connecting() {
+            return ConnectOutcome::AlreadyConnecting;
+        }
+        self.reconnect.start(config.clone(), true);
+        self.connecting_task = Some(spawn_connect(
+            runtime,
+            config.clone(),
+            Duration::ZERO,
+            true,
+            self.limiter.clone(),
+        ));
+        info!("Connecting to Starknet read-only...");
+        ConnectOutcome::Started
+    }
+
+    /// Returns the handle used by read operations, if connected or read-only
+    pub(crate) fn reader(&self) -> Option<Reader> {
+        match (self.account(), &self.read_only) {
+            (Some(account), _) => Some(Reader::Account(account.clone())),
+            (None, Some(read_only)) => Some(Reader::ReadOnly(read_only.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Connect to Starknet without an account, for spectators and leaderboards
+///
+/// Only the RPC provider is created, so `config.account_address` and
+/// `config.private_key` are ignored and may be left empty. Once connected,
+/// queries such as `query_storage` or `query_tx_events` work as on a full
+/// connection, while `execute_transaction` returns `SubmitOutcome::ReadOnly`.
+/// A full connection can still be started later with `init_starknet_connection`.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `config` - The Starknet configuration, of which only the RPC URL is used
+/// * `sn` - The Starknet connection resource
+///
+/// # Returns
+///
+/// The same outcomes as `init_starknet_connection`
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(mut commands: Commands) {
+///     commands.insert_resource(DefaultStarknetConfig {
+///         rpc_url: "https://starknet-sepolia.public.blastapi.io".to_string(),
+///         account_address: String::new(),
+///         private_key: String::new(),
+///         proxy: None,
+///     });
+/// }
+///
+/// fn spectate(
+///     runtime: Res<TokioRuntime>,
+///     config: Res<DefaultStarknetConfig>,
+///     sn: ResMut<StarknetConnection>,
+/// ) {
+///     connect_readonly(runtime, config, sn);
+/// }
+/// ```
+pub fn connect_readonly(
+    runtime: Res<TokioRuntime>,
+    config: Res<DefaultStarknetConfig>,
+    mut sn: ResMut<StarknetConnection>,
+) -> ConnectOutcome {
+    sn.connect_readonly(&runtime, &config)
+}
+
+/// Create the provider of a read-only connection
+pub(crate) async fn try_connect_readonly(
+    config: DefaultStarknetConfig,
+) -> Result<Arc<ReadOnlyProvider>, ConnectError> {
+    let provider = http_provider(&config)?;
+    let chain_id = provider.chain_id().await.map_err(ConnectError::Provider)?;
+    Ok(Arc::new(ReadOnlyProvider { provider, chain_id }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::query::{StorageValueReceived, query_storage};
+    use crate::starknet::SubmitOutcome;
+    use serde_json::json;
+
+    #[test]
+    fn reads_work_and_writes_are_rejected() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on("starknet_getStorageAt", json!("0x2a"));
+        let mut app = test_app();
+        collect::<StorageValueReceived>(&mut app);
+        app.insert_resource(DefaultStarknetConfig {
+            account_address: String::new(),
+            private_key: String::new(),
+            ..mock.config()
+        });
+
+        assert_eq!(run(&mut app, connect_readonly), ConnectOutcome::Started);
+        assert!(update_until(&mut app, |app| connection(app).is_read_only()));
+        assert!(!connection(&app).is_connected());
+        assert_eq!(
+            connection(&app).chain_id(),
+            Some(Felt::from_hex_unchecked("0x534e5f5345504f4c4941"))
+        );
+
+        let (contract, key) = (Felt::from(0x42u8), Felt::from(0x7u8));
+        let id = run(
+            &mut app,
+            move |runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>| {
+                query_storage(runtime, sn, contract, key)
+            },
+        )
+        .unwrap();
+        assert!(update_until(&mut app, |app| {
+            !collected::<StorageValueReceived>(app).is_empty()
+        }));
+        let received = &collected::<StorageValueReceived>(&app)[0];
+        assert_eq!(received.id, id);
+        assert_eq!(received.value, Felt::from(0x2au8));
+
+        let outcome = with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(1)]));
+        assert_eq!(outcome, SubmitOutcome::ReadOnly);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(mock.count("starknet_getNonce"), 0);
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 0);
+    }
+}
@@ -0,0 +1,393 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::block_time::NewBlock;
+use crate::sink::{CustomTransactionSink, TransactionEvents, TransactionSink};
+use crate::starknet::{
+    Connected, DefaultStarknetConfig, PollDeadline, StarknetConnection, spawn_connect,
+};
+use crate::tokio::TokioRuntime;
+
+/// How failed connection attempts are retried
+///
+/// After a failed attempt, the connection is retried after `initial_delay`,
+/// doubling the delay after every further failure up to `max_delay`, until
+/// `max_attempts` retries have failed. The delays are randomized according to
+/// `jitter`. Progress is reported with the `ReconnectAttempt`,
+/// `ReconnectSucceeded` and `ReconnectExhausted` events.
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(mut sn: ResMut<StarknetConnection>) {
+///     sn.set_reconnect_policy(ReconnectPolicy {
+///         max_attempts: 10,
+///         jitter: Jitter::Full,
+///         ..Default::default()
+///     });
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: Jitter,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: Jitter::None,
+        }
+    }
+}
+
+/// How retry delays are randomized
+///
+/// When many clients lose their connection to the same provider at once,
+/// identical backoff makes them all retry at the same moments. Jitter spreads
+/// the retries out to smooth the load on the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Jitter {
+    /// Use the exponential delays as-is
+    #[default]
+    None,
+    /// Pick each delay at random between zero and the exponential delay
+    Full,
+    /// Pick each delay at random between `initial_delay` and three times the
+    /// previous delay, capped at `max_delay`
+    Decorrelated,
+}
+
+impl ReconnectPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the delay before the retry number `attempt`, starting at 1, without jitter
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+
+    /// Returns the delay before the retry number `attempt`, with jitter applied
+    ///
+    /// `previous` is the delay before the previous retry, used by
+    /// `Jitter::Decorrelated`.
+    pub fn jittered_delay(&self, attempt: u32, previous: Duration) -> Duration {
+        match self.jitter {
+            Jitter::None => self.delay(attempt),
+            Jitter::Full => random_between(Duration::ZERO, self.delay(attempt)),
+            Jitter::Decorrelated => {
+                let high = previous.saturating_mul(3).max(self.initial_delay);
+                random_between(self.initial_delay, high).min(self.max_delay)
+            }
+        }
+    }
+}
+
+/// Returns a random duration between `low` and `high`, inclusive
+pub(crate) fn random_between(low: Duration, high: Duration) -> Duration {
+    let range = high.saturating_sub(low).as_millis() as u64;
+    // Each `RandomState` is seeded differently, which is random enough for jitter
+    let random = RandomState::new().build_hasher().finish();
+    low + Duration::from_millis(random % range.saturating_add(1))
+}
+
+/// Event emitted before retrying a failed connection attempt
+#[derive(Event, Debug, Clone)]
+pub struct ReconnectAttempt {
+    /// Number of this retry, starting at 1
+    pub attempt: u32,
+    /// Maximum number of retries, from the `ReconnectPolicy`
+    pub max: u32,
+    /// Delay before the retry
+    pub next_delay: Duration,
+}
+
+/// Event emitted when the connection succeeds after at least one retry
+#[derive(Event, Debug, Clone)]
+pub struct ReconnectSucceeded {
+    /// Number of retries it took
+    pub attempts: u32,
+}
+
+/// Event emitted when the last allowed connection attempt fails
+#[derive(Event, Debug, Clone)]
+pub struct ReconnectExhausted {
+    /// Number of retries made
+    pub attempts: u32,
+    /// Error of the last attempt
+    pub error: String,
+}
+
+/// Event writers and commands used while polling the tasks of a `StarknetConnection`
+#[derive(SystemParam)]
+pub struct TaskEvents<'w, 's> {
+    pub(crate) commands: Commands<'w, 's>,
+    pub(crate) transactions: TransactionEvents<'w>,
+    pub(crate) custom_sink: Option<ResMut<'w, CustomTransactionSink>>,
+    pub(crate) reconnect_attempt: EventWriter<'w, ReconnectAttempt>,
+    pub(crate) reconnect_succeeded: EventWriter<'w, ReconnectSucceeded>,
+    pub(crate) reconnect_exhausted: EventWriter<'w, ReconnectExhausted>,
+    pub(crate) new_block: EventWriter<'w, NewBlock>,
+    pub(crate) deadline: Res<'w, PollDeadline>,
+}
+
+impl TaskEvents<'_, '_> {
+    /// Returns the `CustomTransactionSink` if inserted, the transaction events otherwise
+    pub(crate) fn sink(&mut self) -> &mut dyn TransactionSink {
+        match &mut self.custom_sink {
+            Some(sink) => sink.0.as_mut(),
+            None => &mut self.transactions,
+        }
+    }
+}
+
+/// Retry state of the connection of a `StarknetConnection`
+#[derive(Default)]
+pub(crate) struct ReconnectState {
+    policy: ReconnectPolicy,
+    /// Number of retries made for the current connection
+    attempt: u32,
+    /// Delay before the last retry
+    last_delay: Duration,
+    /// Configuration of the current connection, kept to retry it
+    config: Option<DefaultStarknetConfig>,
+    /// Whether the current connection is read-only
+    read_only: bool,
+}
+
+impl ReconnectState {
+    /// Start tracking a new connection made with `config`
+    pub(crate) fn start(&mut self, config: DefaultStarknetConfig, read_only: bool) {
+        self.attempt = 0;
+        self.last_delay = Duration::ZERO;
+        self.config = Some(config);
+        self.read_only = read_only;
+    }
+
+    /// Returns the proxy of the current connection, if any
+    pub(crate) fn proxy(&self) -> Option<&str> {
+        self.config.as_ref()?.proxy.as_deref()
+    }
+
+    /// Stop tracking the current connection, so it isn't retried
+    pub(crate) fn stop(&mut self) {
+        self.attempt = 0;
+        self.config = None;
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the policy used to retry failed connection attempts
+    pub fn reconnect_policy(&self) -> ReconnectPolicy {
+        self.reconnect.policy
+    }
+
+    /// Sets the policy used to retry failed connection attempts
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect.policy = policy;
+    }
+
+    /// Check the connection task, retrying it according to the reconnect policy
+    pub(crate) fn poll_connection(&mut self, runtime: &TokioRuntime, events: &mut TaskEvents) {
+        let Some(task) = self.connecting_task.take_if(|task| task.is_finished()) else {
+            return;
+        };
+        let error = match runtime.runtime.block_on(task) {
+            Ok(Ok(connected)) => {
+                match connected {
+                    Connected::Account(account, owner_key) => {
+                        info!("Connected to Starknet!");
+                        self.set_account(account, owner_key.clone());
+                        self.sessions.set_owner_key(owner_key);
+                        self.redetect_fee_token(runtime);
+                    }
+                    Connected::ReadOnly(read_only) => {
+                        info!("Connected to Starknet read-only!");
+                        self.read_only = Some(read_only);
+                    }
+                }
+                self.health.record_success(None);
+                if self.reconnect.attempt > 0 {
+                    events.reconnect_succeeded.write(ReconnectSucceeded {
+                        attempts: self.reconnect.attempt,
+                    });
+                }
+                return;
+            }
+            Ok(Err(err)) => err.to_string(),
+            Err(err) => err.to_string(),
+        };
+
+        warn!("Failed to connect to Starknet: {}", error);
+        self.health.record_error(error.clone());
+        let state = &mut self.reconnect;
+        match state.config.clone() {
+            Some(config) if state.attempt < state.policy.max_attempts => {
+                state.attempt += 1;
+                let next_delay = state.policy.jittered_delay(state.attempt, state.last_delay);
+                state.last_delay = next_delay;
+                info!(
+                    "Reconnecting to Starknet in {:?} (attempt {}/{})",
+                    next_delay, state.attempt, state.policy.max_attempts
+                );
+                events.reconnect_attempt.write(ReconnectAttempt {
+                    attempt: state.attempt,
+                    max: state.policy.max_attempts,
+                    next_delay,
+                });
+                self.connecting_task = Some(spawn_connect(
+                    runtime,
+                    config,
+                    next_delay,
+                    state.read_only,
+                    self.limiter.clone(),
+                ));
+            }
+            _ => {
+                events.reconnect_exhausted.write(ReconnectExhausted {
+                    attempts: state.attempt,
+                    error,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Reconnection events in the order they were emitted
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn log_events(
+        mut attempts: EventReader<ReconnectAttempt>,
+        mut succeeded: EventReader<ReconnectSucceeded>,
+        mut exhausted: EventReader<ReconnectExhausted>,
+        mut log: ResMut<Log>,
+    ) {
+        for attempt in attempts.read() {
+            log.0
+                .push(format!("attempt {}/{}", attempt.attempt, attempt.max));
+        }
+        for succeeded in succeeded.read() {
+            log.0
+                .push(format!("succeeded after {}", succeeded.attempts));
+        }
+        for exhausted in exhausted.read() {
+            log.0
+                .push(format!("exhausted after {}", exhausted.attempts));
+        }
+    }
+
+    #[test]
+    fn connection_is_retried_until_it_succeeds() {
+        let mock = MockRpc::start();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain_id_calls = calls.clone();
+        mock.on_fn("starknet_chainId", move |_| {
+            match chain_id_calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(RpcError::new(-32603, "Internal error")),
+                _ => Ok(json!("0x534e5f5345504f4c4941")),
+            }
+        });
+        let mut app = test_app();
+        app.init_resource::<Log>().add_systems(Last, log_events);
+        let config = mock.config();
+        with_connection(&mut app, |runtime, sn| {
+            sn.set_reconnect_policy(ReconnectPolicy {
+                max_attempts: 3,
+                initial_delay: Duration::from_millis(10),
+                ..Default::default()
+            });
+            sn.connect(runtime, &config);
+        });
+
+        assert!(update_until(&mut app, |app| connection(app).is_connected()));
+        app.update();
+        assert_eq!(
+            app.world().resource::<Log>().0,
+            ["attempt 1/3", "attempt 2/3", "succeeded after 2"]
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    /// Times at which each of `clients` retries under `policy`, on a simulated clock
+    fn retry_times(policy: ReconnectPolicy, clients: usize) -> Vec<Vec<Duration>> {
+        (0..clients)
+            .map(|_| {
+                let mut now = Duration::ZERO;
+                let mut previous = policy.initial_delay;
+                (1..=policy.max_attempts)
+                    .map(|attempt| {
+                        previous = policy.jittered_delay(attempt, previous);
+                        now += previous;
+                        now
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn jitter_spreads_retries_across_clients() {
+        let policy = ReconnectPolicy {
+            max_attempts: 4,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(8),
+            jitter: Jitter::None,
+        };
+        let times = retry_times(policy, 50);
+        assert!(times.iter().all(|client| *client == times[0]));
+        assert_eq!(times[0], [1, 3, 7, 15].map(Duration::from_secs).to_vec());
+
+        for jitter in [Jitter::Full, Jitter::Decorrelated] {
+            let policy = ReconnectPolicy { jitter, ..policy };
+            let times = retry_times(policy, 50);
+            for attempt in 0..4 {
+                let first = times[0][attempt];
+                assert!(
+                    times.iter().any(|client| client[attempt] != first),
+                    "{jitter:?} retry {attempt} is at the same time for every client"
+                );
+            }
+            for client in &times {
+                let delays = client
+                    .iter()
+                    .scan(Duration::ZERO, |last, time| {
+                        let delay = *time - *last;
+                        *last = *time;
+                        Some(delay)
+                    })
+                    .collect::<Vec<_>>();
+                for (attempt, delay) in (1..).zip(delays) {
+                    match jitter {
+                        Jitter::Full => assert!(delay <= policy.delay(attempt)),
+                        _ => assert!(delay >= policy.initial_delay && delay <= policy.max_delay),
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use starknet::core::types::{Call, Felt, ResourceBoundsMapping};
+
+use crate::starknet::{StarknetConnection, TxId};
+
+/// Default number of transaction records kept by a `StarknetConnection`
+pub const DEFAULT_RECENT_TXS_CAPACITY: usize = 20;
+
+/// Number of transaction records kept, defaulting to `DEFAULT_RECENT_TXS_CAPACITY`
+pub(crate) struct RecentTxsCapacity(pub(crate) usize);
+
+impl Default for RecentTxsCapacity {
+    fn default() -> Self {
+        Self(DEFAULT_RECENT_TXS_CAPACITY)
+    }
+}
+
+/// Lifecycle status of a transaction submitted through `execute_transaction`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TxStatus {
+    /// Queued, not yet accepted by the provider
+    Pending,
+    /// Accepted by the provider, waiting for confirmation
+    Sent,
+    /// Included in a block and executed successfully
+    Confirmed,
+    /// Included in a block but reverted
+    Reverted { reason: String },
+    /// Could not be sent
+    Failed { error: String },
+    /// Dropped before being included in a block
+    Dropped,
+    /// Cancelled with `reject_fee` before being sent
+    Rejected,
+}
+
+impl TxStatus {
+    /// Returns true if the transaction won't change status anymore
+    pub fn is_final(&self) -> bool {
+        !matches!(self, TxStatus::Pending | TxStatus::Sent)
+    }
+}
+
+/// Record of a transaction submitted through `execute_transaction`
+///
+/// With the `serde` feature enabled, records implement `Serialize` and
+/// `Deserialize` so games can persist their transaction log, e.g. in save
+/// games. Felts are serialized as `0x`-prefixed hex strings and timestamps as
+/// seconds since the Unix epoch.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TxRecord {
+    pub tx_id: TxId,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::option"))]
+    pub hash: Option<Felt>,
+    pub status: TxStatus,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::calls"))]
+    pub calls: Vec<Call>,
+    /// When the transaction was submitted
+    pub submitted_at: u64,
+    /// When the transaction reached a final status
+    pub completed_at: Option<u64>,
+    /// The `actual_fee` paid, once known
+    pub fee: Option<u128>,
+    /// The nonce the transaction was sent with, once sent
+    #[cfg_attr(feature = "serde", serde(default, with = "serde_hex::option"))]
+    pub nonce: Option<Felt>,
+    /// The resource bounds the transaction was sent with, once sent
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bounds: Option<ResourceBoundsMapping>,
+}
+
+/// Snapshot of a transaction that hasn't reached a final status yet
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PendingTxInfo {
+    pub tx_id: TxId,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::option"))]
+    pub hash: Option<Felt>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::calls"))]
+    pub calls: Vec<Call>,
+    /// When the transaction was submitted
+    pub submitted_at: u64,
+    /// The nonce the transaction was sent with, once sent
+    #[cfg_attr(feature = "serde", serde(default, with = "serde_hex::option"))]
+    pub nonce: Option<Felt>,
+    /// The resource bounds the transaction was sent with, once sent
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bounds: Option<ResourceBoundsMapping>,
+}
+
+impl TxRecord {
+    pub(crate) fn new(tx_id: TxId, calls: Vec<Call>) -> Self {
+        Self {
+            tx_id,
+            hash: None,
+            status: TxStatus::Pending,
+            calls,
+            submitted_at: unix_now(),
+            completed_at: None,
+            fee: None,
+            nonce: None,
+            bounds: None,
+        }
+    }
+
+    /// Move the record to `status`, stamping the completion time if final
+    pub(crate) fn set_status(&mut self, status: TxStatus) {
+        if status.is_final() {
+            self.completed_at = Some(unix_now());
+        }
+        self.status = status;
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the records kept by this connection, oldest first
+    ///
+    /// These are the same records as `recent_txs`: once more than
+    /// `recent_txs_capacity` are kept, the oldest finished ones are forgotten.
+    /// Games keeping a full transaction log should persist the finished
+    /// records with `drain_history` before they're forgotten.
+    pub fn export_history(&self) -> Vec<TxRecord> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// Remove and return the records of the transactions that reached a final status
+    ///
+    /// Records of pending transactions are kept, since their status still
+    /// changes. Drained records are no longer returned by `recent_txs` and
+    /// failed transactions among them can't be resubmitted with `resubmit_with`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn save_log(mut sn: ResMut<StarknetConnection>, mut log: ResMut<TxLog>) {
+    ///     log.records.extend(sn.drain_history());
+    /// }
+    /// ```
+    pub fn drain_history(&mut self) -> Vec<TxRecord> {
+        let (done, pending): (VecDeque<_>, _) = std::mem::take(&mut self.history)
+            .into_iter()
+            .partition(|record| record.status.is_final());
+        self.history = pending;
+        done.into()
+    }
+
+    /// Returns the most recently submitted transactions, oldest first
+    ///
+    /// The records are kept in a ring buffer of `recent_txs_capacity` records:
+    /// once it is full, the oldest record that reached a final status is
+    /// dropped for each new one. Records of pending transactions are never
+    /// dropped, so the buffer only grows past its capacity while more
+    /// transactions than that are pending. Statuses are kept up to date as
+    /// transactions confirm, which makes this a ready-made source for an
+    /// activity feed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn activity_feed(sn: Res<StarknetConnection>) {
+    ///     for record in sn.recent_txs().iter().rev() {
+    ///         println!("{:?}: {:?}", record.hash, record.status);
+    ///     }
+    /// }
+    /// ```
+    pub fn recent_txs(&self) -> &VecDeque<TxRecord> {
+        &self.history
+    }
+
+    /// Returns the number of records kept for `recent_txs`
+    pub fn recent_txs_capacity(&self) -> usize {
+        self.recent_txs_capacity.0
+    }
+
+    /// Sets the number of records kept for `recent_txs`
+    ///
+    /// Defaults to `DEFAULT_RECENT_TXS_CAPACITY`. Lowering it drops the oldest
+    /// finished records right away.
+    pub fn set_recent_txs_capacity(&mut self, capacity: usize) {
+        self.recent_txs_capacity = RecentTxsCapacity(capacity);
+        self.trim_history();
+    }
+
+    /// Returns a snapshot of the transactions that haven't reached a final status
+    pub fn pending_txs(&self) -> Vec<PendingTxInfo> {
+        self.history
+            .iter()
+            .filter(|record| !record.status.is_final())
+            .map(|record| PendingTxInfo {
+                tx_id: record.tx_id,
+                hash: record.hash,
+                calls: record.calls.clone(),
+                submitted_at: record.submitted_at,
+                nonce: record.nonce,
+                bounds: record.bounds.clone(),
+            })
+            .collect()
+    }
+
+    /// Start recording a transaction, dropping the oldest finished records past the capacity
+    ///
+    /// Ids are allocated in increasing order, so records stay sorted by id.
+    pub(crate) fn push_record(&mut self, record: TxRecord) {
+        self.history.push_back(record);
+        self.trim_history();
+    }
+
+    /// Drop the oldest finished records until at most `recent_txs_capacity` are kept
+    pub(crate) fn trim_history(&mut self) {
+        while self.history.len() > self.recent_txs_capacity.0 {
+            let Some(oldest) = self
+                .history
+                .iter()
+                .position(|record| record.status.is_final())
+            else {
+                break;
+            };
+            self.history.remove(oldest);
+        }
+    }
+
+    /// Returns the record of `tx_id`, if still kept
+    pub(crate) fn record(&self, tx_id: TxId) -> Option<&TxRecord> {
+        let index = self
+            .history
+            .binary_search_by_key(&tx_id, |record| record.tx_id)
+            .ok()?;
+        self.history.get(index)
+    }
+
+    pub(crate) fn record_mut(&mut self, tx_id: TxId) -> Option<&mut TxRecord> {
+        let index = self
+            .history
+            .binary_search_by_key(&tx_id, |record| record.tx_id)
+            .ok()?;
+        self.history.get_mut(index)
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Serde helpers encoding felts as hex strings
+#[cfg(feature = "serde")]
+pub(crate) mod serde_hex {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use starknet::core::types::{Call, Felt};
+
+    fn to_hex(felt: &Felt) -> String {
+        format!("{:#x}", felt)
+    }
+
+    fn from_hex<E: serde::de::Error>(hex: &str) -> Result<Felt, E> {
+        crate::calldata::parse_felt(hex).ok_or_else(|| E::custom(format!("invalid felt `{hex}`")))
+    }
+
+    pub(crate) mod option {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            felt: &Option<Felt>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            felt.as_ref().map(to_hex).serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Felt>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|hex| from_hex(&hex))
+                .transpose()
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct CallRecord {
+        to: String,
+        selector: String,
+        calldata: Vec<String>,
+    }
+
+    pub(crate) mod calls {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            calls: &[Call],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(calls.iter().map(|call| CallRecord {
+                to: to_hex(&call.to),
+                selector: to_hex(&call.selector),
+                calldata: call.calldata.iter().map(to_hex).collect(),
+            }))
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<Call>, D::Error> {
+            Vec::<CallRecord>::deserialize(deserializer)?
+                .iter()
+                .map(call_from_record)
+                .collect()
+        }
+
+        fn call_from_record<E: serde::de::Error>(call: &CallRecord) -> Result<Call, E> {
+            Ok(Call {
+                to: from_hex(&call.to)?,
+                selector: from_hex(&call.selector)?,
+                calldata: call
+                    .calldata
+                    .iter()
+                    .map(|hex| from_hex(hex))
+                    .collect::<Result<_, E>>()?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    #[test]
+    fn drain_history_keeps_pending_records() {
+        let mut app = test_app();
+        with_connection(&mut app, |_, sn| {
+            for id in 0..3 {
+                sn.push_record(TxRecord::new(TxId(id), vec![call(id)]));
+            }
+            sn.record_mut(TxId(0))
+                .unwrap()
+                .set_status(TxStatus::Confirmed);
+            sn.record_mut(TxId(2))
+                .unwrap()
+                .set_status(TxStatus::Dropped);
+
+            let drained = sn.drain_history();
+            let ids = drained
+                .iter()
+                .map(|record| record.tx_id)
+                .collect::<Vec<_>>();
+            assert_eq!(ids, vec![TxId(0), TxId(2)]);
+            assert!(drained.iter().all(|record| record.completed_at.is_some()));
+
+            let kept = sn.export_history();
+            assert_eq!(kept.len(), 1);
+            assert_eq!(kept[0].tx_id, TxId(1));
+            assert!(sn.drain_history().is_empty());
+        });
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn records_round_trip_through_json_with_hex_felts() {
+        use serde_json::json;
+        use starknet::core::types::ResourceBounds;
+
+        let mut record = TxRecord::new(TxId(7), vec![call(0x2a)]);
+        record.hash = Some(Felt::from(0xabcu64));
+        record.nonce = Some(Felt::from(3u64));
+        record.fee = Some(1000);
+        record.bounds = Some(ResourceBoundsMapping {
+            l1_gas: ResourceBounds {
+                max_amount: 0,
+                max_price_per_unit: 1,
+            },
+            l1_data_gas: ResourceBounds {
+                max_amount: 2,
+                max_price_per_unit: 3,
+            },
+            l2_gas: ResourceBounds {
+                max_amount: 1500,
+                max_price_per_unit: 4,
+            },
+        });
+        record.set_status(TxStatus::Reverted {
+            reason: "out of gas".to_string(),
+        });
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["hash"], json!("0xabc"));
+        assert_eq!(value["nonce"], json!("0x3"));
+        assert_eq!(
+            value["calls"],
+            json!([{ "to": "0x42", "selector": "0x5e1", "calldata": ["0x2a"] }])
+        );
+
+        let decoded: TxRecord = serde_json::from_str(&value.to_string()).unwrap();
+        assert_eq!(decoded.tx_id, record.tx_id);
+        assert_eq!(decoded.hash, record.hash);
+        assert_eq!(decoded.status, record.status);
+        assert_eq!(decoded.calls.len(), 1);
+        assert_eq!(decoded.calls[0].to, record.calls[0].to);
+        assert_eq!(decoded.calls[0].selector, record.calls[0].selector);
+        assert_eq!(decoded.calls[0].calldata, record.calls[0].calldata);
+        assert_eq!(decoded.submitted_at, record.submitted_at);
+        assert_eq!(decoded.completed_at, record.completed_at);
+        assert_eq!(decoded.fee, record.fee);
+        assert_eq!(decoded.nonce, record.nonce);
+        assert_eq!(decoded.bounds, record.bounds);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn invalid_felts_are_rejected() {
+        let mut value = serde_json::to_value(TxRecord::new(TxId(1), vec![call(1)])).unwrap();
+        value["calls"][0]["to"] = serde_json::json!("not a felt");
+        assert!(serde_json::from_value::<TxRecord>(value).is_err());
+    }
+
+    #[test]
+    fn recent_txs_evict_the_oldest_final_records() {
+        let mut app = test_app();
+        with_connection(&mut app, |_, sn| {
+            sn.set_recent_txs_capacity(3);
+            let ids = |sn: &StarknetConnection| {
+                sn.recent_txs()
+                    .iter()
+                    .map(|record| record.tx_id.0)
+                    .collect::<Vec<_>>()
+            };
+            for id in 0..3 {
+                sn.push_record(TxRecord::new(TxId(id), vec![call(id)]));
+            }
+            sn.record_mut(TxId(0))
+                .unwrap()
+                .set_status(TxStatus::Confirmed);
+            sn.record_mut(TxId(2))
+                .unwrap()
+                .set_status(TxStatus::Confirmed);
+
+            sn.push_record(TxRecord::new(TxId(3), vec![call(3)]));
+            assert_eq!(ids(sn), [1, 2, 3]);
+            sn.push_record(TxRecord::new(TxId(4), vec![call(4)]));
+            assert_eq!(ids(sn), [1, 3, 4]);
+            assert!(sn.record(TxId(2)).is_none());
+
+            // Pending records are kept past the capacity
+            sn.push_record(TxRecord::new(TxId(5), vec![call(5)]));
+            assert_eq!(ids(sn), [1, 3, 4, 5]);
+            sn.record_mut(TxId(4))
+                .unwrap()
+                .set_status(TxStatus::Dropped);
+            sn.set_recent_txs_capacity(2);
+            assert_eq!(ids(sn), [1, 3, 5]);
+            assert_eq!(
+                sn.record(TxId(5)).unwrap().calls[0].calldata,
+                call(5).calldata
+            );
+        });
+    }
+
+    #[test]
+    fn recent_txs_are_capped_while_transactions_confirm() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        collect::<crate::sink::TransactionConfirmed>(&mut app);
+        with_connection(&mut app, |_, sn| sn.set_recent_txs_capacity(2));
+        // One at a time, so that transactions finish in the order they're sent
+        for n in 0..4 {
+            with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(n)]));
+            assert!(update_until(&mut app, |app| {
+                collected::<crate::sink::TransactionConfirmed>(app).len() == n as usize + 1
+            }));
+        }
+        app.update();
+
+        let recent = connection(&app).recent_txs();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].calls[0].calldata, call(2).calldata);
+        assert_eq!(recent[1].calls[0].calldata, call(3).calldata);
+        assert!(
+            recent
+                .iter()
+                .all(|record| record.status == TxStatus::Confirmed)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+
+use starknet::core::types::Call;
+
+use crate::record::TxStatus;
+use crate::starknet::{StarknetConnection, SubmitOutcome, TxId};
+use crate::tokio::TokioRuntime;
+
+impl StarknetConnection {
+    /// Returns true if `TransactionFailed` events carry the calls of the transaction
+    pub fn retain_failed_calls(&self) -> bool {
+        self.retain_failed_calls
+    }
+
+    /// Keep the calls of failed transactions in their `TransactionFailed` events
+    ///
+    /// This lets debugging tools show the calls of a failed transaction, tweak
+    /// them and try again with `resubmit_with`. Disabled by default, since
+    /// every failure then clones its calls into the event.
+    pub fn set_retain_failed_calls(&mut self, retain: bool) {
+        self.retain_failed_calls = retain;
+    }
+
+    /// Submit `calls` as a new transaction replacing the failed transaction `tx_id`
+    ///
+    /// This is the method form of `resubmit_with`.
+    pub fn resubmit_with(
+        &mut self,
+        runtime: &TokioRuntime,
+        tx_id: TxId,
+        calls: Vec<Call>,
+    ) -> Option<SubmitOutcome> {
+        let record = self.record(tx_id)?;
+        if !matches!(
+            record.status,
+            TxStatus::Failed { .. } | TxStatus::Reverted { .. } | TxStatus::Dropped
+        ) {
+            return None;
+        }
+        let outcome = self.execute(runtime, calls);
+        if let SubmitOutcome::Queued(new_id) = outcome {
+            info!(
+                "Resubmitting failed transaction {} as {}",
+                tx_id.0, new_id.0
+            );
+        }
+        Some(outcome)
+    }
+}
+
+/// Submit adjusted calls in place of a failed transaction
+///
+/// Meant for debugging tools: once a transaction failed to send, reverted or
+/// was dropped, its calls can be read from its record in `recent_txs`, or from
+/// its `TransactionFailed` event with `set_retain_failed_calls`, adjusted and
+/// submitted again. The calls are submitted as a fresh transaction, with its
+/// own `TxId` and nonce, going through the same checks as `execute_transaction`.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+/// * `tx_id` - The id of the failed transaction
+/// * `calls` - The calls of the new transaction
+///
+/// # Returns
+///
+/// * `Some` with the outcome of submitting the new transaction
+/// * `None` if `tx_id` isn't known or hasn't failed
+///
+/// # Example
+///
+/// ```no_run
+/// fn retry_with_fix(
+///     runtime: Res<TokioRuntime>,
+///     mut sn: ResMut<StarknetConnection>,
+///     mut failures: EventReader<TransactionFailed>,
+/// ) {
+///     for failed in failures.read() {
+///         let Some(mut calls) = failed.calls.clone() else {
+///             continue;
+///         };
+///         calls[0].calldata[1] = Felt::from(100u8);
+///         sn.resubmit_with(&runtime, failed.tx_id, calls);
+///     }
+/// }
+/// ```
+pub fn resubmit_with(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    tx_id: TxId,
+    calls: Vec<Call>,
+) -> Option<SubmitOutcome> {
+    sn.resubmit_with(&runtime, tx_id, calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::sink::{TransactionConfirmed, TransactionFailed};
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    #[test]
+    fn failed_transaction_is_resubmitted_with_new_calls() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on_error(
+            "starknet_addInvokeTransaction",
+            RpcError::new(-32603, "Internal error"),
+        );
+        let mut app = connected_app(&mock);
+        collect::<TransactionFailed>(&mut app);
+        collect::<TransactionConfirmed>(&mut app);
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.set_retain_failed_calls(true);
+            sn.execute(runtime, vec![call(1)])
+        });
+        let SubmitOutcome::Queued(tx_id) = outcome else {
+            panic!("transaction not queued: {outcome:?}");
+        };
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionFailed>(app).is_empty()
+        }));
+        let failed = collected::<TransactionFailed>(&app)[0].clone();
+        assert_eq!(failed.tx_id, tx_id);
+        let mut calls = failed.calls.unwrap();
+        assert_eq!(calls[0].calldata, call(1).calldata);
+
+        mock.accept_transactions(1000);
+        calls[0].calldata = vec![Felt::from(2u8)];
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.resubmit_with(runtime, tx_id, calls)
+        });
+        let Some(SubmitOutcome::Queued(new_id)) = outcome else {
+            panic!("transaction not resubmitted: {outcome:?}");
+        };
+        assert_ne!(new_id, tx_id);
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionConfirmed>(app).is_empty()
+        }));
+        assert_eq!(collected::<TransactionConfirmed>(&app)[0].tx_id, new_id);
+        let request = &mock.requests("starknet_addInvokeTransaction")[1];
+        let sent = param(request, 0, "invoke_transaction");
+        assert_eq!(
+            sent["calldata"].as_array().unwrap().last(),
+            Some(&json!("0x2"))
+        );
+
+        // Only failed transactions can be resubmitted
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.resubmit_with(runtime, new_id, vec![call(3)])
+        });
+        assert_eq!(outcome, None);
+    }
+}
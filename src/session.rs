@@ -0,0 +1,434 @@
+//! Session keys acting on behalf of the connected account
+//!
+//! The session signature of `SessionAccount` is specific to this crate, and
+//! no deployed account contract validates it yet. The module is kept private
+//! until one does, so games can't send session transactions that every account
+//! would reject. It stays built and tested for when it's exposed.
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use starknet::{
+    accounts::{
+        Account, ConnectedAccount, ExecutionEncoder, RawDeclarationV3, RawExecutionV3,
+        single_owner::SignError,
+    },
+    core::{
+        types::{BlockId, Call, Felt},
+        utils::get_selector_from_name,
+    },
+    macros::short_string,
+    providers::AnyProvider,
+    signers::{
+        LocalWallet, Signer, SignerInteractivityContext, SigningKey,
+        local_wallet::SignError as LocalWalletSignError,
+    },
+};
+
+use crate::hash::HashFunction;
+use crate::merkle::MerkleTree;
+use crate::record::{TxRecord, unix_now};
+use crate::signature::{DojoAccount, SignTransactionHash};
+use crate::starknet::{StarknetConnection, SubmitOutcome, validate_calls};
+use crate::tokio::TokioRuntime;
+
+/// First element of the signature of transactions signed by a session key
+pub const SESSION_SIGNATURE_MAGIC: Felt = short_string!("session-token");
+
+/// Domain of the message signed by the owner key to authorize a session
+pub const SESSION_AUTHORIZATION_DOMAIN: Felt = short_string!("bevy_dojo.session");
+
+/// Identifier of a session created with `create_session`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionId(pub u64);
+
+/// Entrypoint a session key is allowed to call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionPolicy {
+    pub contract: Felt,
+    pub selector: Felt,
+}
+
+impl SessionPolicy {
+    /// Allow calling the entrypoint named `entrypoint` of `contract`
+    ///
+    /// Returns `None` if `entrypoint` isn't a valid entrypoint name.
+    pub fn new(contract: Felt, entrypoint: &str) -> Option<Self> {
+        Some(Self {
+            contract,
+            selector: get_selector_from_name(entrypoint).ok()?,
+        })
+    }
+
+    /// Returns true if `call` is allowed by this policy
+    pub fn allows(&self, call: &Call) -> bool {
+        call.to == self.contract && call.selector == self.selector
+    }
+
+    /// Returns the leaf of this policy in the policies Merkle tree
+    fn leaf(&self) -> Felt {
+        HashFunction::Poseidon.hash(&self.contract, &self.selector)
+    }
+}
+
+/// Account signing with a session key on behalf of the connected account
+///
+/// Transactions are signed with the session key, and the signature carries
+/// the authorization of the session by the owner key, laid out as
+/// `[SESSION_SIGNATURE_MAGIC, session_public_key, expiry, policies_root,
+/// authorization_r, authorization_s, r, s]`.
+///
+/// This format is specific to this crate: no deployed account contract
+/// validates it, including the OpenZeppelin, Argent and Braavos accounts, and
+/// the session schemes of Argent and the Cartridge Controller lay out and
+/// authorize sessions differently. Session transactions are only accepted by
+/// an account contract written to check this layout: that `authorization` is
+/// the owner's signature of `poseidon(SESSION_AUTHORIZATION_DOMAIN, chain_id,
+/// account, session_public_key, expiry, policies_root)`, that the session
+/// hasn't expired, that every call is a leaf of the policies Merkle tree, and
+/// that `(r, s)` is the session key's signature of the transaction hash.
+#[derive(Debug)]
+pub struct SessionAccount {
+    owner: Arc<DojoAccount>,
+    signer: LocalWallet,
+    public_key: Felt,
+    expiry: u64,
+    policies_root: Felt,
+    authorization: [Felt; 2],
+}
+
+impl SessionAccount {
+    /// Returns the public key of the session key
+    pub fn public_key(&self) -> Felt {
+        self.public_key
+    }
+
+    /// Returns the expiry of the session, in seconds since the Unix epoch
+    pub fn expiry(&self) -> u64 {
+        self.expiry
+    }
+
+    /// Returns the root of the Merkle tree of the session policies
+    pub fn policies_root(&self) -> Felt {
+        self.policies_root
+    }
+
+    async fn sign_hash(&self, hash: Felt) -> Result<Vec<Felt>, SignError<LocalWalletSignError>> {
+        let signature = self
+            .signer
+            .sign_hash(&hash)
+            .await
+            .map_err(SignError::Signer)?;
+        Ok(vec![
+            SESSION_SIGNATURE_MAGIC,
+            self.public_key,
+            Felt::from(self.expiry),
+            self.policies_root,
+            self.authorization[0],
+            self.authorization[1],
+            signature.r,
+            signature.s,
+        ])
+    }
+}
+
+#[async_trait]
+impl SignTransactionHash for SessionAccount {
+    async fn sign_transaction_hash(
+        &self,
+        hash: Felt,
+    ) -> Result<Vec<Felt>, SignError<LocalWalletSignError>> {
+        self.sign_hash(hash).await
+    }
+}
+
+impl ExecutionEncoder for SessionAccount {
+    fn encode_calls(&self, calls: &[Call]) -> Vec<Felt> {
+        self.owner.encode_calls(calls)
+    }
+}
+
+#[async_trait]
+impl Account for SessionAccount {
+    type SignError = SignError<LocalWalletSignError>;
+
+    fn address(&self) -> Felt {
+        self.owner.address()
+    }
+
+    fn chain_id(&self) -> Felt {
+        self.owner.chain_id()
+    }
+
+    async fn sign_execution_v3(
+        &self,
+        execution: &RawExecutionV3,
+        query_only: bool,
+    ) -> Result<Vec<Felt>, Self::SignError> {
+        let hash = execution.transaction_hash(self.chain_id(), self.address(), query_only, self);
+        self.sign_hash(hash).await
+    }
+
+    async fn sign_declaration_v3(
+        &self,
+        declaration: &RawDeclarationV3,
+        query_only: bool,
+    ) -> Result<Vec<Felt>, Self::SignError> {
+        let hash = declaration.transaction_hash(self.chain_id(), self.address(), query_only);
+        self.sign_hash(hash).await
+    }
+
+    fn is_signer_interactive(&self, _context: SignerInteractivityContext<'_>) -> bool {
+        false
+    }
+}
+
+impl ConnectedAccount for SessionAccount {
+    type Provider = AnyProvider;
+
+    fn provider(&self) -> &AnyProvider {
+        self.owner.provider()
+    }
+
+    fn block_id(&self) -> BlockId {
+        self.owner.block_id()
+    }
+}
+
+struct Session {
+    policies: Vec<SessionPolicy>,
+    account: Arc<SessionAccount>,
+}
+
+/// Sessions of a `StarknetConnection`
+#[derive(Default)]
+pub(crate) struct SessionState {
+    /// Key of the connected account, authorizing sessions
+    owner_key: Option<SigningKey>,
+    next_id: u64,
+    sessions: HashMap<SessionId, Session>,
+}
+
+impl SessionState {
+    /// Use `owner_key` to authorize sessions, dropping sessions of the previous key
+    pub(crate) fn set_owner_key(&mut self, owner_key: SigningKey) {
+        self.owner_key = Some(owner_key);
+        self.sessions.clear();
+    }
+}
+
+impl StarknetConnection {
+    /// Create a session allowed to call the entrypoints of `policies` until `expiry`
+    ///
+    /// A random session key is generated and authorized by the key of the
+    /// connected account, which signs the session public key, its expiry and
+    /// the root of the Merkle tree of `policies`.
+    pub(crate) fn create_session(
+        &mut self,
+        policies: Vec<SessionPolicy>,
+        expiry: u64,
+    ) -> Option<SessionId> {
+        let owner = self.account().cloned()?;
+        let state = &mut self.sessions;
+        let owner_key = state.owner_key.as_ref()?;
+
+        let session_key = SigningKey::from_random();
+        let public_key = session_key.verifying_key().scalar();
+        let policies_root = MerkleTree::new(
+            policies.iter().map(SessionPolicy::leaf).collect(),
+            HashFunction::Poseidon,
+        )
+        .root();
+        let message = HashFunction::Poseidon.hash_many(&[
+            SESSION_AUTHORIZATION_DOMAIN,
+            owner.chain_id(),
+            owner.address(),
+            public_key,
+            Felt::from(expiry),
+            policies_root,
+        ]);
+        let authorization = match owner_key.sign(&message) {
+            Ok(signature) => [signature.r, signature.s],
+            Err(err) => {
+                warn!("Failed to authorize session: {}", err);
+                return None;
+            }
+        };
+
+        let id = SessionId(state.next_id);
+        state.next_id += 1;
+        let account = Arc::new(SessionAccount {
+            owner,
+            signer: LocalWallet::from(session_key),
+            public_key,
+            expiry,
+            policies_root,
+            authorization,
+        });
+        state.sessions.insert(id, Session { policies, account });
+        Some(id)
+    }
+
+    /// Returns the account of the session `id`, if it exists
+    pub(crate) fn session_account(&self, id: SessionId) -> Option<&Arc<SessionAccount>> {
+        self.sessions
+            .sessions
+            .get(&id)
+            .map(|session| &session.account)
+    }
+
+    /// Drop the session `id`
+    ///
+    /// Returns false if no session with this id exists.
+    pub(crate) fn end_session(&mut self, id: SessionId) -> bool {
+        self.sessions.sessions.remove(&id).is_some()
+    }
+
+    /// Queue a transaction executing `calls`, signed by the session `id`
+    ///
+    /// Every call must be allowed by one of the session policies, otherwise
+    /// the transaction is rejected before being signed. It is then tracked
+    /// like those of `execute`, including fee approval.
+    pub(crate) fn execute_session(
+        &mut self,
+        runtime: &TokioRuntime,
+        id: SessionId,
+        calls: Vec<Call>,
+    ) -> SubmitOutcome {
+        if !self.is_connected() {
+            return SubmitOutcome::NotConnected;
+        }
+        let Some(session) = self.sessions.sessions.get(&id) else {
+            return SubmitOutcome::UnknownSession;
+        };
+        if session.account.expiry <= unix_now() {
+            return SubmitOutcome::SessionExpired;
+        }
+        if let Some(index) = calls
+            .iter()
+            .position(|call| !session.policies.iter().any(|policy| policy.allows(call)))
+        {
+            warn!("Call {} is not allowed by session {}", index, id.0);
+            return SubmitOutcome::NotAllowedBySession { index };
+        }
+        if self.spend_limit_reached() {
+            warn!("Session spend limit reached, rejecting transaction");
+            return SubmitOutcome::SpendLimitReached;
+        }
+        if let Err(outcome) = validate_calls(&calls) {
+            warn!("Rejecting malformed transaction: {:?}", outcome);
+            return outcome;
+        }
+        if let Err(outcome) = self.check_calldata(&calls) {
+            return outcome;
+        }
+
+        let session = session.account.clone();
+        let Some(account) = self.account().cloned() else {
+            return SubmitOutcome::NotConnected;
+        };
+        let tx_id = self.next_tx_id();
+        self.push_record(TxRecord::new(tx_id, calls.clone()));
+        self.dispatch(runtime, account, Some(session), tx_id, calls);
+        self.metrics.submitted_txs += 1;
+        SubmitOutcome::Queued(tx_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approval::FeeEstimated;
+    use crate::mock::*;
+    use starknet::core::types::FeeEstimate;
+
+    const GAME: Felt = Felt::from_hex_unchecked("0x42");
+
+    fn session(app: &mut App) -> SessionId {
+        let policies = vec![SessionPolicy::new(GAME, "move").unwrap()];
+        with_connection(app, |_, sn| sn.create_session(policies, unix_now() + 3600)).unwrap()
+    }
+
+    fn game_call(entrypoint: &str) -> Call {
+        Call {
+            to: GAME,
+            selector: get_selector_from_name(entrypoint).unwrap(),
+            calldata: vec![Felt::ONE],
+        }
+    }
+
+    #[test]
+    fn allowed_calls_are_signed_by_the_session() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        let id = session(&mut app);
+
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.execute_session(runtime, id, vec![game_call("move"), game_call("attack")])
+        });
+        assert_eq!(outcome, SubmitOutcome::NotAllowedBySession { index: 1 });
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.execute_session(runtime, id, vec![game_call("move")])
+        });
+        assert!(outcome.is_queued());
+
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+        let request = &mock.requests("starknet_addInvokeTransaction")[0];
+        let signature = &param(request, 0, "invoke_transaction")["signature"];
+        let session = connection(&app).session_account(id).unwrap();
+        assert_eq!(signature.as_array().unwrap().len(), 8);
+        assert_eq!(signature[0], json_felt(SESSION_SIGNATURE_MAGIC));
+        assert_eq!(signature[1], json_felt(session.public_key()));
+        assert_eq!(signature[3], json_felt(session.policies_root()));
+    }
+
+    #[test]
+    fn session_transactions_wait_for_fee_approval() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        collect::<FeeEstimated>(&mut app);
+        let id = session(&mut app);
+
+        let outcome = with_connection(&mut app, |runtime, sn| {
+            sn.set_require_fee_approval(true);
+            sn.execute_session(runtime, id, vec![game_call("move")])
+        });
+        let SubmitOutcome::Queued(tx_id) = outcome else {
+            panic!("transaction not queued: {outcome:?}");
+        };
+        assert!(update_until(&mut app, |app| {
+            !collected::<FeeEstimated>(app).is_empty()
+        }));
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 0);
+        // Estimated with the session signature
+        let request = &mock.requests("starknet_estimateFee")[0];
+        let estimated = &param(request, 0, "request")[0]["signature"];
+        assert_eq!(estimated[0], json_felt(SESSION_SIGNATURE_MAGIC));
+
+        let estimate: FeeEstimate = collected::<FeeEstimated>(&app)[0].estimate.clone();
+        assert!(with_connection(&mut app, |runtime, sn| {
+            sn.approve_fee(runtime, tx_id, (&estimate).into())
+        }));
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+        let request = &mock.requests("starknet_addInvokeTransaction")[0];
+        let signature = &param(request, 0, "invoke_transaction")["signature"];
+        assert_eq!(signature[0], json_felt(SESSION_SIGNATURE_MAGIC));
+    }
+
+    fn json_felt(felt: Felt) -> serde_json::Value {
+        serde_json::Value::String(format!("{felt:#x}"))
+    }
+}
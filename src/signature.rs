@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use starknet::{
+    accounts::{
+        Account, ConnectedAccount, ExecutionEncoder, RawDeclarationV3, RawExecutionV3,
+        SingleOwnerAccount, single_owner::SignError,
+    },
+    core::types::{BlockId, Call, Felt},
+    providers::AnyProvider,
+    signers::{
+        LocalWallet, Signer, SignerInteractivityContext, SigningKey,
+        local_wallet::SignError as LocalWalletSignError,
+    },
+};
+
+use crate::starknet::StarknetConnection;
+
+/// Layout of the signature array sent with transactions
+///
+/// The standard format, used by default, is the `[r, s]` stark-curve
+/// signature. It is what OpenZeppelin accounts, Braavos accounts with their
+/// stark key and Argent accounts without a guardian expect, so most games
+/// never change it.
+///
+/// Some account contracts expect extra elements around it. The `prefix` and
+/// `suffix` are placed before and after `[r, s]`, so the array becomes
+/// `[..prefix, r, s, ..suffix]`. For example, Argent accounts from version 0.4
+/// also accept a list of signer signatures, `[1, 0, public_key, r, s]` for a
+/// single stark signer, and custom accounts may expect a signature scheme
+/// version before `r`. Setups needing a second signature per transaction,
+/// like an Argent guardian or a multisig, can't be expressed as a fixed
+/// prefix and suffix.
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(mut sn: ResMut<StarknetConnection>) {
+///     // The account expects `[1, r, s]`
+///     sn.set_signature_format(SignatureFormat::versioned(Felt::ONE));
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignatureFormat {
+    pub prefix: Vec<Felt>,
+    pub suffix: Vec<Felt>,
+}
+
+impl SignatureFormat {
+    /// The standard `[r, s]` format
+    pub fn standard() -> Self {
+        Self::default()
+    }
+
+    /// The `[version, r, s]` format
+    pub fn versioned(version: Felt) -> Self {
+        Self {
+            prefix: vec![version],
+            suffix: vec![],
+        }
+    }
+
+    /// Returns true if this is the standard `[r, s]` format
+    pub fn is_standard(&self) -> bool {
+        self.prefix.is_empty() && self.suffix.is_empty()
+    }
+
+    /// Lay out `signature` according to this format
+    pub fn apply(&self, signature: Vec<Felt>) -> Vec<Felt> {
+        if self.is_standard() {
+            return signature;
+        }
+        let mut formatted =
+            Vec::with_capacity(self.prefix.len() + signature.len() + self.suffix.len());
+        formatted.extend_from_slice(&self.prefix);
+        formatted.extend(signature);
+        formatted.extend_from_slice(&self.suffix);
+        formatted
+    }
+}
+
+/// Account used by a `StarknetConnection`
+///
+/// It signs with the `LocalWallet` of a `SingleOwnerAccount` and lays the
+/// signature out according to the connection's `SignatureFormat`.
+#[derive(Debug)]
+pub struct DojoAccount {
+    inner: Arc<SingleOwnerAccount<AnyProvider, LocalWallet>>,
+    signer: LocalWallet,
+    format: SignatureFormat,
+}
+
+impl DojoAccount {
+    /// Wrap `inner`, signing with the given signature format
+    ///
+    /// `signing_key` must be the key of the signer of `inner`. It signs the
+    /// transactions the crate builds itself, such as those carrying a tip.
+    pub fn new(
+        inner: Arc<SingleOwnerAccount<AnyProvider, LocalWallet>>,
+        signing_key: SigningKey,
+        format: SignatureFormat,
+    ) -> Self {
+        Self {
+            inner,
+            signer: LocalWallet::from(signing_key),
+            format,
+        }
+    }
+
+    /// Returns the wrapped single owner account
+    pub fn inner(&self) -> &SingleOwnerAccount<AnyProvider, LocalWallet> {
+        &self.inner
+    }
+
+    /// Returns the signature format used by this account
+    pub fn signature_format(&self) -> &SignatureFormat {
+        &self.format
+    }
+}
+
+/// Account able to sign a transaction hash computed by the crate
+///
+/// starknet-rs hashes the transactions it builds itself, always with a zero
+/// tip, so transactions carrying a tip are hashed by the crate and signed
+/// through this trait.
+#[async_trait]
+pub(crate) trait SignTransactionHash {
+    /// Returns the signature of the transaction `hash`, as sent with the transaction
+    async fn sign_transaction_hash(
+        &self,
+        hash: Felt,
+    ) -> Result<Vec<Felt>, SignError<LocalWalletSignError>>;
+}
+
+#[async_trait]
+impl SignTransactionHash for DojoAccount {
+    async fn sign_transaction_hash(
+        &self,
+        hash: Felt,
+    ) -> Result<Vec<Felt>, SignError<LocalWalletSignError>> {
+        let signature = self
+            .signer
+            .sign_hash(&hash)
+            .await
+            .map_err(SignError::Signer)?;
+        Ok(self.format.apply(vec![signature.r, signature.s]))
+    }
+}
+
+impl ExecutionEncoder for DojoAccount {
+    fn encode_calls(&self, calls: &[Call]) -> Vec<Felt> {
+        self.inner.encode_calls(calls)
+    }
+}
+
+#[async_trait]
+impl Account for DojoAccount {
+    type SignError = SignError<LocalWalletSignError>;
+
+    fn address(&self) -> Felt {
+        self.inner.address()
+    }
+
+    fn chain_id(&self) -> Felt {
+        self.inner.chain_id()
+    }
+
+    async fn sign_execution_v3(
+        &self,
+        execution: &RawExecutionV3,
+        query_only: bool,
+    ) -> Result<Vec<Felt>, Self::SignError> {
+        let signature = self.inner.sign_execution_v3(execution, query_only).await?;
+        Ok(self.format.apply(signature))
+    }
+
+    async fn sign_declaration_v3(
+        &self,
+        declaration: &RawDeclarationV3,
+        query_only: bool,
+    ) -> Result<Vec<Felt>, Self::SignError> {
+        let signature = self
+            .inner
+            .sign_declaration_v3(declaration, query_only)
+            .await?;
+        Ok(self.format.apply(signature))
+    }
+
+    fn is_signer_interactive(&self, context: SignerInteractivityContext<'_>) -> bool {
+        self.inner.is_signer_interactive(context)
+    }
+}
+
+impl ConnectedAccount for DojoAccount {
+    type Provider = AnyProvider;
+
+    fn provider(&self) -> &AnyProvider {
+        self.inner.provider()
+    }
+
+    fn block_id(&self) -> BlockId {
+        self.inner.block_id()
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the signature format used for transactions
+    pub fn signature_format(&self) -> &SignatureFormat {
+        &self.signature_format
+    }
+
+    /// Sets the signature format used for transactions
+    ///
+    /// It applies to the account created by the next connection, so it must be
+    /// set before connecting.
+    pub fn set_signature_format(&mut self, format: SignatureFormat) {
+        self.signature_format = format;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use serde_json::Value;
+    use starknet_crypto::get_public_key;
+
+    fn sent_signature(format: SignatureFormat) -> Vec<Value> {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = test_app();
+        app.insert_resource(mock.config());
+        with_connection(&mut app, |_, sn| sn.set_signature_format(format));
+        connect(&mut app);
+        with_connection(&mut app, |runtime, sn| sn.execute(runtime, vec![call(1)]));
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+        let request = &mock.requests("starknet_addInvokeTransaction")[0];
+        param(request, 0, "invoke_transaction")["signature"]
+            .as_array()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn apply_wraps_the_signature() {
+        let (r, s) = (Felt::from(0xau8), Felt::from(0xbu8));
+        assert_eq!(SignatureFormat::standard().apply(vec![r, s]), vec![r, s]);
+        assert_eq!(
+            SignatureFormat::versioned(Felt::ONE).apply(vec![r, s]),
+            vec![Felt::ONE, r, s]
+        );
+        let format = SignatureFormat {
+            prefix: vec![Felt::TWO],
+            suffix: vec![Felt::THREE, Felt::ZERO],
+        };
+        assert_eq!(
+            format.apply(vec![r, s]),
+            vec![Felt::TWO, r, s, Felt::THREE, Felt::ZERO]
+        );
+    }
+
+    #[test]
+    fn transactions_are_sent_with_the_signature_format() {
+        assert_eq!(sent_signature(SignatureFormat::standard()).len(), 2);
+
+        let public_key = get_public_key(&Felt::ONE);
+        let signer_list = SignatureFormat {
+            prefix: vec![Felt::ONE, Felt::ZERO, public_key],
+            suffix: vec![],
+        };
+        let signature = sent_signature(signer_list);
+        assert_eq!(signature.len(), 5);
+        assert_eq!(
+            signature[..3],
+            felts(&[Felt::ONE, Felt::ZERO, public_key])
+                .as_array()
+                .unwrap()[..]
+        );
+        assert_ne!(signature[3], "0x0");
+        assert_ne!(signature[4], "0x0");
+    }
+}
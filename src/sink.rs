@@ -0,0 +1,187 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use std::fmt;
+
+use starknet::core::types::{Call, Felt};
+
+use crate::display::fmt_felt;
+use crate::record::TxStatus;
+use crate::starknet::{TransactionDropped, TransactionSubmitted, TxId};
+
+/// Event emitted when a transaction is included in a block and executed successfully
+#[derive(Event, Debug, Clone)]
+pub struct TransactionConfirmed {
+    pub tx_id: TxId,
+    pub hash: Felt,
+    /// Fee paid by the transaction, in the smallest unit of the fee token
+    pub fee: u128,
+}
+
+impl fmt::Display for TransactionConfirmed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "transaction {} ({}) confirmed",
+            self.tx_id.0,
+            fmt_felt(&self.hash)
+        )
+    }
+}
+
+/// Event emitted when a transaction fails to be sent, reverts, is dropped or is rejected
+#[derive(Event, Debug, Clone)]
+pub struct TransactionFailed {
+    pub tx_id: TxId,
+    /// Hash of the transaction, `None` if it couldn't be sent
+    pub hash: Option<Felt>,
+    /// One of `TxStatus::Failed`, `TxStatus::Reverted`, `TxStatus::Dropped` or
+    /// `TxStatus::Rejected`
+    pub status: TxStatus,
+    /// Calls of the transaction, kept only if `set_retain_failed_calls` is enabled
+    pub calls: Option<Vec<Call>>,
+}
+
+impl fmt::Display for TransactionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction {}", self.tx_id.0)?;
+        if let Some(hash) = &self.hash {
+            write!(f, " ({})", fmt_felt(hash))?;
+        }
+        match &self.status {
+            TxStatus::Failed { error } => write!(f, " failed to send: {error}"),
+            TxStatus::Reverted { reason } => write!(f, " reverted: {reason}"),
+            TxStatus::Dropped => write!(f, " dropped"),
+            TxStatus::Rejected => write!(f, " rejected"),
+            status => write!(f, " failed: {status:?}"),
+        }
+    }
+}
+
+/// What happens when a transaction is submitted, confirmed or fails
+///
+/// By default, the `check_sn_task` system emits the `TransactionSubmitted`,
+/// `TransactionConfirmed` and `TransactionFailed` events, as well as
+/// `TransactionDropped` for dropped transactions. Inserting a
+/// `CustomTransactionSink` resource replaces these events by calls to the
+/// custom sink, to log, forward to a callback or record metrics instead.
+/// Transaction records, metrics and batches are updated either way.
+///
+/// Every method does nothing by default, so a sink only implements those it
+/// cares about.
+///
+/// # Example
+///
+/// ```no_run
+/// #[derive(Default)]
+/// struct ConfirmationCounter {
+///     confirmed: usize,
+/// }
+///
+/// impl TransactionSink for ConfirmationCounter {
+///     fn on_confirmed(&mut self, confirmed: &TransactionConfirmed) {
+///         self.confirmed += 1;
+///         println!("{} confirmed so far, last {}", self.confirmed, confirmed);
+///     }
+/// }
+///
+/// fn count_confirmations(app: &mut App) {
+///     app.insert_resource(CustomTransactionSink::new(ConfirmationCounter::default()));
+/// }
+/// ```
+pub trait TransactionSink {
+    /// Called when a transaction has been accepted by the provider
+    fn on_submitted(&mut self, _submitted: &TransactionSubmitted) {}
+
+    /// Called when a transaction has been included in a block and executed successfully
+    fn on_confirmed(&mut self, _confirmed: &TransactionConfirmed) {}
+
+    /// Called when a transaction fails to be sent, reverts, is dropped or is rejected
+    fn on_failed(&mut self, _failed: &TransactionFailed) {}
+}
+
+/// Resource replacing the transaction events by a custom `TransactionSink`
+///
+/// The sink is shared by the main connection and those of `StarknetChains`.
+#[derive(Resource)]
+pub struct CustomTransactionSink(pub(crate) Box<dyn TransactionSink + Send + Sync>);
+
+impl CustomTransactionSink {
+    /// Wrap `sink` to insert it as a resource
+    pub fn new(sink: impl TransactionSink + Send + Sync + 'static) -> Self {
+        Self(Box::new(sink))
+    }
+}
+
+/// Default `TransactionSink`, emitting the transaction events
+#[derive(SystemParam)]
+pub struct TransactionEvents<'w> {
+    submitted: EventWriter<'w, TransactionSubmitted>,
+    confirmed: EventWriter<'w, TransactionConfirmed>,
+    failed: EventWriter<'w, TransactionFailed>,
+    dropped: EventWriter<'w, TransactionDropped>,
+}
+
+impl TransactionSink for TransactionEvents<'_> {
+    fn on_submitted(&mut self, submitted: &TransactionSubmitted) {
+        self.submitted.write(submitted.clone());
+    }
+
+    fn on_confirmed(&mut self, confirmed: &TransactionConfirmed) {
+        self.confirmed.write(confirmed.clone());
+    }
+
+    fn on_failed(&mut self, failed: &TransactionFailed) {
+        if let (TxStatus::Dropped, Some(hash)) = (&failed.status, failed.hash) {
+            self.dropped.write(TransactionDropped {
+                tx_id: failed.tx_id,
+                hash,
+            });
+        }
+        self.failed.write(failed.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the confirmations it receives
+    struct ConfirmationCounter(Arc<AtomicUsize>);
+
+    impl TransactionSink for ConfirmationCounter {
+        fn on_confirmed(&mut self, confirmed: &TransactionConfirmed) {
+            assert_eq!(confirmed.fee, 1000);
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn custom_sink_replaces_the_events() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        let confirmed = Arc::new(AtomicUsize::new(0));
+        app.insert_resource(CustomTransactionSink::new(ConfirmationCounter(
+            confirmed.clone(),
+        )));
+        collect::<TransactionSubmitted>(&mut app);
+        collect::<TransactionConfirmed>(&mut app);
+
+        with_connection(&mut app, |runtime, sn| {
+            for n in 0..2 {
+                assert!(sn.execute(runtime, vec![call(n)]).is_queued());
+            }
+        });
+        assert!(update_until(&mut app, |_| {
+            confirmed.load(Ordering::SeqCst) == 2
+        }));
+
+        assert_eq!(connection(&app).metrics().confirmed_txs, 2);
+        assert!(collected::<TransactionSubmitted>(&app).is_empty());
+        assert!(collected::<TransactionConfirmed>(&app).is_empty());
+    }
+}
@@ -1,21 +1,337 @@
+use bevy::ecs::system::SystemId;
 use bevy::prelude::*;
 
 use std::collections::VecDeque;
-use std::str::FromStr;
+use std::fmt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
+use crate::abi::AbiState;
+use crate::approval::{ApprovalState, ESTIMATE_MARGIN, FeeBounds};
+use crate::batch::{BatchState, CancelledChunk};
+use crate::block_time::BlockTimeState;
+use crate::calldata::parse_felt;
+use crate::display::fmt_felt;
+use crate::faucet::{FaucetConfig, ensure_funds};
+use crate::fee_token::FeeTokenState;
+use crate::hash::invoke_v3_hash;
+use crate::health::HealthState;
+use crate::limit::RequestLimiter;
+use crate::query::QueryState;
+use crate::readonly::{ReadOnlyProvider, try_connect_readonly};
+use crate::reconnect::{Jitter, ReconnectState, TaskEvents, random_between};
+use crate::record::{RecentTxsCapacity, TxRecord, TxStatus};
+use crate::session::{SessionAccount, SessionState};
+use crate::signature::{DojoAccount, SignTransactionHash, SignatureFormat};
+use crate::sink::{TransactionConfirmed, TransactionFailed};
+use crate::subscription::SubscriptionState;
 use crate::tokio::TokioRuntime;
 use starknet::accounts::single_owner::SignError;
 use starknet::signers::local_wallet::SignError as LocalWalletSignError;
 use starknet::{
-    accounts::{Account, AccountError, ExecutionEncoding, SingleOwnerAccount},
-    core::types::{Call, Felt, InvokeTransactionResult},
-    providers::{AnyProvider, JsonRpcClient, Provider, Url, jsonrpc::HttpTransport},
+    accounts::{Account, AccountError, ConnectedAccount, ExecutionEncoding, SingleOwnerAccount},
+    core::types::{
+        BroadcastedInvokeTransactionV3, Call, DataAvailabilityMode, ExecutionResult, FeePayment,
+        Felt, InvokeTransactionResult, ResourceBoundsMapping, StarknetError, TransactionReceipt,
+        TransactionReceiptWithBlockInfo, TransactionStatus,
+    },
+    providers::{AnyProvider, JsonRpcClient, Provider, ProviderError, Url, jsonrpc::HttpTransport},
     signers::{LocalWallet, SigningKey},
 };
 
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 
+/// Source of transaction ids, shared by all connections so ids never collide
+static NEXT_TX_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Identifier assigned to every transaction submitted through `execute_transaction`
+///
+/// Ids are unique across all connections, including those of `StarknetChains`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TxId(pub u64);
+
+/// Result of submitting a transaction with `execute_transaction`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitOutcome {
+    /// The transaction was queued and will be sent in the background
+    Queued(TxId),
+    /// There is no active Starknet connection
+    NotConnected,
+    /// The connection is read-only and can't send transactions
+    ReadOnly,
+    /// The configured `SessionSpendLimit` has been reached
+    SpendLimitReached,
+    /// No chain is registered under the requested `ChainKey`
+    UnknownChain,
+    /// No session exists with the requested `SessionId`
+    UnknownSession,
+    /// The session has expired
+    SessionExpired,
+    /// The call at `index` isn't allowed by the session policies
+    NotAllowedBySession { index: usize },
+    /// The call at `index` is malformed and would be rejected by the network
+    InvalidCall {
+        index: usize,
+        reason: InvalidCallReason,
+    },
+    /// The call at `index` doesn't match the strict ABI binding registered for
+    /// its contract, see `register_abi`
+    CalldataTypeMismatch { index: usize },
+}
+
+/// Why a call was rejected by `execute_transaction` before being sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidCallReason {
+    /// No calls were given
+    NoCalls,
+    /// The target contract address is zero
+    ZeroAddress,
+    /// The entrypoint selector is zero
+    ZeroSelector,
+    /// The transaction calldata would exceed `MAX_CALLDATA_LEN` felts
+    CalldataTooLong,
+}
+
+impl fmt::Display for InvalidCallReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidCallReason::NoCalls => write!(f, "no calls"),
+            InvalidCallReason::ZeroAddress => write!(f, "zero contract address"),
+            InvalidCallReason::ZeroSelector => write!(f, "zero selector"),
+            InvalidCallReason::CalldataTooLong => {
+                write!(f, "calldata longer than {MAX_CALLDATA_LEN} felts")
+            }
+        }
+    }
+}
+
+/// Maximum length of the calldata of a transaction accepted by sequencers
+pub const MAX_CALLDATA_LEN: usize = 4000;
+
+/// Check `calls` for mistakes that would only surface once the transaction is sent
+///
+/// This catches an empty list of calls, zero addresses and selectors, which
+/// usually come from unset values, and calldata too long to be accepted.
+pub fn validate_calls(calls: &[Call]) -> Result<(), SubmitOutcome> {
+    let invalid = |index, reason| Err(SubmitOutcome::InvalidCall { index, reason });
+    if calls.is_empty() {
+        return invalid(0, InvalidCallReason::NoCalls);
+    }
+    for (index, call) in calls.iter().enumerate() {
+        if call.to == Felt::ZERO {
+            return invalid(index, InvalidCallReason::ZeroAddress);
+        }
+        if call.selector == Felt::ZERO {
+            return invalid(index, InvalidCallReason::ZeroSelector);
+        }
+    }
+    // The account's `__execute__` receives the call count, then the target,
+    // selector, calldata length and calldata of every call
+    let len = 1 + calls
+        .iter()
+        .map(|call| 3 + call.calldata.len())
+        .sum::<usize>();
+    if len > MAX_CALLDATA_LEN {
+        return invalid(calls.len() - 1, InvalidCallReason::CalldataTooLong);
+    }
+    Ok(())
+}
+
+impl SubmitOutcome {
+    /// Returns true if the transaction was queued for sending
+    pub fn is_queued(&self) -> bool {
+        matches!(self, SubmitOutcome::Queued(_))
+    }
+}
+
+/// Result of starting a connection with `init_starknet_connection`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectOutcome {
+    /// A new connection attempt was started
+    Started,
+    /// A connection attempt is already in progress, nothing was started
+    AlreadyConnecting,
+    /// The connection is already established, nothing was started
+    AlreadyConnected,
+}
+
+/// Cap on the total fees a session may spend
+///
+/// Once the cumulative `actual_fee` of confirmed transactions reaches
+/// `max_total_fee`, `execute_transaction` rejects new submissions with
+/// `SubmitOutcome::SpendLimitReached`. Fees are tracked in the smallest unit
+/// of the fee token (FRI for STRK-paid v3 transactions).
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(mut sn: ResMut<StarknetConnection>) {
+///     // Allow at most 10 STRK worth of fees this session
+///     sn.set_spend_limit(Some(SessionSpendLimit {
+///         max_total_fee: 10 * 10u128.pow(18),
+///     }));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionSpendLimit {
+    pub max_total_fee: u128,
+}
+
+/// Counters describing the transactions handled by a `StarknetConnection`
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StarknetMetrics {
+    /// Number of transactions queued with `execute_transaction`
+    pub submitted_txs: u64,
+    /// Number of transactions whose receipt reported a successful execution
+    pub confirmed_txs: u64,
+    /// Number of transactions that failed to send or were reverted
+    pub failed_txs: u64,
+    /// Number of transactions dropped before being included in a block
+    pub dropped_txs: u64,
+    /// Sum of the `actual_fee` of every received receipt
+    pub total_fee_spent: u128,
+    /// Number of background tasks owned by the connection, as of the last poll
+    ///
+    /// This covers connection attempts, transaction sends and confirmations,
+    /// fee estimates, queries and subscriptions. It should return to its
+    /// baseline once the game stops submitting work; steady growth hints at
+    /// leaked tasks.
+    pub active_tasks: usize,
+}
+
+/// Polling intervals used while waiting for a transaction to be confirmed
+///
+/// Polling starts at `min_interval` and doubles after every lookup that finds
+/// the transaction in the same state, up to `max_interval`. Whenever the
+/// transaction status changes, the interval resets to `min_interval`.
+/// With `jitter`, each wait is picked at random so that many clients
+/// confirming at once don't all poll the provider at the same moments.
+///
+/// A transaction the provider still doesn't know `dropped_after` its
+/// submission is considered dropped from the mempool, and a
+/// `TransactionDropped` event is emitted so the game can resubmit it.
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(mut sn: ResMut<StarknetConnection>) {
+///     sn.set_confirmation_polling(ConfirmationPolling {
+///         min_interval: Duration::from_millis(500),
+///         max_interval: Duration::from_secs(5),
+///         ..Default::default()
+///     });
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmationPolling {
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+    pub dropped_after: Duration,
+    pub jitter: Jitter,
+}
+
+impl Default for ConfirmationPolling {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(10),
+            dropped_after: Duration::from_secs(120),
+            jitter: Jitter::None,
+        }
+    }
+}
+
+impl ConfirmationPolling {
+    /// Returns the interval to wait after `current` when the status did not change
+    pub fn next_interval(&self, current: Duration) -> Duration {
+        current
+            .saturating_mul(2)
+            .clamp(self.min_interval, self.max_interval.max(self.min_interval))
+    }
+
+    /// Returns the time to actually wait for `interval`, with jitter applied
+    ///
+    /// `Jitter::Full` picks a wait between `min_interval` and `interval`, and
+    /// `Jitter::Decorrelated` one between `min_interval` and three times
+    /// `interval`, capped at `max_interval`.
+    pub fn jittered_interval(&self, interval: Duration) -> Duration {
+        let max_interval = self.max_interval.max(self.min_interval);
+        match self.jitter {
+            Jitter::None => interval,
+            Jitter::Full => random_between(self.min_interval, interval.max(self.min_interval)),
+            Jitter::Decorrelated => {
+                let high = interval.saturating_mul(3).max(self.min_interval);
+                random_between(self.min_interval, high).min(max_interval)
+            }
+        }
+    }
+}
+
+/// A transaction accepted by the provider, with the nonce and bounds it was sent with
+struct SentTx {
+    result: InvokeTransactionResult,
+    nonce: Felt,
+    bounds: FeeBounds,
+}
+
+struct PendingTx {
+    id: TxId,
+    calls: Vec<Call>,
+    task: JoinHandle<Result<SentTx, AccountError<SignError<LocalWalletSignError>>>>,
+}
+
+/// Event emitted when a transaction has been sent to the provider
+///
+/// It carries the nonce and the final resource bounds of the transaction,
+/// whether they were set explicitly or derived from the fee estimate, which
+/// helps debugging fee issues.
+#[derive(Event, Debug, Clone)]
+pub struct TransactionSubmitted {
+    pub tx_id: TxId,
+    pub bounds: ResourceBoundsMapping,
+    pub nonce: Felt,
+}
+
+/// Event emitted when a submitted transaction is no longer known to the provider
+///
+/// This happens when a transaction is dropped from the mempool before being
+/// included in a block. See `ConfirmationPolling::dropped_after`.
+#[derive(Event, Debug, Clone)]
+pub struct TransactionDropped {
+    pub tx_id: TxId,
+    pub hash: Felt,
+}
+
+impl fmt::Display for TransactionDropped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "transaction {} ({}) dropped",
+            self.tx_id.0,
+            fmt_felt(&self.hash)
+        )
+    }
+}
+
+pub(crate) enum Confirmation {
+    Accepted(Box<TransactionReceiptWithBlockInfo>),
+    Dropped,
+}
+
+pub(crate) struct ConfirmingTx {
+    pub(crate) id: TxId,
+    pub(crate) hash: Felt,
+    /// Calls, nonce and bounds of the transaction, kept to resubmit it when bumped
+    pub(crate) calls: Vec<Call>,
+    pub(crate) nonce: Felt,
+    pub(crate) bounds: FeeBounds,
+    pub(crate) task: JoinHandle<Result<Confirmation, ProviderError>>,
+}
+
 /// Resource to store Starknet connection state
 ///
 /// This resource manages the connection to Starknet and tracks pending transactions.
@@ -39,15 +355,41 @@ use tokio::task::JoinHandle;
 /// ```
 #[derive(Resource, Default)]
 pub struct StarknetConnection {
-    connecting_task: Option<JoinHandle<Arc<SingleOwnerAccount<AnyProvider, LocalWallet>>>>,
-    account: Option<Arc<SingleOwnerAccount<AnyProvider, LocalWallet>>>,
-    pending_txs: VecDeque<
-        JoinHandle<Result<InvokeTransactionResult, AccountError<SignError<LocalWalletSignError>>>>,
-    >,
+    /// Set once the connection is established, for `wait_until_connected`
+    ready: watch::Sender<bool>,
+    pub(crate) connecting_task: Option<JoinHandle<Result<Connected, ConnectError>>>,
+    account: Option<Arc<DojoAccount>>,
+    pub(crate) read_only: Option<Arc<ReadOnlyProvider>>,
+    pending_txs: VecDeque<PendingTx>,
+    pub(crate) confirming_txs: Vec<ConfirmingTx>,
+    pub(crate) metrics: StarknetMetrics,
+    spend_limit: Option<SessionSpendLimit>,
+    confirmation_polling: ConfirmationPolling,
+    tip: u64,
+    pub(crate) abis: AbiState,
+    pub(crate) approvals: ApprovalState,
+    pub(crate) faucet: Option<FaucetConfig>,
+    pub(crate) fee_token: FeeTokenState,
+    pub(crate) batches: BatchState,
+    pub(crate) block_times: BlockTimeState,
+    pub(crate) health: HealthState,
+    pub(crate) limiter: RequestLimiter,
+    pub(crate) retain_failed_calls: bool,
+    pub(crate) history: VecDeque<TxRecord>,
+    pub(crate) signature_format: SignatureFormat,
+    pub(crate) poll_budget: Option<Duration>,
+    pub(crate) recent_txs_capacity: RecentTxsCapacity,
+    pub(crate) queries: QueryState,
+    pub(crate) reconnect: ReconnectState,
+    pub(crate) sessions: SessionState,
+    pub(crate) subscriptions: SubscriptionState,
+    pub(crate) connected_hooks: Vec<SystemId>,
 }
 
 impl StarknetConnection {
-    /// Returns true if the connection is established
+    /// Returns true if the connection is established with an account
+    ///
+    /// Read-only connections aren't included, see `is_read_only`.
     pub fn is_connected(&self) -> bool {
         self.account.is_some()
     }
@@ -57,9 +399,612 @@ impl StarknetConnection {
         self.connecting_task.is_some()
     }
 
+    /// Returns a future that resolves once the connection is established
+    ///
+    /// The future resolves immediately if already connected. It doesn't
+    /// borrow the connection, so it can be moved into an async task, and
+    /// completes when the `check_sn_task` system marks the connection ready.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn setup(runtime: Res<TokioRuntime>, sn: Res<StarknetConnection>) {
+    ///     let connected = sn.wait_until_connected();
+    ///     runtime.runtime.spawn(async move {
+    ///         connected.await;
+    ///         println!("Connected to Starknet");
+    ///     });
+    /// }
+    /// ```
+    pub fn wait_until_connected(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut ready = self.ready.subscribe();
+        async move {
+            // An error means the connection resource was dropped, it will never connect
+            let _ = ready.wait_for(|ready| *ready).await;
+        }
+    }
+
+    /// Returns the chain id of the connected network, if connected or read-only
+    pub fn chain_id(&self) -> Option<Felt> {
+        match (&self.account, &self.read_only) {
+            (Some(account), _) => Some(account.chain_id()),
+            (None, Some(read_only)) => Some(read_only.chain_id),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the connected account, if any
+    pub(crate) fn account(&self) -> Option<&Arc<DojoAccount>> {
+        self.account.as_ref()
+    }
+
     /// Returns the number of pending transactions
     pub fn pending_tx_count(&self) -> usize {
-        self.pending_txs.len()
+        // A bumped transaction is both confirming and sending its replacement
+        let replacing = self
+            .confirming_txs
+            .iter()
+            .filter(|confirming| self.is_sending(confirming.id))
+            .count();
+        self.pending_txs.len() + self.confirming_txs.len() - replacing + self.approvals.len()
+    }
+
+    /// Returns the transaction metrics collected for this connection
+    pub fn metrics(&self) -> &StarknetMetrics {
+        &self.metrics
+    }
+
+    /// Returns the session spend limit, if any
+    pub fn spend_limit(&self) -> Option<SessionSpendLimit> {
+        self.spend_limit
+    }
+
+    /// Sets or clears the session spend limit
+    pub fn set_spend_limit(&mut self, limit: Option<SessionSpendLimit>) {
+        self.spend_limit = limit;
+    }
+
+    /// Returns the polling intervals used while waiting for confirmations
+    pub fn confirmation_polling(&self) -> ConfirmationPolling {
+        self.confirmation_polling
+    }
+
+    /// Sets the polling intervals used while waiting for confirmations
+    ///
+    /// Only transactions submitted after this call use the new intervals.
+    pub fn set_confirmation_polling(&mut self, polling: ConfirmationPolling) {
+        self.confirmation_polling = polling;
+    }
+
+    /// Returns the tip added to transactions
+    pub fn tip(&self) -> u64 {
+        self.tip
+    }
+
+    /// Sets the tip added to transactions
+    ///
+    /// The tip is paid to the sequencer on top of the fee, per unit of L2 gas,
+    /// in FRI. Starknet orders its mempool by tip, so a higher tip gets
+    /// transactions included sooner when blocks are busy. Fee estimates don't
+    /// include it, and the L2 gas price bound must leave room for it.
+    /// Defaults to 0, and only applies to transactions sent after this call.
+    pub fn set_tip(&mut self, tip: u64) {
+        self.tip = tip;
+    }
+
+    /// Returns the time budget for processing completed tasks each frame, if any
+    pub fn poll_budget(&self) -> Option<Duration> {
+        self.poll_budget
+    }
+
+    /// Sets or clears the time budget for processing completed tasks each frame
+    ///
+    /// When many transactions and queries complete at once, processing all of
+    /// them in a single frame can cause a hitch. With a budget, the pollers
+    /// stop processing completed tasks once it has elapsed and pick up the
+    /// rest on the next frame. By default there is no budget.
+    ///
+    /// The budget is shared by every poller of the frame: `check_sn_task`,
+    /// `check_chain_tasks`, `check_fee_estimates` and `check_sn_queries`. The
+    /// budget of the main connection applies to the connections of
+    /// `StarknetChains` too. Each poller still processes at least one completed
+    /// task per frame, so a tiny budget slows processing down without stalling it.
+    pub fn set_poll_budget(&mut self, budget: Option<Duration>) {
+        self.poll_budget = budget;
+    }
+
+    /// Returns true if the spend limit is set and has been reached
+    pub fn spend_limit_reached(&self) -> bool {
+        self.spend_limit
+            .is_some_and(|limit| self.metrics.total_fee_spent >= limit.max_total_fee)
+    }
+
+    /// Start connecting to Starknet unless already connected or connecting
+    ///
+    /// This is the method form of `init_starknet_connection`.
+    pub fn connect(
+        &mut self,
+        runtime: &TokioRuntime,
+        config: &DefaultStarknetConfig,
+    ) -> ConnectOutcome {
+        if self.account.is_some() {
+            return ConnectOutcome::AlreadyConnected;
+        }
+        if self.connecting_task.is_some() {
+            return ConnectOutcome::AlreadyConnecting;
+        }
+        self.reconnect.start(config.clone(), false);
+        self.connecting_task = Some(spawn_connect(
+            runtime,
+            config.clone(),
+            Duration::ZERO,
+            false,
+            self.limiter.clone(),
+        ));
+        info!("Connecting to Starknet...");
+        ConnectOutcome::Started
+    }
+
+    /// Abort the connection attempt in progress, if any
+    ///
+    /// This is the method form of `cancel_connecting`.
+    pub fn cancel_connecting(&mut self) -> bool {
+        let Some(task) = self.connecting_task.take() else {
+            return false;
+        };
+        task.abort();
+        self.reconnect.stop();
+        info!("Cancelled connecting to Starknet");
+        true
+    }
+
+    /// Use `account`, signing with `signing_key`, wrapped with the configured signature format
+    pub(crate) fn set_account(
+        &mut self,
+        account: Arc<SingleOwnerAccount<AnyProvider, LocalWallet>>,
+        signing_key: SigningKey,
+    ) {
+        self.account = Some(Arc::new(DojoAccount::new(
+            account,
+            signing_key,
+            self.signature_format.clone(),
+        )));
+        self.read_only = None;
+        self.queries.public_key = None;
+        self.ready.send_replace(true);
+    }
+
+    /// Queue a transaction executing `calls`
+    ///
+    /// This is the method form of `execute_transaction`.
+    pub fn execute(&mut self, runtime: &TokioRuntime, calls: Vec<Call>) -> SubmitOutcome {
+        if self.is_read_only() {
+            return SubmitOutcome::ReadOnly;
+        }
+        let Some(account) = self.account.clone() else {
+            return SubmitOutcome::NotConnected;
+        };
+        if self.spend_limit_reached() {
+            warn!("Session spend limit reached, rejecting transaction");
+            return SubmitOutcome::SpendLimitReached;
+        }
+        if let Err(outcome) = validate_calls(&calls) {
+            warn!("Rejecting malformed transaction: {:?}", outcome);
+            return outcome;
+        }
+        if let Err(outcome) = self.check_calldata(&calls) {
+            return outcome;
+        }
+
+        let id = self.next_tx_id();
+        self.push_record(TxRecord::new(id, calls.clone()));
+        self.dispatch(runtime, account, None, id, calls);
+        self.metrics.submitted_txs += 1;
+        SubmitOutcome::Queued(id)
+    }
+
+    /// Send the transaction `id`, or estimate its fee first if approval is required
+    ///
+    /// The transaction is signed by `session` if given, by `account` otherwise.
+    pub(crate) fn dispatch(
+        &mut self,
+        runtime: &TokioRuntime,
+        account: Arc<DojoAccount>,
+        session: Option<Arc<SessionAccount>>,
+        id: TxId,
+        calls: Vec<Call>,
+    ) {
+        if self.approvals.require_fee_approval {
+            self.approvals
+                .estimate(runtime, account, session, self.limiter.clone(), id, calls);
+        } else {
+            self.queue_send_as(runtime, session, id, calls, None, None);
+        }
+    }
+
+    /// Spawn the task sending the transaction `id`
+    ///
+    /// The transaction uses explicit fee bounds and nonce if given, otherwise
+    /// they are fetched from the provider and derived from the fee estimate
+    /// with an `ESTIMATE_MARGIN`.
+    pub(crate) fn queue_send(
+        &mut self,
+        runtime: &TokioRuntime,
+        id: TxId,
+        calls: Vec<Call>,
+        bounds: Option<FeeBounds>,
+        nonce: Option<Felt>,
+    ) {
+        let Some(account) = self.account.clone() else {
+            return;
+        };
+        self.queue_send_from(runtime, account, id, calls, bounds, nonce);
+    }
+
+    /// Spawn the task sending the transaction `id`, signed by `session` if given
+    pub(crate) fn queue_send_as(
+        &mut self,
+        runtime: &TokioRuntime,
+        session: Option<Arc<SessionAccount>>,
+        id: TxId,
+        calls: Vec<Call>,
+        bounds: Option<FeeBounds>,
+        nonce: Option<Felt>,
+    ) {
+        match session {
+            Some(session) => self.queue_send_from(runtime, session, id, calls, bounds, nonce),
+            None => self.queue_send(runtime, id, calls, bounds, nonce),
+        }
+    }
+
+    /// Spawn the task sending the transaction `id`, signed by `sender`
+    ///
+    /// This is `queue_send` for transactions signed by another account than
+    /// the connected one, such as a session account acting on its behalf.
+    pub(crate) fn queue_send_from<A>(
+        &mut self,
+        runtime: &TokioRuntime,
+        sender: Arc<A>,
+        id: TxId,
+        calls: Vec<Call>,
+        bounds: Option<FeeBounds>,
+        nonce: Option<Felt>,
+    ) where
+        A: ConnectedAccount<Provider = AnyProvider, SignError = SignError<LocalWalletSignError>>
+            + SignTransactionHash
+            + Send
+            + Sync
+            + 'static,
+    {
+        let Some(account) = self.account.clone() else {
+            return;
+        };
+        let faucet = self.faucet.clone();
+        let proxy = self.reconnect.proxy().map(str::to_string);
+        let limiter = self.limiter.clone();
+        let tip = self.tip;
+        let sent_calls = calls.clone();
+        let task = runtime.runtime.spawn(async move {
+            if let Some(faucet) = faucet {
+                ensure_funds(&account, &sent_calls, &faucet, proxy.as_deref(), &limiter).await;
+            }
+            let _permit = limiter.acquire().await;
+            let nonce = match nonce {
+                Some(nonce) => nonce,
+                None => match sender.get_nonce().await {
+                    Ok(nonce) => nonce,
+                    Err(err) => return Err(AccountError::Provider(err)),
+                },
+            };
+            let bounds = match bounds {
+                Some(bounds) => bounds,
+                None => {
+                    let estimate = sender
+                        .execute_v3(sent_calls.clone())
+                        .nonce(nonce)
+                        .estimate_fee()
+                        .await?;
+                    FeeBounds::with_margin(&estimate, ESTIMATE_MARGIN)
+                }
+            };
+            let result = send_invoke_v3(&*sender, &sent_calls, nonce, bounds, tip).await?;
+            Ok::<_, AccountError<SignError<LocalWalletSignError>>>(SentTx {
+                result,
+                nonce,
+                bounds,
+            })
+        });
+        self.pending_txs.push_back(PendingTx { id, calls, task });
+    }
+
+    /// Check the connection task and the pending transactions
+    ///
+    /// This is the body of the `check_sn_task` system, shared with the
+    /// connections of `StarknetChains`.
+    pub(crate) fn poll_tasks(&mut self, runtime: &TokioRuntime, events: &mut TaskEvents) {
+        // Check connection task
+        self.poll_connection(runtime, events);
+        self.run_connected_hooks(events);
+        self.poll_fee_token(runtime);
+        self.poll_block_times(runtime, events);
+        self.poll_rejected(events);
+        self.poll_cancelled(events);
+        // Records that became final since the last frame can make room now
+        self.trim_history();
+
+        // Check pending transactions
+        let mut processed = 0;
+        let mut i = 0;
+        while i < self.pending_txs.len()
+            && self.account.is_some()
+            && events.deadline.allows(processed)
+        {
+            if !self.pending_txs[i].task.is_finished() {
+                i += 1;
+                continue;
+            }
+            processed += 1;
+            if let Some(pending) = self.pending_txs.remove(i) {
+                match runtime.runtime.block_on(pending.task) {
+                    Ok(Ok(_)) if self.replaced_tx_completed(pending.id) => {
+                        warn!(
+                            "Replacement of transaction {} was accepted after the transaction completed",
+                            pending.id.0
+                        );
+                    }
+                    Ok(Ok(sent)) => {
+                        let result = sent.result;
+                        if let Some(index) = self
+                            .confirming_txs
+                            .iter()
+                            .position(|confirming| confirming.id == pending.id)
+                        {
+                            let replaced = self.confirming_txs.swap_remove(index);
+                            replaced.task.abort();
+                            info!(
+                                "Transaction {} ({}) replaced by {}",
+                                pending.id.0,
+                                fmt_felt(&replaced.hash),
+                                fmt_felt(&result.transaction_hash)
+                            );
+                        }
+                        info!(
+                            "Transaction completed: {}",
+                            fmt_felt(&result.transaction_hash)
+                        );
+                        self.subscriptions.record_local_tx(result.transaction_hash);
+                        if let Some(record) = self.record_mut(pending.id) {
+                            record.hash = Some(result.transaction_hash);
+                            record.nonce = Some(sent.nonce);
+                            record.bounds = Some(sent.bounds.resource_bounds());
+                            record.set_status(TxStatus::Sent);
+                        }
+                        events.sink().on_submitted(&TransactionSubmitted {
+                            tx_id: pending.id,
+                            bounds: sent.bounds.resource_bounds(),
+                            nonce: sent.nonce,
+                        });
+                        if let Some(account) = self.account.clone() {
+                            let hash = result.transaction_hash;
+                            let polling = self.confirmation_polling;
+                            let limiter = self.limiter.clone();
+                            let task = runtime.runtime.spawn(async move {
+                                wait_for_confirmation(account.provider(), hash, polling, &limiter)
+                                    .await
+                            });
+                            self.confirming_txs.push(ConfirmingTx {
+                                id: pending.id,
+                                hash,
+                                calls: pending.calls,
+                                nonce: sent.nonce,
+                                bounds: sent.bounds,
+                                task,
+                            });
+                        }
+                    }
+                    Ok(Err(err)) if self.is_replacing(pending.id) => {
+                        warn!(
+                            "Replacement of transaction {} was rejected, keeping the original: {}",
+                            pending.id.0, err
+                        );
+                    }
+                    Ok(Err(err)) => {
+                        warn!("Transaction {} failed to send: {}", pending.id.0, err);
+                        if matches!(
+                            err,
+                            AccountError::Provider(ProviderError::StarknetError(
+                                StarknetError::InsufficientAccountBalance
+                            ))
+                        ) {
+                            self.redetect_fee_token(runtime);
+                        }
+                        events.sink().on_failed(&TransactionFailed {
+                            tx_id: pending.id,
+                            hash: None,
+                            status: TxStatus::Failed {
+                                error: err.to_string(),
+                            },
+                            calls: self.retain_failed_calls.then_some(pending.calls),
+                        });
+                        let cancelled = self.fail_tx(pending.id, err.to_string());
+                        self.report_cancelled(cancelled, events);
+                    }
+                    Err(err) if self.is_replacing(pending.id) => {
+                        warn!(
+                            "Replacement of transaction {} did not finish, keeping the original: {}",
+                            pending.id.0, err
+                        );
+                    }
+                    Err(err) => {
+                        warn!("Transaction {} send task failed: {}", pending.id.0, err);
+                        events.sink().on_failed(&TransactionFailed {
+                            tx_id: pending.id,
+                            hash: None,
+                            status: TxStatus::Failed {
+                                error: err.to_string(),
+                            },
+                            calls: self.retain_failed_calls.then_some(pending.calls),
+                        });
+                        let cancelled = self.fail_tx(pending.id, err.to_string());
+                        self.report_cancelled(cancelled, events);
+                    }
+                }
+            }
+        }
+
+        // Check transactions awaiting their receipt
+        let mut i = 0;
+        while i < self.confirming_txs.len() && events.deadline.allows(processed) {
+            if !self.confirming_txs[i].task.is_finished() {
+                i += 1;
+                continue;
+            }
+            processed += 1;
+            let confirming = self.confirming_txs.swap_remove(i);
+            match runtime.runtime.block_on(confirming.task) {
+                Ok(Ok(Confirmation::Accepted(receipt))) => {
+                    let fee =
+                        felt_to_u128(&actual_fee(&receipt.receipt).amount).unwrap_or(u128::MAX);
+                    self.metrics.total_fee_spent = self.metrics.total_fee_spent.saturating_add(fee);
+                    let status = match receipt.receipt.execution_result() {
+                        ExecutionResult::Succeeded => {
+                            info!("Transaction confirmed: {}", fmt_felt(&confirming.hash));
+                            self.metrics.confirmed_txs += 1;
+                            self.health.record_success(receipt.block.block_number());
+                            TxStatus::Confirmed
+                        }
+                        ExecutionResult::Reverted { reason } => {
+                            warn!(
+                                "Transaction reverted: {}: {}",
+                                fmt_felt(&confirming.hash),
+                                reason
+                            );
+                            self.metrics.failed_txs += 1;
+                            self.health.record_error(reason.clone());
+                            TxStatus::Reverted {
+                                reason: reason.clone(),
+                            }
+                        }
+                    };
+                    let confirmed = status == TxStatus::Confirmed;
+                    if confirmed {
+                        events.sink().on_confirmed(&TransactionConfirmed {
+                            tx_id: confirming.id,
+                            hash: confirming.hash,
+                            fee,
+                        });
+                    } else {
+                        events.sink().on_failed(&TransactionFailed {
+                            tx_id: confirming.id,
+                            hash: Some(confirming.hash),
+                            status: status.clone(),
+                            calls: self.retain_failed_calls.then(|| confirming.calls.clone()),
+                        });
+                    }
+                    if let Some(record) = self.record_mut(confirming.id) {
+                        record.fee = Some(fee);
+                        record.set_status(status);
+                    }
+                    if confirmed {
+                        self.send_next_chunk(runtime, confirming.id);
+                    } else {
+                        let cancelled = self.abort_batch(confirming.id);
+                        self.report_cancelled(cancelled, events);
+                    }
+                }
+                Ok(Ok(Confirmation::Dropped)) => {
+                    warn!("Transaction dropped: {}", fmt_felt(&confirming.hash));
+                    self.metrics.dropped_txs += 1;
+                    self.health.record_error(format!(
+                        "transaction {} dropped",
+                        fmt_felt(&confirming.hash)
+                    ));
+                    if let Some(record) = self.record_mut(confirming.id) {
+                        record.set_status(TxStatus::Dropped);
+                    }
+                    events.sink().on_failed(&TransactionFailed {
+                        tx_id: confirming.id,
+                        hash: Some(confirming.hash),
+                        status: TxStatus::Dropped,
+                        calls: self.retain_failed_calls.then_some(confirming.calls),
+                    });
+                    let cancelled = self.abort_batch(confirming.id);
+                    self.report_cancelled(cancelled, events);
+                }
+                Ok(Err(err)) => {
+                    warn!(
+                        "Failed to fetch receipt for transaction {} ({}): {}",
+                        confirming.id.0,
+                        fmt_felt(&confirming.hash),
+                        err
+                    );
+                    events.sink().on_failed(&TransactionFailed {
+                        tx_id: confirming.id,
+                        hash: Some(confirming.hash),
+                        status: TxStatus::Failed {
+                            error: err.to_string(),
+                        },
+                        calls: self.retain_failed_calls.then_some(confirming.calls),
+                    });
+                    let cancelled = self.fail_tx(confirming.id, err.to_string());
+                    self.report_cancelled(cancelled, events);
+                }
+                Err(err) => {
+                    warn!(
+                        "Confirmation task for transaction {} ({}) failed: {}",
+                        confirming.id.0,
+                        fmt_felt(&confirming.hash),
+                        err
+                    );
+                    events.sink().on_failed(&TransactionFailed {
+                        tx_id: confirming.id,
+                        hash: Some(confirming.hash),
+                        status: TxStatus::Failed {
+                            error: err.to_string(),
+                        },
+                        calls: self.retain_failed_calls.then_some(confirming.calls),
+                    });
+                    let cancelled = self.fail_tx(confirming.id, err.to_string());
+                    self.report_cancelled(cancelled, events);
+                }
+            }
+        }
+
+        self.metrics.active_tasks = self.task_count();
+    }
+
+    /// Record that the transaction `id` could not be sent
+    ///
+    /// Returns the chunks of its batch cancelled by `abort_batch`.
+    #[must_use]
+    pub(crate) fn fail_tx(&mut self, id: TxId, error: String) -> Vec<CancelledChunk> {
+        self.metrics.failed_txs += 1;
+        self.health.record_error(error.clone());
+        if let Some(record) = self.record_mut(id) {
+            record.set_status(TxStatus::Failed { error });
+        }
+        self.abort_batch(id)
+    }
+
+    /// Returns the number of background tasks owned by the connection
+    pub(crate) fn task_count(&self) -> usize {
+        usize::from(self.connecting_task.is_some())
+            + self.pending_txs.len()
+            + self.confirming_txs.len()
+            + self.approvals.task_count()
+            + self.fee_token.task_count()
+            + self.block_times.task_count()
+            + self.queries.task_count()
+            + self.subscriptions.task_count()
+    }
+
+    /// Returns true if the transaction `id` is being sent
+    pub(crate) fn is_sending(&self, id: TxId) -> bool {
+        self.pending_txs.iter().any(|pending| pending.id == id)
+    }
+
+    pub(crate) fn next_tx_id(&mut self) -> TxId {
+        TxId(NEXT_TX_ID.fetch_add(1, Ordering::Relaxed))
     }
 }
 
@@ -68,8 +1013,12 @@ impl StarknetConnection {
 /// This resource provides configuration for connecting to Starknet.
 /// By default, it reads values from environment variables:
 /// - `STARKNET_RPC_URL`: URL of your Starknet RPC provider
-/// - `STARKNET_ACCOUNT_ADDRESS`: Your Starknet account address (as a hex string)
-/// - `STARKNET_PRIVATE_KEY`: Your private key (as a hex string)
+/// - `STARKNET_ACCOUNT_ADDRESS`: Your Starknet account address (as a hex or decimal string)
+/// - `STARKNET_PRIVATE_KEY`: Your private key (as a hex or decimal string)
+/// - `STARKNET_PROXY`: An optional HTTP or HTTPS proxy to reach the RPC provider
+///
+/// Without an explicit `proxy`, the standard `HTTPS_PROXY`, `HTTP_PROXY` and
+/// `NO_PROXY` environment variables are honored.
 ///
 /// # Custom Configuration
 ///
@@ -81,6 +1030,7 @@ impl StarknetConnection {
 ///         rpc_url: "https://starknet-mainnet.infura.io/v3/YOUR_API_KEY".to_string(),
 ///         account_address: "0x123...".to_string(),
 ///         private_key: "0x456...".to_string(),
+///         proxy: Some("http://proxy.example.com:8080".to_string()),
 ///     });
 /// }
 /// ```
@@ -89,6 +1039,8 @@ pub struct DefaultStarknetConfig {
     pub rpc_url: String,
     pub account_address: String,
     pub private_key: String,
+    /// URL of the proxy all requests to the RPC provider go through
+    pub proxy: Option<String>,
 }
 
 impl Default for DefaultStarknetConfig {
@@ -97,6 +1049,7 @@ impl Default for DefaultStarknetConfig {
             rpc_url: std::env::var("STARKNET_RPC_URL").unwrap_or_default(),
             account_address: std::env::var("STARKNET_ACCOUNT_ADDRESS").unwrap_or_default(),
             private_key: std::env::var("STARKNET_PRIVATE_KEY").unwrap_or_default(),
+            proxy: std::env::var("STARKNET_PROXY").ok(),
         }
     }
 }
@@ -112,6 +1065,12 @@ impl Default for DefaultStarknetConfig {
 /// * `config` - The Starknet configuration resource
 /// * `sn` - The Starknet connection resource
 ///
+/// # Returns
+///
+/// * `ConnectOutcome::Started` if a connection attempt was started
+/// * `ConnectOutcome::AlreadyConnecting` if an attempt is already in progress
+/// * `ConnectOutcome::AlreadyConnected` if the connection is already established
+///
 /// # Example
 ///
 /// ```no_run
@@ -130,15 +1089,40 @@ pub fn init_starknet_connection(
     runtime: Res<TokioRuntime>,
     config: Res<DefaultStarknetConfig>,
     mut sn: ResMut<StarknetConnection>,
-) {
-    if sn.connecting_task.is_none() && sn.account.is_none() {
-        let config_clone = config.clone();
-        let handle = runtime
-            .runtime
-            .spawn(async move { connect_to_starknet(config_clone).await });
-        sn.connecting_task = Some(handle);
-        info!("Connecting to Starknet...");
-    }
+) -> ConnectOutcome {
+    sn.connect(&runtime, &config)
+}
+
+/// Cancel the connection attempt in progress
+///
+/// The connecting task is aborted, including a retry waiting for its delay,
+/// and no further retries are made. The connection is left as it was before
+/// the attempt, so a new one can be started right away, for example with a
+/// different configuration.
+///
+/// # Arguments
+///
+/// * `sn` - The Starknet connection resource
+///
+/// # Returns
+///
+/// True if an attempt was cancelled, false if none was in progress
+///
+/// # Example
+///
+/// ```no_run
+/// fn switch_network(
+///     runtime: Res<TokioRuntime>,
+///     mut config: ResMut<DefaultStarknetConfig>,
+///     mut sn: ResMut<StarknetConnection>,
+/// ) {
+///     sn.cancel_connecting();
+///     config.rpc_url = "https://starknet-sepolia.public.blastapi.io".to_string();
+///     sn.connect(&runtime, &config);
+/// }
+/// ```
+pub fn cancel_connecting(mut sn: ResMut<StarknetConnection>) -> bool {
+    sn.cancel_connecting()
 }
 
 /// Execute a Starknet transaction
@@ -155,8 +1139,12 @@ pub fn init_starknet_connection(
 ///
 /// # Returns
 ///
-/// * `true` if the transaction was queued successfully
-/// * `false` if there's no active Starknet connection
+/// * `SubmitOutcome::Queued` with the transaction id if it was queued successfully
+/// * `SubmitOutcome::NotConnected` if there's no active Starknet connection
+/// * `SubmitOutcome::SpendLimitReached` if the session spend limit has been reached
+/// * `SubmitOutcome::InvalidCall` if a call is malformed, see `validate_calls`
+/// * `SubmitOutcome::CalldataTypeMismatch` if a call doesn't match the strict ABI
+///   binding of its contract, see `register_abi`
 ///
 /// # Example
 ///
@@ -179,10 +1167,10 @@ pub fn init_starknet_connection(
 ///         },
 ///     ];
 ///
-///     if execute_transaction(runtime, sn, calls) {
-///         println!("Transaction submitted!");
-///     } else {
-///         println!("Not connected to Starknet!");
+///     match execute_transaction(runtime, sn, calls) {
+///         SubmitOutcome::Queued(_) => println!("Transaction submitted!"),
+///         SubmitOutcome::NotConnected => println!("Not connected to Starknet!"),
+///         outcome => println!("Transaction rejected: {outcome:?}"),
 ///     }
 /// }
 /// ```
@@ -190,25 +1178,24 @@ pub fn execute_transaction(
     runtime: Res<TokioRuntime>,
     mut sn: ResMut<StarknetConnection>,
     calls: Vec<Call>,
-) -> bool {
-    if let Some(account) = sn.account.clone() {
-        let task = runtime.runtime.spawn(async move {
-            // Create the transaction inside the async block where we own the account
-            let tx = account.execute_v3(calls);
-            tx.send().await
-        });
-        sn.pending_txs.push_back(task);
-        true
-    } else {
-        false
-    }
+) -> SubmitOutcome {
+    sn.execute(&runtime, calls)
 }
 
 /// System that checks the status of Starknet tasks
 ///
 /// This system:
 /// 1. Checks if a connection task has completed and updates the connection state
-/// 2. Checks pending transactions and logs their completion
+/// 2. Checks pending transactions, emits `TransactionSubmitted` for those sent and
+///    starts watching for their receipts
+/// 3. Records the fee and outcome of transactions whose receipt has arrived, and
+///    emits `TransactionConfirmed` or `TransactionFailed`
+/// 4. Emits `TransactionDropped` for transactions the provider has lost track of
+/// 5. Retries failed connection attempts according to the `ReconnectPolicy`
+/// 6. Runs the systems registered with `on_connected` once the connection is ready
+///
+/// The transaction events are replaced by calls to the `CustomTransactionSink`
+/// if this resource is inserted.
 ///
 /// It is automatically registered by the `BevyDojoPlugin` and should run every frame.
 ///
@@ -216,26 +1203,232 @@ pub fn execute_transaction(
 ///
 /// * `runtime` - The Tokio runtime resource
 /// * `sn` - The Starknet connection resource
-pub fn check_sn_task(runtime: Res<TokioRuntime>, mut sn: ResMut<StarknetConnection>) {
-    // Check connection task
-    if let Some(task) = &mut sn.connecting_task {
-        if let Ok(account) = runtime.runtime.block_on(async { task.await }) {
-            info!("Connected to Starknet!");
-            sn.account = Some(account);
-            sn.connecting_task = None;
-        }
+pub fn check_sn_task(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+    mut events: TaskEvents,
+) {
+    sn.poll_tasks(&runtime, &mut events);
+}
+
+/// End of the time budget for processing completed tasks in the current frame
+///
+/// Set by `start_poll_budget` from the `poll_budget` of the main connection
+/// before the `StarknetPollSet` runs, and shared by all its pollers.
+#[derive(Resource, Default)]
+pub struct PollDeadline(pub(crate) Option<Instant>);
+
+impl PollDeadline {
+    /// Returns true if a poller that processed `processed` tasks may process another
+    pub(crate) fn allows(&self, processed: usize) -> bool {
+        processed == 0 || self.0.is_none_or(|deadline| Instant::now() < deadline)
     }
+}
 
-    // Check pending transactions
-    if !sn.pending_txs.is_empty() && sn.account.is_some() {
-        if let Some(task) = sn.pending_txs.pop_front() {
-            if let Ok(Ok(result)) = runtime.runtime.block_on(async { task.await }) {
-                info!("Transaction completed: {:#x}", result.transaction_hash);
+/// System that starts the time budget of the frame's pollers
+pub(crate) fn start_poll_budget(sn: Res<StarknetConnection>, mut deadline: ResMut<PollDeadline>) {
+    deadline.0 = sn.poll_budget.map(|budget| Instant::now() + budget);
+}
+
+/// Sign and send the v3 invoke of `calls` from `sender`, with `tip`
+///
+/// The v3 invokes of starknet-rs 0.15 can't carry a tip, so the transaction
+/// is hashed and signed here instead.
+async fn send_invoke_v3<A>(
+    sender: &A,
+    calls: &[Call],
+    nonce: Felt,
+    bounds: FeeBounds,
+    tip: u64,
+) -> Result<InvokeTransactionResult, AccountError<SignError<LocalWalletSignError>>>
+where
+    A: ConnectedAccount<Provider = AnyProvider> + SignTransactionHash + Sync,
+{
+    let calldata = sender.encode_calls(calls);
+    let resource_bounds = bounds.resource_bounds();
+    let hash = invoke_v3_hash(
+        sender.chain_id(),
+        sender.address(),
+        nonce,
+        &calldata,
+        &resource_bounds,
+        tip,
+    );
+    let signature = sender
+        .sign_transaction_hash(hash)
+        .await
+        .map_err(AccountError::Signing)?;
+    sender
+        .provider()
+        .add_invoke_transaction(BroadcastedInvokeTransactionV3 {
+            sender_address: sender.address(),
+            calldata,
+            signature,
+            nonce,
+            resource_bounds,
+            tip,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            is_query: false,
+        })
+        .await
+        .map_err(AccountError::Provider)
+}
+
+/// Poll the provider until the transaction is accepted and return its receipt
+///
+/// The delay between status lookups grows while the status stays the same and
+/// resets whenever it changes, as described by `ConfirmationPolling`. If the
+/// provider doesn't know the transaction `dropped_after` it was submitted, it is
+/// reported as dropped.
+///
+/// Transient errors (rate limiting, transport failures) are retried with the
+/// same backoff until the provider has failed to answer for `dropped_after`.
+/// Any other provider error is returned right away.
+async fn wait_for_confirmation(
+    provider: &AnyProvider,
+    hash: Felt,
+    polling: ConfirmationPolling,
+    limiter: &RequestLimiter,
+) -> Result<Confirmation, ProviderError> {
+    let submitted_at = Instant::now();
+    let mut last_answer = submitted_at;
+    let mut interval = Duration::ZERO;
+    let mut last_status = None;
+    loop {
+        let permit = limiter.acquire().await;
+        let status = match provider.get_transaction_status(hash).await {
+            Ok(status) => Some(status),
+            Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => None,
+            Err(err) if is_transient(&err) && last_answer.elapsed() < polling.dropped_after => {
+                drop(permit);
+                interval = polling.next_interval(interval);
+                tokio::time::sleep(polling.jittered_interval(interval)).await;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if matches!(
+            status,
+            Some(TransactionStatus::AcceptedOnL2(_) | TransactionStatus::AcceptedOnL1(_))
+        ) {
+            match provider.get_transaction_receipt(hash).await {
+                Ok(receipt) => return Ok(Confirmation::Accepted(Box::new(receipt))),
+                Err(err) if is_transient(&err) && last_answer.elapsed() < polling.dropped_after => {
+                }
+                Err(err) => return Err(err),
             }
+        } else {
+            last_answer = Instant::now();
+        }
+        if status.is_none() && submitted_at.elapsed() >= polling.dropped_after {
+            return Ok(Confirmation::Dropped);
+        }
+        drop(permit);
+
+        interval = if status == last_status {
+            polling.next_interval(interval)
+        } else {
+            polling.min_interval
+        };
+        last_status = status;
+        tokio::time::sleep(polling.jittered_interval(interval)).await;
+    }
+}
+
+/// Whether `err` may go away by itself, so the request is worth retrying
+fn is_transient(err: &ProviderError) -> bool {
+    matches!(err, ProviderError::RateLimited | ProviderError::Other(_))
+}
+
+/// Returns the fee paid by the transaction of `receipt`
+fn actual_fee(receipt: &TransactionReceipt) -> &FeePayment {
+    match receipt {
+        TransactionReceipt::Invoke(receipt) => &receipt.actual_fee,
+        TransactionReceipt::L1Handler(receipt) => &receipt.actual_fee,
+        TransactionReceipt::Declare(receipt) => &receipt.actual_fee,
+        TransactionReceipt::Deploy(receipt) => &receipt.actual_fee,
+        TransactionReceipt::DeployAccount(receipt) => &receipt.actual_fee,
+    }
+}
+
+/// Convert a felt to a `u128`, returning `None` if it doesn't fit
+pub(crate) fn felt_to_u128(felt: &Felt) -> Option<u128> {
+    let bytes = felt.to_bytes_be();
+    let (high, low) = bytes.split_at(16);
+    if high.iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(u128::from_be_bytes(low.try_into().ok()?))
+}
+
+/// Error returned by `try_connect_to_starknet`
+#[derive(Debug)]
+pub enum ConnectError {
+    InvalidRpcUrl,
+    InvalidAccountAddress,
+    InvalidPrivateKey,
+    InvalidProxy,
+    HttpClient(reqwest::Error),
+    Provider(ProviderError),
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectError::InvalidRpcUrl => write!(f, "invalid RPC URL"),
+            ConnectError::InvalidAccountAddress => write!(f, "invalid account address"),
+            ConnectError::InvalidPrivateKey => write!(f, "invalid private key"),
+            ConnectError::InvalidProxy => write!(f, "invalid proxy URL"),
+            ConnectError::HttpClient(err) => write!(f, "failed to create HTTP client: {err}"),
+            ConnectError::Provider(err) => write!(f, "{err}"),
         }
     }
 }
 
+impl std::error::Error for ConnectError {}
+
+/// Result of a successful connection attempt
+pub(crate) enum Connected {
+    /// The account, with the key it signs with, kept to authorize sessions
+    Account(
+        Arc<SingleOwnerAccount<AnyProvider, LocalWallet>>,
+        SigningKey,
+    ),
+    ReadOnly(Arc<ReadOnlyProvider>),
+}
+
+/// Spawn a connection attempt starting after `delay`, read-only if `read_only` is set
+///
+/// The attempt waits for a request slot of `limiter` once the delay is over.
+pub(crate) fn spawn_connect(
+    runtime: &TokioRuntime,
+    config: DefaultStarknetConfig,
+    delay: Duration,
+    read_only: bool,
+    limiter: RequestLimiter,
+) -> JoinHandle<Result<Connected, ConnectError>> {
+    runtime.runtime.spawn(async move {
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        let _permit = limiter.acquire().await;
+        if read_only {
+            try_connect_readonly(config).await.map(Connected::ReadOnly)
+        } else {
+            let owner_key = match parse_felt(&config.private_key) {
+                Some(key) => SigningKey::from_secret_scalar(key),
+                None => return Err(ConnectError::InvalidPrivateKey),
+            };
+            try_connect_to_starknet(config)
+                .await
+                .map(|account| Connected::Account(account, owner_key))
+        }
+    })
+}
+
 /// Connect to Starknet using the provided configuration
 ///
 /// This is an async function that establishes a connection to Starknet.
@@ -248,23 +1441,587 @@ pub fn check_sn_task(runtime: Res<TokioRuntime>, mut sn: ResMut<StarknetConnecti
 /// # Returns
 ///
 /// An Arc-wrapped SingleOwnerAccount that can be used to interact with Starknet
+///
+/// # Panics
+///
+/// If the configuration is invalid or the provider can't be reached. Use
+/// `try_connect_to_starknet` to handle these errors.
 pub async fn connect_to_starknet(
     config: DefaultStarknetConfig,
 ) -> Arc<SingleOwnerAccount<AnyProvider, LocalWallet>> {
-    let provider = AnyProvider::JsonRpcHttp(JsonRpcClient::new(HttpTransport::new(
-        Url::parse(&config.rpc_url).expect("Invalid RPC URL"),
-    )));
-    let account_addr = Felt::from_str(&config.account_address).expect("Invalid account address");
-    let chain_id = provider.chain_id().await.unwrap();
-    let signer = LocalWallet::from(SigningKey::from_secret_scalar(
-        Felt::from_str(&config.private_key).expect("Invalid private key"),
-    ));
-
-    Arc::new(SingleOwnerAccount::new(
+    match try_connect_to_starknet(config).await {
+        Ok(account) => account,
+        Err(err) => panic!("Failed to connect to Starknet: {err}"),
+    }
+}
+
+/// Create the JSON-RPC provider of `config`, going through its proxy if any
+pub(crate) fn http_provider(config: &DefaultStarknetConfig) -> Result<AnyProvider, ConnectError> {
+    let url = Url::parse(&config.rpc_url).map_err(|_| ConnectError::InvalidRpcUrl)?;
+    let client = http_client(config.proxy.as_deref())?;
+    Ok(AnyProvider::JsonRpcHttp(JsonRpcClient::new(
+        HttpTransport::new_with_client(url, client),
+    )))
+}
+
+/// Create an HTTP client going through `proxy` if any
+pub(crate) fn http_client(proxy: Option<&str>) -> Result<reqwest::Client, ConnectError> {
+    let mut client = reqwest::Client::builder();
+    if let Some(proxy) = proxy {
+        client = client.proxy(reqwest::Proxy::all(proxy).map_err(|_| ConnectError::InvalidProxy)?);
+    }
+    client.build().map_err(ConnectError::HttpClient)
+}
+
+/// Connect to Starknet using the provided configuration, returning errors
+///
+/// This is the fallible form of `connect_to_starknet`.
+pub async fn try_connect_to_starknet(
+    config: DefaultStarknetConfig,
+) -> Result<Arc<SingleOwnerAccount<AnyProvider, LocalWallet>>, ConnectError> {
+    let provider = http_provider(&config)?;
+    let account_addr =
+        parse_felt(&config.account_address).ok_or(ConnectError::InvalidAccountAddress)?;
+    let private_key = parse_felt(&config.private_key).ok_or(ConnectError::InvalidPrivateKey)?;
+    let chain_id = provider.chain_id().await.map_err(ConnectError::Provider)?;
+    let signer = LocalWallet::from(SigningKey::from_secret_scalar(private_key));
+
+    Ok(Arc::new(SingleOwnerAccount::new(
         provider,
         signer,
         account_addr,
         chain_id,
         ExecutionEncoding::New,
-    ))
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::query::{AccountDeployedStatus, query_account_deployed};
+    use serde_json::json;
+    use starknet::core::{crypto::Signature, types::FeeEstimate};
+    use std::sync::atomic::AtomicUsize;
+
+    fn execute(app: &mut App, calls: Vec<Call>) -> SubmitOutcome {
+        with_connection(app, |runtime, sn| sn.execute(runtime, calls))
+    }
+
+    #[test]
+    fn next_interval_doubles_within_bounds() {
+        let polling = ConfirmationPolling::default();
+        assert_eq!(
+            polling.next_interval(Duration::ZERO),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            polling.next_interval(Duration::from_secs(2)),
+            Duration::from_secs(4)
+        );
+        assert_eq!(
+            polling.next_interval(Duration::from_secs(8)),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn polling_jitter_stays_within_bounds() {
+        let polling = ConfirmationPolling {
+            min_interval: Duration::from_millis(100),
+            max_interval: Duration::from_secs(2),
+            ..Default::default()
+        };
+        let interval = Duration::from_secs(1);
+        assert_eq!(polling.jittered_interval(interval), interval);
+
+        for jitter in [Jitter::Full, Jitter::Decorrelated] {
+            let polling = ConfirmationPolling { jitter, ..polling };
+            let waits = (0..50)
+                .map(|_| polling.jittered_interval(interval))
+                .collect::<Vec<_>>();
+            assert!(waits.iter().any(|wait| *wait != waits[0]), "{jitter:?}");
+            let high = match jitter {
+                Jitter::Full => interval,
+                _ => polling.max_interval,
+            };
+            for wait in waits {
+                assert!(wait >= polling.min_interval && wait <= high, "{wait:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn polling_interval_grows_while_pending() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on(
+            "starknet_getTransactionStatus",
+            json!({ "finality_status": "RECEIVED" }),
+        );
+        let mut app = connected_app(&mock);
+        with_connection(&mut app, |_, sn| {
+            sn.set_confirmation_polling(ConfirmationPolling {
+                min_interval: Duration::from_millis(20),
+                max_interval: Duration::from_millis(160),
+                dropped_after: Duration::from_secs(60),
+                ..Default::default()
+            })
+        });
+
+        assert!(execute(&mut app, vec![call(0)]).is_queued());
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_getTransactionStatus") >= 6
+        }));
+
+        let times = mock.request_times("starknet_getTransactionStatus");
+        let gaps = times
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect::<Vec<_>>();
+        for (gap, expected) in gaps.iter().zip([20, 40, 80, 160, 160]) {
+            assert!(*gap >= Duration::from_millis(expected), "{gaps:?}");
+        }
+        assert_eq!(connection(&app).pending_tx_count(), 1);
+    }
+
+    #[test]
+    fn unknown_transaction_is_dropped_after_timeout() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on_error(
+            "starknet_getTransactionStatus",
+            RpcError::new(TRANSACTION_HASH_NOT_FOUND, "Transaction hash not found"),
+        );
+        let mut app = connected_app(&mock);
+        collect::<TransactionDropped>(&mut app);
+        collect::<TransactionFailed>(&mut app);
+        with_connection(&mut app, |_, sn| {
+            sn.set_confirmation_polling(ConfirmationPolling {
+                min_interval: Duration::from_millis(10),
+                max_interval: Duration::from_millis(20),
+                dropped_after: Duration::from_millis(200),
+                ..Default::default()
+            })
+        });
+
+        let SubmitOutcome::Queued(tx_id) = execute(&mut app, vec![call(0)]) else {
+            panic!("transaction not queued");
+        };
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionDropped>(app).is_empty()
+        }));
+
+        let dropped = collected::<TransactionDropped>(&app);
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].tx_id, tx_id);
+        assert_eq!(dropped[0].hash, Felt::from(0x100u64));
+        assert_eq!(
+            collected::<TransactionFailed>(&app)[0].status,
+            TxStatus::Dropped
+        );
+        assert_eq!(connection(&app).metrics().dropped_txs, 1);
+        assert_eq!(mock.count("starknet_getTransactionReceipt"), 0);
+    }
+
+    #[test]
+    fn spend_limit_rejects_once_fees_reach_it() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        with_connection(&mut app, |_, sn| {
+            sn.set_spend_limit(Some(SessionSpendLimit {
+                max_total_fee: 1500,
+            }))
+        });
+
+        assert!(execute(&mut app, vec![call(0)]).is_queued());
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 1
+        }));
+        // 1000 spent, still under the limit
+        assert!(!connection(&app).spend_limit_reached());
+        assert!(execute(&mut app, vec![call(1)]).is_queued());
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 2
+        }));
+
+        assert_eq!(connection(&app).metrics().total_fee_spent, 2000);
+        assert_eq!(
+            execute(&mut app, vec![call(2)]),
+            SubmitOutcome::SpendLimitReached
+        );
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 2);
+    }
+
+    #[test]
+    fn receipt_error_fails_transaction() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on_error(
+            "starknet_getTransactionReceipt",
+            RpcError::new(TRANSACTION_HASH_NOT_FOUND, "Transaction hash not found"),
+        );
+        let mut app = connected_app(&mock);
+        collect::<TransactionFailed>(&mut app);
+
+        let SubmitOutcome::Queued(tx_id) = execute(&mut app, vec![call(0)]) else {
+            panic!("transaction not queued");
+        };
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionFailed>(app).is_empty()
+        }));
+
+        let failed = collected::<TransactionFailed>(&app);
+        assert_eq!(failed[0].tx_id, tx_id);
+        assert_eq!(failed[0].hash, Some(Felt::from(0x100u64)));
+        assert!(matches!(failed[0].status, TxStatus::Failed { .. }));
+        assert!(connection(&app).pending_txs().is_empty());
+        assert_eq!(connection(&app).metrics().failed_txs, 1);
+    }
+
+    #[test]
+    fn transient_status_errors_are_retried() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let status_lookups = lookups.clone();
+        mock.on_fn(
+            "starknet_getTransactionStatus",
+            move |_| match status_lookups.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(RpcError::new(-32603, "Internal error")),
+                _ => Ok(json!({
+                    "finality_status": "ACCEPTED_ON_L2",
+                    "execution_status": "SUCCEEDED"
+                })),
+            },
+        );
+        let mut app = connected_app(&mock);
+        collect::<TransactionFailed>(&mut app);
+        with_connection(&mut app, |_, sn| {
+            sn.set_confirmation_polling(ConfirmationPolling {
+                min_interval: Duration::from_millis(10),
+                max_interval: Duration::from_millis(20),
+                ..Default::default()
+            })
+        });
+
+        assert!(execute(&mut app, vec![call(0)]).is_queued());
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 1
+        }));
+
+        assert_eq!(mock.count("starknet_getTransactionStatus"), 3);
+        assert!(collected::<TransactionFailed>(&app).is_empty());
+    }
+
+    #[test]
+    fn transient_status_errors_fail_after_dropped_after() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on_error(
+            "starknet_getTransactionStatus",
+            RpcError::new(-32603, "Internal error"),
+        );
+        let mut app = connected_app(&mock);
+        collect::<TransactionFailed>(&mut app);
+        with_connection(&mut app, |_, sn| {
+            sn.set_confirmation_polling(ConfirmationPolling {
+                min_interval: Duration::from_millis(10),
+                max_interval: Duration::from_millis(20),
+                dropped_after: Duration::from_millis(200),
+                ..Default::default()
+            })
+        });
+
+        assert!(execute(&mut app, vec![call(0)]).is_queued());
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionFailed>(app).is_empty()
+        }));
+
+        let failed = collected::<TransactionFailed>(&app);
+        assert!(matches!(failed[0].status, TxStatus::Failed { .. }));
+        assert!(mock.count("starknet_getTransactionStatus") > 1);
+        assert_eq!(connection(&app).metrics().dropped_txs, 0);
+    }
+
+    #[test]
+    fn panicked_confirmation_task_fails_transaction() {
+        let mock = MockRpc::start();
+        let mut app = connected_app(&mock);
+        collect::<TransactionFailed>(&mut app);
+        with_connection(&mut app, |runtime, sn| {
+            let task = runtime
+                .runtime
+                .spawn(async { panic!("confirmation panicked") });
+            sn.confirming_txs.push(ConfirmingTx {
+                id: TxId(7),
+                hash: Felt::from(0x100u64),
+                calls: vec![call(0)],
+                nonce: Felt::ZERO,
+                bounds: FeeBounds::with_margin(
+                    &serde_json::from_value(fee_estimate()).unwrap(),
+                    1.0,
+                ),
+                task,
+            });
+        });
+
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionFailed>(app).is_empty()
+        }));
+
+        let failed = collected::<TransactionFailed>(&app);
+        assert_eq!(failed[0].tx_id, TxId(7));
+        assert!(matches!(failed[0].status, TxStatus::Failed { .. }));
+        assert!(connection(&app).pending_txs().is_empty());
+        assert_eq!(connection(&app).metrics().failed_txs, 1);
+    }
+
+    #[test]
+    fn transactions_are_signed_with_the_tip() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        with_connection(&mut app, |_, sn| sn.set_tip(5));
+
+        assert!(execute(&mut app, vec![call(0)]).is_queued());
+        assert!(update_until(&mut app, |_| {
+            mock.count("starknet_addInvokeTransaction") == 1
+        }));
+
+        let request = &mock.requests("starknet_addInvokeTransaction")[0];
+        let sent: BroadcastedInvokeTransactionV3 =
+            serde_json::from_value(param(request, 0, "invoke_transaction")).unwrap();
+        assert_eq!(sent.tip, 5);
+        let hash = invoke_v3_hash(
+            Felt::from_hex_unchecked("0x534e5f5345504f4c4941"),
+            ACCOUNT_ADDRESS,
+            sent.nonce,
+            &sent.calldata,
+            &sent.resource_bounds,
+            5,
+        );
+        let key = SigningKey::from_secret_scalar(Felt::ONE).verifying_key();
+        let signature = Signature {
+            r: sent.signature[0],
+            s: sent.signature[1],
+        };
+        assert!(key.verify(&hash, &signature).unwrap());
+    }
+
+    #[test]
+    fn poll_deadline_allows_one_task_past_the_deadline() {
+        assert!(PollDeadline(None).allows(100));
+        let passed = PollDeadline(Some(Instant::now()));
+        assert!(passed.allows(0));
+        assert!(!passed.allows(1));
+    }
+
+    #[test]
+    fn tiny_budget_processes_one_transaction_per_frame() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        collect::<TransactionSubmitted>(&mut app);
+        with_connection(&mut app, |_, sn| {
+            sn.set_poll_budget(Some(Duration::from_nanos(1)))
+        });
+        for n in 0..3 {
+            assert!(matches!(
+                execute(&mut app, vec![call(n)]),
+                SubmitOutcome::Queued(_)
+            ));
+        }
+
+        let started_at = Instant::now();
+        while !connection(&app)
+            .pending_txs
+            .iter()
+            .all(|pending| pending.task.is_finished())
+        {
+            assert!(started_at.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        for submitted in 1..=3 {
+            app.update();
+            assert_eq!(collected::<TransactionSubmitted>(&app).len(), submitted);
+        }
+    }
+
+    #[test]
+    fn task_count_returns_to_baseline() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on("starknet_getClassHashAt", json!("0xc1a55"));
+        let mut app = connected_app(&mock);
+        collect::<AccountDeployedStatus>(&mut app);
+        app.update();
+        let baseline = connection(&app).task_count();
+
+        assert!(execute(&mut app, vec![call(0)]).is_queued());
+        run(&mut app, query_account_deployed).unwrap();
+        assert!(connection(&app).task_count() > baseline);
+
+        assert!(update_until(&mut app, |app| {
+            connection(app).metrics().confirmed_txs == 1
+                && !collected::<AccountDeployedStatus>(app).is_empty()
+        }));
+        assert!(update_until(&mut app, |app| {
+            connection(app).task_count() == baseline
+        }));
+        app.update();
+        assert_eq!(connection(&app).metrics().active_tasks, baseline);
+    }
+
+    #[test]
+    fn connect_outcome_depends_on_the_connection_state() {
+        let mock = MockRpc::start();
+        mock.delay("starknet_chainId", Duration::from_millis(200));
+        let mut app = test_app();
+        app.insert_resource(mock.config());
+
+        assert_eq!(
+            run(&mut app, init_starknet_connection),
+            ConnectOutcome::Started
+        );
+        assert_eq!(
+            run(&mut app, init_starknet_connection),
+            ConnectOutcome::AlreadyConnecting
+        );
+        assert!(update_until(&mut app, |app| connection(app).is_connected()));
+        assert_eq!(
+            run(&mut app, init_starknet_connection),
+            ConnectOutcome::AlreadyConnected
+        );
+        assert_eq!(mock.count("starknet_chainId"), 1);
+    }
+
+    #[test]
+    fn slow_connection_can_be_cancelled() {
+        let mock = MockRpc::start();
+        mock.delay("starknet_chainId", Duration::from_millis(300));
+        let mut app = test_app();
+        app.insert_resource(mock.config());
+
+        assert_eq!(
+            run(&mut app, init_starknet_connection),
+            ConnectOutcome::Started
+        );
+        app.update();
+        assert!(connection(&app).is_connecting());
+        assert!(run(&mut app, cancel_connecting));
+        assert!(!connection(&app).is_connecting());
+        assert!(!run(&mut app, cancel_connecting));
+
+        // The aborted attempt never completes
+        let started_at = Instant::now();
+        while started_at.elapsed() < Duration::from_millis(500) {
+            app.update();
+            assert!(!connection(&app).is_connected());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!connection(&app).is_connecting());
+
+        // A new attempt can be started afterwards
+        mock.delay("starknet_chainId", Duration::ZERO);
+        assert_eq!(
+            run(&mut app, init_starknet_connection),
+            ConnectOutcome::Started
+        );
+        assert!(update_until(&mut app, |app| connection(app).is_connected()));
+    }
+
+    #[test]
+    fn wait_until_connected_resolves_once_connected() {
+        let mock = MockRpc::start();
+        mock.delay("starknet_chainId", Duration::from_millis(100));
+        let mut app = test_app();
+        app.insert_resource(mock.config());
+        let connected = connection(&app).wait_until_connected();
+        let waiting = app
+            .world()
+            .resource::<TokioRuntime>()
+            .runtime
+            .spawn(connected);
+
+        run(&mut app, init_starknet_connection);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(!connection(&app).is_connected());
+        assert!(!waiting.is_finished());
+
+        assert!(update_until(&mut app, |_| waiting.is_finished()));
+        assert!(connection(&app).is_connected());
+
+        // Once connected, new futures resolve right away
+        let connected = connection(&app).wait_until_connected();
+        let runtime = app.world().resource::<TokioRuntime>();
+        runtime
+            .runtime
+            .block_on(async { tokio::time::timeout(Duration::from_secs(1), connected).await })
+            .unwrap();
+    }
+
+    #[test]
+    fn zero_selector_is_rejected_before_sending() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        let mut app = connected_app(&mock);
+        let mut malformed = call(2);
+        malformed.selector = Felt::ZERO;
+
+        let outcome = execute(&mut app, vec![call(1), malformed]);
+        assert_eq!(
+            outcome,
+            SubmitOutcome::InvalidCall {
+                index: 1,
+                reason: InvalidCallReason::ZeroSelector,
+            }
+        );
+        for _ in 0..10 {
+            app.update();
+        }
+        let sn = connection(&app);
+        assert_eq!(sn.pending_tx_count(), 0);
+        assert!(sn.recent_txs().is_empty());
+        assert_eq!(sn.metrics().submitted_txs, 0);
+        assert_eq!(mock.count("starknet_estimateFee"), 0);
+        assert_eq!(mock.count("starknet_addInvokeTransaction"), 0);
+    }
+
+    #[test]
+    fn submitted_event_carries_bounds_and_nonce() {
+        let mock = MockRpc::start();
+        mock.accept_transactions(1000);
+        mock.on("starknet_getNonce", json!("0x9"));
+        mock.on(
+            "starknet_getTransactionStatus",
+            json!({ "finality_status": "RECEIVED" }),
+        );
+        let mut app = connected_app(&mock);
+        collect::<TransactionSubmitted>(&mut app);
+
+        let SubmitOutcome::Queued(tx_id) = execute(&mut app, vec![call(1)]) else {
+            panic!("transaction not queued");
+        };
+        assert!(update_until(&mut app, |app| {
+            !collected::<TransactionSubmitted>(app).is_empty()
+        }));
+
+        let estimate: FeeEstimate = serde_json::from_value(fee_estimate()).unwrap();
+        let expected = FeeBounds::with_margin(&estimate, ESTIMATE_MARGIN).resource_bounds();
+        let submitted = &collected::<TransactionSubmitted>(&app)[0];
+        assert_eq!(submitted.tx_id, tx_id);
+        assert_eq!(submitted.nonce, Felt::from(9u8));
+        assert_eq!(submitted.bounds, expected);
+        assert_eq!(expected.l2_gas.max_amount, 1500);
+
+        let pending = connection(&app).pending_txs();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].tx_id, tx_id);
+        assert_eq!(pending[0].nonce, Some(Felt::from(9u8)));
+        assert_eq!(pending[0].bounds, Some(expected));
+    }
 }
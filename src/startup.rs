@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use crate::starknet::{DefaultStarknetConfig, StarknetConnection};
+use crate::tokio::TokioRuntime;
+
+/// Connect automatically once the app has started
+///
+/// When this resource exists, the `auto_connect` system connects with the
+/// `DefaultStarknetConfig` in `PostStartup`, within the `StarknetConnectSet`.
+/// Every `Startup` system has run by then, so a configuration inserted by a
+/// `Startup` system is used instead of the one read from environment
+/// variables. The resource itself may also be inserted by a `Startup` system.
+///
+/// # Example
+///
+/// ```no_run
+/// fn setup(mut commands: Commands) {
+///     commands.insert_resource(DefaultStarknetConfig {
+///         rpc_url: "http://localhost:5050".to_string(),
+///         account_address: "0x123...".to_string(),
+///         private_key: "0x456...".to_string(),
+///         proxy: None,
+///     });
+/// }
+///
+/// fn main() {
+///     App::new()
+///         .add_plugins(DefaultPlugins)
+///         .add_plugins(BevyDojoPlugin)
+///         .insert_resource(AutoConnect::Account)
+///         .add_systems(Startup, setup)
+///         .run();
+/// }
+/// ```
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoConnect {
+    /// Connect with the account of the configuration, like `init_starknet_connection`
+    Account,
+    /// Connect without an account, like `connect_readonly`
+    ReadOnly,
+}
+
+/// Returns the names of the fields `config` is missing to connect
+fn missing_fields(config: &DefaultStarknetConfig, read_only: bool) -> Vec<&'static str> {
+    let mut missing = Vec::new();
+    if config.rpc_url.trim().is_empty() {
+        missing.push("rpc_url");
+    }
+    if !read_only {
+        if config.account_address.trim().is_empty() {
+            missing.push("account_address");
+        }
+        if config.private_key.trim().is_empty() {
+            missing.push("private_key");
+        }
+    }
+    missing
+}
+
+/// System that connects to Starknet at startup if the `AutoConnect` resource exists
+///
+/// It is automatically registered by the `BevyDojoPlugin` in `PostStartup`.
+/// If the configuration is missing fields needed to connect, an error naming
+/// them is logged and no connection is attempted.
+pub fn auto_connect(
+    auto_connect: Option<Res<AutoConnect>>,
+    runtime: Res<TokioRuntime>,
+    config: Res<DefaultStarknetConfig>,
+    mut sn: ResMut<StarknetConnection>,
+) {
+    let Some(auto_connect) = auto_connect else {
+        return;
+    };
+    let read_only = *auto_connect == AutoConnect::ReadOnly;
+    let missing = missing_fields(&config, read_only);
+    if !missing.is_empty() {
+        error!(
+            "Not connecting to Starknet: DefaultStarknetConfig has no {}. Insert a \
+             DefaultStarknetConfig in a Startup system or set the STARKNET_* environment variables",
+            missing.join(", ")
+        );
+        return;
+    }
+    if read_only {
+        sn.connect_readonly(&runtime, &config);
+    } else {
+        sn.connect(&runtime, &config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    #[test]
+    fn config_inserted_in_startup_is_used() {
+        let mock = MockRpc::start();
+        let mut app = test_app();
+        let config = mock.config();
+        app.insert_resource(AutoConnect::Account).add_systems(
+            Startup,
+            move |mut commands: Commands| {
+                commands.insert_resource(config.clone());
+            },
+        );
+
+        assert!(update_until(&mut app, |app| connection(app).is_connected()));
+        assert_eq!(mock.count("starknet_chainId"), 1);
+        assert_eq!(
+            app.world().resource::<DefaultStarknetConfig>().rpc_url,
+            mock.url()
+        );
+    }
+
+    #[test]
+    fn incomplete_config_does_not_connect() {
+        let mut app = test_app();
+        app.insert_resource(AutoConnect::Account)
+            .insert_resource(DefaultStarknetConfig {
+                rpc_url: "http://127.0.0.1:1".to_string(),
+                account_address: String::new(),
+                private_key: String::new(),
+                proxy: None,
+            });
+        app.update();
+        assert!(!connection(&app).is_connecting());
+
+        assert_eq!(
+            missing_fields(app.world().resource::<DefaultStarknetConfig>(), false),
+            ["account_address", "private_key"]
+        );
+        assert!(missing_fields(app.world().resource::<DefaultStarknetConfig>(), true).is_empty());
+    }
+}
@@ -0,0 +1,555 @@
+use bevy::prelude::*;
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use starknet::{
+    accounts::{Account, ConnectedAccount},
+    core::types::{
+        BlockId, Call, EventFilter, Felt, InvokeTransaction, StarknetError, Transaction,
+    },
+    macros::selector,
+    providers::{AnyProvider, Provider, ProviderError},
+};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::display::fmt_felt;
+use crate::faucet::STRK_TOKEN_ADDRESS;
+use crate::limit::RequestLimiter;
+use crate::signature::DojoAccount;
+use crate::starknet::{StarknetConnection, felt_to_u128};
+use crate::tokio::TokioRuntime;
+
+/// Interval between two polls of a subscription
+const SUBSCRIPTION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of events requested per `get_events` page
+const EVENTS_CHUNK_SIZE: u64 = 100;
+
+/// Number of hashes of locally submitted transactions remembered to filter them out
+///
+/// Subscriptions see a transaction a few seconds after it's sent, so only the
+/// most recent hashes need to be kept.
+const LOCAL_TX_HASHES_CAPACITY: usize = 1024;
+
+/// Default maximum number of blocks covered by a single `get_events` request
+pub const DEFAULT_MAX_BLOCK_RANGE: u64 = 100;
+
+/// Identifier of a subscription started with `subscribe_account_txs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(pub u64);
+
+/// Event emitted for each new transaction sent by the connected account
+///
+/// Transactions submitted through this connection are not reported, so this
+/// only surfaces activity from other devices or clients.
+#[derive(Event, Debug, Clone)]
+pub struct AccountTransaction {
+    pub hash: Felt,
+    pub calls: Vec<Call>,
+}
+
+pub(crate) enum SubscriptionItem {
+    AccountTransaction(AccountTransaction),
+}
+
+struct Subscription {
+    id: SubscriptionId,
+    task: JoinHandle<()>,
+    receiver: mpsc::UnboundedReceiver<SubscriptionItem>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // The task would otherwise keep polling the provider in the background
+        self.task.abort();
+    }
+}
+
+/// Active subscriptions of a `StarknetConnection`
+pub(crate) struct SubscriptionState {
+    next_id: u64,
+    subscriptions: Vec<Subscription>,
+    /// Hashes of the latest transactions submitted through this connection
+    local_tx_hashes: HashSet<Felt>,
+    /// The same hashes, oldest first, to forget the oldest once full
+    local_tx_order: VecDeque<Felt>,
+    max_block_range: u64,
+}
+
+impl Default for SubscriptionState {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            subscriptions: Vec::new(),
+            local_tx_hashes: HashSet::new(),
+            local_tx_order: VecDeque::new(),
+            max_block_range: DEFAULT_MAX_BLOCK_RANGE,
+        }
+    }
+}
+
+impl SubscriptionState {
+    /// Returns the number of running subscription tasks
+    pub(crate) fn task_count(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    /// Remember a transaction submitted through this connection
+    ///
+    /// Only the last `LOCAL_TX_HASHES_CAPACITY` hashes are kept.
+    pub(crate) fn record_local_tx(&mut self, hash: Felt) {
+        if !self.local_tx_hashes.insert(hash) {
+            return;
+        }
+        self.local_tx_order.push_back(hash);
+        if self.local_tx_order.len() > LOCAL_TX_HASHES_CAPACITY {
+            if let Some(oldest) = self.local_tx_order.pop_front() {
+                self.local_tx_hashes.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl StarknetConnection {
+    /// Returns the number of active subscriptions
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.subscriptions.len()
+    }
+
+    /// Stop a subscription
+    ///
+    /// Returns false if no subscription with this id is active.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let subscriptions = &mut self.subscriptions.subscriptions;
+        match subscriptions.iter().position(|s| s.id == id) {
+            Some(index) => {
+                subscriptions.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop every active subscription
+    pub fn unsubscribe_all(&mut self) {
+        self.subscriptions.subscriptions.clear();
+    }
+
+    /// Returns the maximum number of blocks covered by a single `get_events` request
+    pub fn max_block_range(&self) -> u64 {
+        self.subscriptions.max_block_range
+    }
+
+    /// Sets the maximum number of blocks covered by a single `get_events` request
+    ///
+    /// Some providers reject event queries spanning too many blocks, which
+    /// happens when a subscription catches up after failed polls. Larger
+    /// ranges are split into requests of at most `max_block_range` blocks.
+    /// Defaults to `DEFAULT_MAX_BLOCK_RANGE`, applies to subscriptions started
+    /// afterwards, and a value of zero is treated as one.
+    pub fn set_max_block_range(&mut self, max_block_range: u64) {
+        self.subscriptions.max_block_range = max_block_range.max(1);
+    }
+}
+
+/// Subscribe to the transactions sent by the connected account
+///
+/// This spawns a background task that polls the provider for the STRK
+/// `Transfer` events sent from the account, starting from the latest block.
+/// Every v3 transaction pays its fee with such a transfer, whereas accounts
+/// such as OpenZeppelin's or katana's don't emit events of their own. Each
+/// new transaction is
+/// fetched, its calls are decoded, and it is delivered as an
+/// `AccountTransaction` event by the `check_sn_subscriptions` system.
+///
+/// Transactions submitted through this connection are filtered out, so the
+/// feed only contains activity from other devices or clients.
+///
+/// # Arguments
+///
+/// * `runtime` - The Tokio runtime resource
+/// * `sn` - The Starknet connection resource
+///
+/// # Returns
+///
+/// * `Some(SubscriptionId)` that can be passed to `StarknetConnection::unsubscribe`
+/// * `None` if there's no active Starknet connection
+///
+/// # Example
+///
+/// ```no_run
+/// fn start_feed(runtime: Res<TokioRuntime>, sn: ResMut<StarknetConnection>) {
+///     subscribe_account_txs(runtime, sn);
+/// }
+///
+/// fn show_feed(mut events: EventReader<AccountTransaction>) {
+///     for tx in events.read() {
+///         println!("{:#x} made {} calls", tx.hash, tx.calls.len());
+///     }
+/// }
+/// ```
+pub fn subscribe_account_txs(
+    runtime: Res<TokioRuntime>,
+    mut sn: ResMut<StarknetConnection>,
+) -> Option<SubscriptionId> {
+    let account = sn.account().cloned()?;
+    let limiter = sn.limiter.clone();
+    let state = &mut sn.subscriptions;
+    let id = SubscriptionId(state.next_id);
+    state.next_id += 1;
+
+    let max_block_range = state.max_block_range;
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let task = runtime
+        .runtime
+        .spawn(async move { poll_account_txs(account, max_block_range, limiter, sender).await });
+    state
+        .subscriptions
+        .push(Subscription { id, task, receiver });
+    Some(id)
+}
+
+/// System that delivers the items received by active subscriptions as events
+///
+/// It is automatically registered by the `BevyDojoPlugin`.
+pub fn check_sn_subscriptions(
+    mut sn: ResMut<StarknetConnection>,
+    mut account_txs: EventWriter<AccountTransaction>,
+) {
+    let state = &mut sn.subscriptions;
+    for subscription in &mut state.subscriptions {
+        while let Ok(item) = subscription.receiver.try_recv() {
+            match item {
+                SubscriptionItem::AccountTransaction(tx) => {
+                    if !state.local_tx_hashes.contains(&tx.hash) {
+                        account_txs.write(tx);
+                    }
+                }
+            }
+        }
+    }
+    state
+        .subscriptions
+        .retain(|subscription| !subscription.task.is_finished());
+}
+
+/// System that stops every active subscription when the app exits
+///
+/// Subscriptions are also stopped when the `StarknetConnection` resource is
+/// dropped. It is automatically registered by the `BevyDojoPlugin`.
+pub fn stop_subscriptions_on_exit(
+    mut exit: EventReader<AppExit>,
+    mut sn: ResMut<StarknetConnection>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+    exit.clear();
+    sn.unsubscribe_all();
+}
+
+async fn poll_account_txs(
+    account: Arc<DojoAccount>,
+    max_block_range: u64,
+    limiter: RequestLimiter,
+    sender: mpsc::UnboundedSender<SubscriptionItem>,
+) {
+    let provider = account.provider();
+    let address = account.address();
+    let mut interval = tokio::time::interval(SUBSCRIPTION_POLL_INTERVAL);
+    let mut next_block = None;
+
+    loop {
+        interval.tick().await;
+        let _permit = limiter.acquire().await;
+        let latest = match provider.block_number().await {
+            Ok(latest) => latest,
+            Err(err) => {
+                warn!("Account transaction subscription failed to poll: {}", err);
+                continue;
+            }
+        };
+        let from = *next_block.get_or_insert(latest + 1);
+        if latest < from {
+            continue;
+        }
+
+        let hashes = match account_tx_hashes(provider, address, from, latest, max_block_range).await
+        {
+            Ok(hashes) => hashes,
+            Err(err) => {
+                warn!("Account transaction subscription failed to poll: {}", err);
+                continue;
+            }
+        };
+        for hash in hashes {
+            let calls = match provider.get_transaction_by_hash(hash).await {
+                Ok(tx) => invoke_calls(&tx, address),
+                Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => None,
+                Err(err) => {
+                    warn!(
+                        "Failed to fetch account transaction {}: {}",
+                        fmt_felt(&hash),
+                        err
+                    );
+                    None
+                }
+            };
+            if let Some(calls) = calls {
+                let item = SubscriptionItem::AccountTransaction(AccountTransaction { hash, calls });
+                if sender.send(item).is_err() {
+                    return;
+                }
+            }
+        }
+        next_block = Some(latest + 1);
+    }
+}
+
+/// Hashes of the transactions that transferred STRK from `address`, in block order
+///
+/// This covers every v3 transaction sent by `address`, since each one pays
+/// its fee with a STRK `Transfer`, keyed by sender and recipient. The blocks
+/// are queried in ranges of at most `max_block_range` blocks.
+async fn account_tx_hashes(
+    provider: &AnyProvider,
+    address: Felt,
+    from: u64,
+    to: u64,
+    max_block_range: u64,
+) -> Result<Vec<Felt>, ProviderError> {
+    let mut seen = HashSet::new();
+    let mut hashes = Vec::new();
+    for (from, to) in block_ranges(from, to, max_block_range) {
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(from)),
+            to_block: Some(BlockId::Number(to)),
+            address: Some(STRK_TOKEN_ADDRESS),
+            keys: Some(vec![vec![selector!("Transfer")], vec![address]]),
+        };
+        let mut continuation_token = None;
+        loop {
+            let page = provider
+                .get_events(filter.clone(), continuation_token, EVENTS_CHUNK_SIZE)
+                .await?;
+            for event in page.events {
+                if seen.insert(event.transaction_hash) {
+                    hashes.push(event.transaction_hash);
+                }
+            }
+            match page.continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+    }
+    Ok(hashes)
+}
+
+/// Split the blocks `from..=to` into inclusive ranges of at most `max_block_range` blocks
+fn block_ranges(from: u64, to: u64, max_block_range: u64) -> impl Iterator<Item = (u64, u64)> {
+    let step = max_block_range.max(1);
+    (from..=to)
+        .step_by(step as usize)
+        .map(move |start| (start, start.saturating_add(step - 1).min(to)))
+}
+
+/// Returns the calls of `tx` if it is an invoke transaction sent by `sender`
+fn invoke_calls(tx: &Transaction, sender: Felt) -> Option<Vec<Call>> {
+    let (sender_address, calldata) = match tx {
+        Transaction::Invoke(InvokeTransaction::V1(tx)) => (tx.sender_address, &tx.calldata),
+        Transaction::Invoke(InvokeTransaction::V3(tx)) => (tx.sender_address, &tx.calldata),
+        _ => return None,
+    };
+    if sender_address != sender {
+        return None;
+    }
+    decode_calls(calldata)
+}
+
+/// Decode the calldata of an account's `__execute__` into the calls it contains
+pub(crate) fn decode_calls(calldata: &[Felt]) -> Option<Vec<Call>> {
+    let (count, mut rest) = calldata.split_first()?;
+    let count = usize::try_from(felt_to_u128(count)?).ok()?;
+    let mut calls = Vec::with_capacity(count.min(rest.len()));
+    for _ in 0..count {
+        let [to, selector, len, tail @ ..] = rest else {
+            return None;
+        };
+        let len = usize::try_from(felt_to_u128(len)?).ok()?;
+        if tail.len() < len {
+            return None;
+        }
+        calls.push(Call {
+            to: *to,
+            selector: *selector,
+            calldata: tail[..len].to_vec(),
+        });
+        rest = &tail[len..];
+    }
+    Some(calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::starknet::http_provider;
+    use serde_json::json;
+
+    fn transfer(tx: u64) -> serde_json::Value {
+        json!({
+            "from_address": format!("{:#x}", STRK_TOKEN_ADDRESS),
+            "keys": felts(&[selector!("Transfer"), ACCOUNT_ADDRESS, Felt::from(0x5e9u64)]),
+            "data": ["0x3e8", "0x0"],
+            "block_hash": "0xb1",
+            "block_number": 1,
+            "transaction_hash": format!("{tx:#x}"),
+        })
+    }
+
+    #[test]
+    fn account_txs_are_found_from_fee_transfers() {
+        let mock = MockRpc::start();
+        // The second transaction also transfers STRK on top of its fee
+        mock.on(
+            "starknet_getEvents",
+            json!({
+                "events": [transfer(0xa), transfer(0xb), transfer(0xb)],
+                "continuation_token": null,
+            }),
+        );
+        let provider = http_provider(&mock.config()).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let hashes = runtime
+            .block_on(account_tx_hashes(&provider, ACCOUNT_ADDRESS, 1, 1, 100))
+            .unwrap();
+        assert_eq!(hashes, vec![Felt::from(0xau64), Felt::from(0xbu64)]);
+
+        let filter = param(&mock.requests("starknet_getEvents")[0], 0, "filter");
+        assert_eq!(
+            filter["address"],
+            json!(format!("{:#x}", STRK_TOKEN_ADDRESS))
+        );
+        assert_eq!(
+            filter["keys"],
+            json!([felts(&[selector!("Transfer")]), felts(&[ACCOUNT_ADDRESS])])
+        );
+    }
+
+    #[test]
+    fn local_txs_are_filtered_out() {
+        let mut app = test_app();
+        collect::<AccountTransaction>(&mut app);
+        let (sender, receiver) = mpsc::unbounded_channel();
+        with_connection(&mut app, |runtime, sn| {
+            sn.subscriptions.record_local_tx(Felt::from(0xbu64));
+            sn.subscriptions.subscriptions.push(Subscription {
+                id: SubscriptionId(0),
+                task: runtime.runtime.spawn(std::future::pending()),
+                receiver,
+            });
+        });
+        for hash in [0xau64, 0xb] {
+            let tx = AccountTransaction {
+                hash: Felt::from(hash),
+                calls: vec![call(hash)],
+            };
+            sender
+                .send(SubscriptionItem::AccountTransaction(tx))
+                .unwrap();
+        }
+        app.update();
+
+        let txs = collected::<AccountTransaction>(&app);
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].hash, Felt::from(0xau64));
+    }
+
+    #[test]
+    fn local_tx_hashes_are_bounded() {
+        let mut state = SubscriptionState::default();
+        let count = LOCAL_TX_HASHES_CAPACITY as u64 + 10;
+        for hash in 0..count {
+            state.record_local_tx(Felt::from(hash));
+        }
+        assert_eq!(state.local_tx_hashes.len(), LOCAL_TX_HASHES_CAPACITY);
+        assert_eq!(state.local_tx_order.len(), LOCAL_TX_HASHES_CAPACITY);
+        assert!(!state.local_tx_hashes.contains(&Felt::ZERO));
+        assert!(state.local_tx_hashes.contains(&Felt::from(count - 1)));
+    }
+
+    #[test]
+    fn subscriptions_are_aborted_on_exit() {
+        let mut app = test_app();
+        let (_sender, receiver) = mpsc::unbounded_channel();
+        let (alive, aborted) = tokio::sync::oneshot::channel::<()>();
+        with_connection(&mut app, |runtime, sn| {
+            let task = runtime.runtime.spawn(async move {
+                let _alive = alive;
+                std::future::pending::<()>().await
+            });
+            sn.subscriptions.subscriptions.push(Subscription {
+                id: SubscriptionId(0),
+                task,
+                receiver,
+            });
+        });
+        app.update();
+        assert_eq!(connection(&app).subscription_count(), 1);
+
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        assert_eq!(connection(&app).subscription_count(), 0);
+        // The task dropped its end of the channel when it was aborted
+        let runtime = &app.world().resource::<TokioRuntime>().runtime;
+        let closed =
+            runtime.block_on(async { tokio::time::timeout(Duration::from_secs(1), aborted).await });
+        assert!(matches!(closed, Ok(Err(_))));
+    }
+
+    #[test]
+    fn block_ranges_are_split_by_max_block_range() {
+        assert_eq!(
+            block_ranges(10, 35, 10).collect::<Vec<_>>(),
+            [(10, 19), (20, 29), (30, 35)]
+        );
+        assert_eq!(block_ranges(5, 5, 10).collect::<Vec<_>>(), [(5, 5)]);
+        assert_eq!(block_ranges(1, 20, 20).collect::<Vec<_>>(), [(1, 20)]);
+        assert_eq!(
+            block_ranges(1, 3, 0).collect::<Vec<_>>(),
+            [(1, 1), (2, 2), (3, 3)]
+        );
+    }
+
+    #[test]
+    fn large_ranges_are_queried_in_chunks() {
+        let mock = MockRpc::start();
+        mock.on(
+            "starknet_getEvents",
+            json!({ "events": [], "continuation_token": null }),
+        );
+        let provider = http_provider(&mock.config()).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime
+            .block_on(account_tx_hashes(&provider, ACCOUNT_ADDRESS, 1, 250, 100))
+            .unwrap();
+        let ranges = mock
+            .requests("starknet_getEvents")
+            .iter()
+            .map(|request| {
+                let filter = param(request, 0, "filter");
+                (
+                    filter["from_block"]["block_number"].as_u64().unwrap(),
+                    filter["to_block"]["block_number"].as_u64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [(1, 100), (101, 200), (201, 250)]);
+    }
+}
@@ -39,3 +39,14 @@ impl Default for TokioRuntime {
         }
     }
 }
+
+impl TokioRuntime {
+    /// Returns the number of tasks alive on the runtime
+    ///
+    /// This counts every task spawned on the runtime, including those spawned
+    /// by the game. Along with `StarknetMetrics::active_tasks`, it helps
+    /// detecting leaked tasks.
+    pub fn alive_tasks(&self) -> usize {
+        self.runtime.metrics().num_alive_tasks()
+    }
+}
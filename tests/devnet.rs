@@ -0,0 +1,73 @@
+//! End-to-end tests against a local `katana` devnet
+//!
+//! Run with `cargo test --features devnet-tests`. Every test is skipped when
+//! `katana` isn't installed.
+
+#![cfg(feature = "devnet-tests")]
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy_dojo::devnet::KatanaDevnet;
+use bevy_dojo::faucet::STRK_TOKEN_ADDRESS;
+use bevy_dojo::prelude::*;
+
+/// Update `app` until `done` returns true, returning false after a minute
+fn update_until(app: &mut App, mut done: impl FnMut(&StarknetConnection) -> bool) -> bool {
+    let started_at = Instant::now();
+    while started_at.elapsed() < Duration::from_secs(60) {
+        app.update();
+        if done(app.world().resource::<StarknetConnection>()) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn transfer_confirms_on_devnet() {
+    let Some(devnet) = KatanaDevnet::spawn() else {
+        eprintln!("katana is not installed, skipping");
+        return;
+    };
+    let config = devnet.config();
+    let account = Felt::from_hex(&config.account_address).unwrap();
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, BevyDojoPlugin))
+        .insert_resource(config);
+    app.finish();
+    app.cleanup();
+
+    app.world_mut()
+        .run_system_once(init_starknet_connection)
+        .unwrap();
+    assert!(update_until(&mut app, |sn| sn.is_connected()));
+
+    // Send 1 fri to itself, which only needs the account to be funded
+    let transfer = Call {
+        to: STRK_TOKEN_ADDRESS,
+        selector: get_selector_from_name("transfer").unwrap(),
+        calldata: vec![account, Felt::ONE, Felt::ZERO],
+    };
+    let outcome = app
+        .world_mut()
+        .resource_scope(|world, mut sn: Mut<StarknetConnection>| {
+            sn.execute(world.resource::<TokioRuntime>(), vec![transfer])
+        });
+    let SubmitOutcome::Queued(tx_id) = outcome else {
+        panic!("transaction not queued: {outcome:?}");
+    };
+
+    assert!(update_until(&mut app, |sn| {
+        sn.recent_txs()
+            .iter()
+            .any(|record| record.tx_id == tx_id && record.status.is_final())
+    }));
+    let sn = app.world().resource::<StarknetConnection>();
+    let record = sn.recent_txs().iter().find(|record| record.tx_id == tx_id);
+    assert_eq!(record.unwrap().status, TxStatus::Confirmed);
+    assert_eq!(sn.metrics().confirmed_txs, 1);
+}